@@ -1,4 +1,7 @@
 pub mod sealing;
+pub mod sector;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 
 use std::fmt::Display;
 
@@ -20,7 +23,7 @@ impl From<JobId> for Uuid {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum JobType {
     PC1,
     PC2,
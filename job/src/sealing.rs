@@ -20,13 +20,13 @@ pub struct Metadata {
 // ****** PC1 **********
 
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PC1 {
     pub input: PC1Input,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PC1Input {
     pub registered_proof: RegisteredSealProof,
     pub storage_provider_id: StorageProviderId,
@@ -37,7 +37,7 @@ pub struct PC1Input {
 
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PC1Output(#[serde_as(as = "Base64<Standard, Padded>")] pub Vec<u8>);
 
 impl AsRef<[u8]> for PC1Output {
@@ -101,14 +101,14 @@ impl SealingJob for PC1 {
 // ****** PC2 **********
 
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PC2 {
     pub input: PC2Input,
 }
 
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PC2Output(#[serde_as(as = "Base64<Standard, Padded>")] pub Vec<u8>);
 
 impl AsRef<[u8]> for PC2Output {
@@ -124,7 +124,7 @@ impl From<Vec<u8>> for PC2Output {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PC2Input {
     pub pc1_output: PC1Output,
     pub sector_id: SectorId,
@@ -181,14 +181,14 @@ impl SealingJob for PC2 {
 // ****** PC1/PC2 **********
 
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PC {
     pub input: PC1Input,
 }
 
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct PCOutput(#[serde_as(as = "Base64<Standard, Padded>")] pub Vec<u8>);
 
 impl AsRef<[u8]> for PCOutput {
@@ -252,14 +252,14 @@ impl SealingJob for PC {
 // ****** C1 **********
 
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct C1 {
     pub input: C1Input,
 }
 
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct C1Output(#[serde_as(as = "Base64<Standard, Padded>")] pub Vec<u8>);
 
 impl AsRef<[u8]> for C1Output {
@@ -275,7 +275,7 @@ impl From<Vec<u8>> for C1Output {
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct C1Input {
     pub pc2_output: PC2Output,
 
@@ -336,13 +336,13 @@ impl SealingJob for C1 {
 // ****** C2 **********
 
 #[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct C2 {
     pub input: C2Input,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct C2Input {
     pub c1_output: C1Output,
     pub storage_provider_id: StorageProviderId,
@@ -352,7 +352,7 @@ pub struct C2Input {
 
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize, Default)]
-#[cfg_attr(feature = "test-utils", derive(PartialEq, Eq, Clone))]
+#[cfg_attr(any(test, feature = "test-utils"), derive(PartialEq, Eq, Clone))]
 pub struct C2Output(#[serde_as(as = "Base64<Standard, Padded>")] pub Vec<u8>);
 
 impl AsRef<[u8]> for C2Output {
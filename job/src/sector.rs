@@ -0,0 +1,233 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::JobType;
+
+/// Where a sector is in its life, from packing deals to proving. Every
+/// service tracking sectors moves them through [`SectorState::next`].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SectorState {
+    Packing,
+    PreCommit1,
+    PreCommit2,
+    /// Pre-commit message is sent, waiting for it to land on chain.
+    PreCommitting,
+    /// Pre-commit is on chain, waiting for the interactive seed.
+    WaitSeed,
+    Commit1,
+    Commit2,
+    /// Proof is done, waiting for Lotus to take the sector over.
+    Handoff,
+    Proving,
+
+    PackingFailed,
+    PreCommit1Failed,
+    PreCommit2Failed,
+    PreCommitFailed,
+    WaitSeedFailed,
+    Commit1Failed,
+    Commit2Failed,
+    HandoffFailed,
+
+    Aborted,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SectorEvent {
+    Packed,
+    PackingFailed,
+    JobDone(JobType),
+    JobFailed(JobType),
+    PreCommitLanded,
+    PreCommitFailed,
+    SeedReady,
+    SeedFailed,
+    HandedOff,
+    HandoffFailed,
+    /// Goes back from a failed state to the step that failed.
+    Retry,
+    Abort,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct SectorTransition {
+    pub from: SectorState,
+    pub event: SectorEvent,
+    pub to: SectorState,
+}
+
+const fn transition(from: SectorState, event: SectorEvent, to: SectorState) -> SectorTransition {
+    SectorTransition { from, event, to }
+}
+
+/// Every legal transition except [`SectorEvent::Abort`], which is legal from
+/// any state that isn't terminal.
+pub const TRANSITIONS: &[SectorTransition] = {
+    use SectorEvent::*;
+    use SectorState as S;
+
+    &[
+        transition(S::Packing, Packed, S::PreCommit1),
+        transition(S::Packing, SectorEvent::PackingFailed, S::PackingFailed),
+        transition(S::PackingFailed, Retry, S::Packing),
+        transition(S::PreCommit1, JobDone(JobType::PC1), S::PreCommit2),
+        // PC runs both pre-commit phases in one job.
+        transition(S::PreCommit1, JobDone(JobType::PC), S::PreCommitting),
+        transition(S::PreCommit1, JobFailed(JobType::PC1), S::PreCommit1Failed),
+        transition(S::PreCommit1, JobFailed(JobType::PC), S::PreCommit1Failed),
+        transition(S::PreCommit1Failed, Retry, S::PreCommit1),
+        transition(S::PreCommit2, JobDone(JobType::PC2), S::PreCommitting),
+        transition(S::PreCommit2, JobFailed(JobType::PC2), S::PreCommit2Failed),
+        transition(S::PreCommit2Failed, Retry, S::PreCommit2),
+        transition(S::PreCommitting, PreCommitLanded, S::WaitSeed),
+        transition(
+            S::PreCommitting,
+            SectorEvent::PreCommitFailed,
+            S::PreCommitFailed,
+        ),
+        transition(S::PreCommitFailed, Retry, S::PreCommitting),
+        transition(S::WaitSeed, SeedReady, S::Commit1),
+        transition(S::WaitSeed, SeedFailed, S::WaitSeedFailed),
+        transition(S::WaitSeedFailed, Retry, S::WaitSeed),
+        transition(S::Commit1, JobDone(JobType::C1), S::Commit2),
+        transition(S::Commit1, JobFailed(JobType::C1), S::Commit1Failed),
+        transition(S::Commit1Failed, Retry, S::Commit1),
+        transition(S::Commit2, JobDone(JobType::C2), S::Handoff),
+        transition(S::Commit2, JobFailed(JobType::C2), S::Commit2Failed),
+        transition(S::Commit2Failed, Retry, S::Commit2),
+        transition(S::Handoff, HandedOff, S::Proving),
+        transition(S::Handoff, SectorEvent::HandoffFailed, S::HandoffFailed),
+        transition(S::HandoffFailed, Retry, S::Handoff),
+    ]
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InvalidTransition {
+    pub from: SectorState,
+    pub event: SectorEvent,
+}
+
+impl Display for InvalidTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} is not allowed in {:?}", self.event, self.from)
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+impl SectorState {
+    pub fn next(self, event: SectorEvent) -> Result<SectorState, InvalidTransition> {
+        if event == SectorEvent::Abort && !self.is_terminal() {
+            return Ok(SectorState::Aborted);
+        }
+
+        TRANSITIONS
+            .iter()
+            .find(|transition| transition.from == self && transition.event == event)
+            .map(|transition| transition.to)
+            .ok_or(InvalidTransition { from: self, event })
+    }
+
+    /// Events that are legal in this state.
+    pub fn events(self) -> Vec<SectorEvent> {
+        let mut events: Vec<_> = TRANSITIONS
+            .iter()
+            .filter(|transition| transition.from == self)
+            .map(|transition| transition.event)
+            .collect();
+        if !self.is_terminal() {
+            events.push(SectorEvent::Abort);
+        }
+
+        events
+    }
+
+    pub fn is_terminal(self) -> bool {
+        matches!(self, SectorState::Proving | SectorState::Aborted)
+    }
+
+    pub fn is_failed(self) -> bool {
+        matches!(
+            self,
+            SectorState::PackingFailed
+                | SectorState::PreCommit1Failed
+                | SectorState::PreCommit2Failed
+                | SectorState::PreCommitFailed
+                | SectorState::WaitSeedFailed
+                | SectorState::Commit1Failed
+                | SectorState::Commit2Failed
+                | SectorState::HandoffFailed
+        )
+    }
+
+    /// Jobs that can be computed while the sector is in this state, `PC`
+    /// stands in for both pre-commit phases.
+    pub fn job_types(self) -> &'static [JobType] {
+        match self {
+            SectorState::PreCommit1 => &[JobType::PC1, JobType::PC],
+            SectorState::PreCommit2 => &[JobType::PC2],
+            SectorState::Commit1 => &[JobType::C1],
+            SectorState::Commit2 => &[JobType::C2],
+            _ => &[],
+        }
+    }
+}
+
+impl Display for SectorState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sector_lifecycle() {
+        let events = [
+            SectorEvent::Packed,
+            SectorEvent::JobDone(JobType::PC1),
+            SectorEvent::JobFailed(JobType::PC2),
+            SectorEvent::Retry,
+            SectorEvent::JobDone(JobType::PC2),
+            SectorEvent::PreCommitFailed,
+            SectorEvent::Retry,
+            SectorEvent::PreCommitLanded,
+            SectorEvent::SeedFailed,
+            SectorEvent::Retry,
+            SectorEvent::SeedReady,
+            SectorEvent::JobDone(JobType::C1),
+            SectorEvent::JobDone(JobType::C2),
+            SectorEvent::HandedOff,
+        ];
+        let state = events
+            .into_iter()
+            .try_fold(SectorState::Packing, SectorState::next)
+            .unwrap();
+        assert_eq!(state, SectorState::Proving);
+
+        assert_eq!(
+            SectorState::Proving.next(SectorEvent::Abort),
+            Err(InvalidTransition {
+                from: SectorState::Proving,
+                event: SectorEvent::Abort
+            })
+        );
+        assert!(SectorState::WaitSeed
+            .next(SectorEvent::JobDone(JobType::C1))
+            .is_err());
+        assert_eq!(
+            SectorState::PreCommit1.next(SectorEvent::JobDone(JobType::PC)),
+            Ok(SectorState::PreCommitting)
+        );
+        assert!(SectorState::PreCommit1.job_types().contains(&JobType::PC));
+        assert!(SectorState::WaitSeedFailed.is_failed());
+
+        let json = serde_json::to_string(&SectorState::Commit1Failed).unwrap();
+        assert_eq!(json, "\"Commit1Failed\"");
+        let event: SectorEvent = serde_json::from_str(r#"{"JobDone":"PC1"}"#).unwrap();
+        assert_eq!(event, SectorEvent::JobDone(JobType::PC1));
+    }
+}
@@ -0,0 +1,18 @@
+//! Fixtures shared by the tests of crates depending on `job`.
+
+use filecoin_spec::{ChainEpoch, RegisteredSealProof, SectorId, StorageProviderId, Ticket};
+
+use crate::sealing::{PC1Input, PC1};
+
+/// PC1 job for `sector_id` of storage provider 1000, with a zero ticket.
+pub fn pc1(sector_id: u64) -> PC1 {
+    PC1 {
+        input: PC1Input {
+            registered_proof: RegisteredSealProof::StackedDrg2KiBV1,
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(sector_id),
+            ticket: Ticket([0; 32]),
+            ticket_epoch: ChainEpoch(0),
+        },
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fmt::Display, time::Duration};
+
+use hyper::{http, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Error body returned by the job manager for non-success responses.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ErrorResponse {
+    pub code: Option<String>,
+    pub message: String,
+    pub request_id: Option<String>,
+}
+
+pub const ALREADY_SUBMITTED_CODE: &str = "ALREADY_SUBMITTED";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub request_id: Option<String>,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.status)?;
+        if let Some(code) = &self.code {
+            write!(f, " {}", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request id: {})", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(http::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Turns an unexpected response into a typed error, falling back to the raw
+/// body as the message when the server didn't send an `ErrorResponse`.
+pub(crate) async fn from_response(response: reqwest::Response) -> Error {
+    let status = response.status();
+    let retry_after = retry_after(&response);
+    let header_request_id = response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(err) => return err.into(),
+    };
+
+    let (code, message, body_request_id) = match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(resp) => (resp.code, resp.message, resp.request_id),
+        Err(_) => (None, body, None),
+    };
+
+    let error = ApiError {
+        status,
+        request_id: header_request_id.or(body_request_id),
+        code,
+        message,
+    };
+
+    match status {
+        StatusCode::NOT_FOUND => Error::NotFound(error),
+        StatusCode::CONFLICT if error.code.as_deref() == Some(ALREADY_SUBMITTED_CODE) => {
+            Error::AlreadySubmitted(error)
+        }
+        StatusCode::CONFLICT => Error::Conflict(error),
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => Error::Validation(error),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Unauthorized(error),
+        StatusCode::TOO_MANY_REQUESTS => Error::RateLimited { error, retry_after },
+        _ => Error::Server(error),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_from_response_already_submitted() {
+        let response = http::Response::builder()
+            .status(StatusCode::CONFLICT)
+            .header(REQUEST_ID_HEADER, "req-1")
+            .body(r#"{"code":"ALREADY_SUBMITTED","message":"output exists","request_id":null}"#)
+            .unwrap();
+
+        match from_response(response.into()).await {
+            Error::AlreadySubmitted(error) => {
+                assert_eq!(error.status, StatusCode::CONFLICT);
+                assert_eq!(error.request_id.as_deref(), Some("req-1"));
+                assert_eq!(error.message, "output exists");
+            }
+            err => panic!("Unexpected error {:?}", err),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::Error;
+
+/// Source of bearer tokens that may expire, implementations are expected to
+/// cache the token and refresh it when needed.
+#[async_trait]
+pub trait TokenProvider: Send + Sync {
+    async fn token(&self) -> Result<String, Error>;
+
+    /// Called when the server rejected the last token with a 401, returns a
+    /// new one. Providers that cache tokens should drop the cached one here.
+    async fn refresh(&self) -> Result<String, Error> {
+        self.token().await
+    }
+}
+
+#[derive(Clone, Default)]
+pub enum Auth {
+    #[default]
+    None,
+    Bearer(String),
+    Provider(Arc<dyn TokenProvider>),
+}
+
+impl Auth {
+    pub(crate) async fn bearer_token(&self) -> Result<Option<String>, Error> {
+        match self {
+            Auth::None => Ok(None),
+            Auth::Bearer(token) => Ok(Some(token.clone())),
+            Auth::Provider(provider) => provider.token().await.map(Some),
+        }
+    }
+
+    /// New token after a 401, `None` when there is no way to get one.
+    pub(crate) async fn refreshed_token(&self) -> Result<Option<String>, Error> {
+        match self {
+            Auth::None | Auth::Bearer(_) => Ok(None),
+            Auth::Provider(provider) => provider.refresh().await.map(Some),
+        }
+    }
+}
+
+pub(crate) fn with_bearer(
+    request: reqwest::RequestBuilder,
+    token: &Option<String>,
+) -> reqwest::RequestBuilder {
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
@@ -0,0 +1,215 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::{Certificate, Identity, Proxy};
+
+use crate::{
+    auth::{Auth, TokenProvider},
+    metrics::{MetricsObserver, NoopMetricsObserver},
+    retry::RetryConfig,
+    Error, SealingJobManagerHttpClient,
+};
+
+pub struct SealingJobManagerHttpClientBuilder {
+    uri: String,
+    auth: Auth,
+    identity: Option<Identity>,
+    root_certificates: Vec<Certificate>,
+    built_in_root_certificates: bool,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+    retry: RetryConfig,
+    metrics: Arc<dyn MetricsObserver>,
+}
+
+impl SealingJobManagerHttpClientBuilder {
+    pub fn new(uri: String) -> Self {
+        Self {
+            uri,
+            auth: Auth::None,
+            identity: None,
+            root_certificates: vec![],
+            built_in_root_certificates: true,
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+            retry: RetryConfig::default(),
+            metrics: Arc::new(NoopMetricsObserver),
+        }
+    }
+
+    pub fn bearer_token(mut self, token: String) -> Self {
+        self.auth = Auth::Bearer(token);
+        self
+    }
+
+    pub fn token_provider(mut self, provider: Arc<dyn TokenProvider>) -> Self {
+        self.auth = Auth::Provider(provider);
+        self
+    }
+
+    /// mTLS client certificate, `cert` holds the PEM certificate chain and
+    /// `key` the PEM PKCS#8 private key.
+    pub fn client_identity_pem(mut self, cert: &[u8], key: &[u8]) -> Result<Self, Error> {
+        self.identity = Some(Identity::from_pkcs8_pem(cert, key)?);
+        Ok(self)
+    }
+
+    pub fn add_root_certificate_pem(mut self, pem: &[u8]) -> Result<Self, Error> {
+        self.root_certificates.push(Certificate::from_pem(pem)?);
+        Ok(self)
+    }
+
+    /// Only trust the certificates added with `add_root_certificate_pem`.
+    pub fn disable_built_in_root_certificates(mut self) -> Self {
+        self.built_in_root_certificates = false;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn proxy(mut self, uri: &str) -> Result<Self, Error> {
+        self.proxy = Some(Proxy::all(uri)?);
+        Ok(self)
+    }
+
+    pub fn retry(mut self, config: RetryConfig) -> Self {
+        self.retry = config;
+        self
+    }
+
+    /// Reports request latency, payload sizes, errors and job counts, e.g. to a
+    /// [`crate::metrics::PrometheusObserver`].
+    pub fn metrics(mut self, observer: Arc<dyn MetricsObserver>) -> Self {
+        self.metrics = observer;
+        self
+    }
+
+    pub fn build(self) -> Result<SealingJobManagerHttpClient, Error> {
+        let mut builder =
+            reqwest::Client::builder().tls_built_in_root_certs(self.built_in_root_certificates);
+
+        for certificate in self.root_certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+        if let Some(identity) = self.identity {
+            builder = builder.identity(identity);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(SealingJobManagerHttpClient::from_parts(
+            self.uri,
+            builder.build()?,
+            self.auth,
+            self.retry,
+            self.metrics,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use axum::{
+        http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+
+    use super::*;
+    use crate::{worker::ListWorkersResponse, SealingJobManagerClient, LIST_WORKERS_URL};
+
+    struct RotatingToken;
+
+    #[async_trait]
+    impl TokenProvider for RotatingToken {
+        async fn token(&self) -> Result<String, Error> {
+            Ok("expired".to_string())
+        }
+
+        async fn refresh(&self) -> Result<String, Error> {
+            Ok("fresh".to_string())
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_settings() {
+        let builder = SealingJobManagerHttpClientBuilder::new("http://localhost".to_string());
+        assert!(builder
+            .add_root_certificate_pem(b"not a certificate")
+            .is_err());
+
+        let builder = SealingJobManagerHttpClientBuilder::new("http://localhost".to_string());
+        assert!(builder
+            .client_identity_pem(b"not a certificate", b"not a key")
+            .is_err());
+
+        let client = SealingJobManagerHttpClientBuilder::new("http://localhost".to_string())
+            .timeout(Duration::from_secs(5))
+            .connect_timeout(Duration::from_secs(1))
+            .proxy("http://localhost:3128")
+            .unwrap()
+            .disable_built_in_root_certificates()
+            .build();
+        assert!(client.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_token_refreshed_on_unauthorized() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let router = Router::new().route(
+            LIST_WORKERS_URL,
+            get(move |headers: HeaderMap| async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                match headers.get(AUTHORIZATION) {
+                    Some(token) if token == "Bearer fresh" => {
+                        Json(ListWorkersResponse { workers: vec![] }).into_response()
+                    }
+                    _ => StatusCode::UNAUTHORIZED.into_response(),
+                }
+            }),
+        );
+        let server =
+            axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(router.into_make_service());
+        let uri = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let client = SealingJobManagerHttpClientBuilder::new(uri.clone())
+            .token_provider(Arc::new(RotatingToken))
+            .build()
+            .unwrap();
+        assert!(client.list_workers().await.unwrap().is_empty());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // A static token can't be refreshed, the 401 is returned as is.
+        let client = SealingJobManagerHttpClientBuilder::new(uri)
+            .bearer_token("expired".to_string())
+            .build()
+            .unwrap();
+        assert!(matches!(
+            client.list_workers().await,
+            Err(Error::Unauthorized(_))
+        ));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use job::{
+    sealing::{C1Output, C2Output, PC1Output, PC2Output, PCOutput, C1, C2, PC, PC1, PC2},
+    JobType,
+};
+
+use crate::{
+    failure::JobFailure, Error, JobEnvelope, JobHttp, JobOutputHttp, SealingJobManagerClient,
+};
+
+fn unsupported(job_type: JobType) -> JobFailure {
+    // Not retryable, every attempt would end up on the same mismatch.
+    JobFailure::invalid_input(format!(
+        "{} jobs are not supported by this worker",
+        job_type
+    ))
+    .with_code("UNSUPPORTED_JOB_TYPE")
+}
+
+/// Runs jobs of whichever types a worker supports. Implementors override the
+/// handlers for the types listed in `job_types`.
+#[async_trait]
+pub trait JobDispatcher: Send + Sync {
+    fn job_types(&self) -> Vec<JobType>;
+
+    async fn pc1(&self, _job: PC1) -> Result<PC1Output, JobFailure> {
+        Err(unsupported(JobType::PC1))
+    }
+
+    async fn pc2(&self, _job: PC2) -> Result<PC2Output, JobFailure> {
+        Err(unsupported(JobType::PC2))
+    }
+
+    async fn c1(&self, _job: C1) -> Result<C1Output, JobFailure> {
+        Err(unsupported(JobType::C1))
+    }
+
+    async fn c2(&self, _job: C2) -> Result<C2Output, JobFailure> {
+        Err(unsupported(JobType::C2))
+    }
+
+    async fn pc(&self, _job: PC) -> Result<PCOutput, JobFailure> {
+        Err(unsupported(JobType::PC))
+    }
+
+    async fn dispatch(&self, job: JobHttp) -> Result<JobOutputHttp, JobFailure> {
+        match job {
+            JobHttp::PC1(job) => self.pc1(job).await.map(Into::into),
+            JobHttp::PC2(job) => self.pc2(job).await.map(Into::into),
+            JobHttp::C1(job) => self.c1(job).await.map(Into::into),
+            JobHttp::C2(job) => self.c2(job).await.map(Into::into),
+            JobHttp::PC(job) => self.pc(job).await.map(Into::into),
+        }
+    }
+}
+
+/// What [`process_jobs`] did with the jobs it claimed.
+#[derive(Debug, Default)]
+pub struct ProcessedJobs {
+    /// Number of jobs run, whether or not their report went through.
+    pub processed: usize,
+    /// Outputs and failures the job manager didn't accept.
+    pub errors: Vec<Error>,
+}
+
+/// Claims up to `count` jobs of the dispatcher's types, runs them one after
+/// another and reports each output or failure. A failed report doesn't stop
+/// the remaining jobs, its error is collected instead.
+pub async fn process_jobs<Client, Dispatcher>(
+    client: &Client,
+    dispatcher: &Dispatcher,
+    count: usize,
+) -> Result<ProcessedJobs, Error>
+where
+    Client: SealingJobManagerClient,
+    Dispatcher: JobDispatcher,
+{
+    let jobs = client
+        .request_any_jobs(&dispatcher.job_types(), count)
+        .await?;
+    let mut processed = ProcessedJobs {
+        processed: jobs.len(),
+        errors: vec![],
+    };
+
+    for JobEnvelope { job, .. } in jobs {
+        let storage_provider_id = job.storage_provider_id();
+        let sector_id = job.sector_id();
+        let job_type = job.job_type();
+
+        let reported = match dispatcher.dispatch(job).await {
+            Ok(output) => {
+                client
+                    .submit_any_job_output(storage_provider_id, sector_id, output)
+                    .await
+            }
+            Err(failure) => {
+                tracing::error!(
+                    "{} job failed for storage_provider_id: {}, sector_id: {}: {}",
+                    job_type,
+                    storage_provider_id.0,
+                    sector_id.0,
+                    failure
+                );
+                client
+                    .fail_any_job(storage_provider_id, sector_id, job_type, failure)
+                    .await
+            }
+        };
+
+        if let Err(err) = reported {
+            tracing::error!(
+                "Failed to report {} job for storage_provider_id: {}, sector_id: {}: {}",
+                job_type,
+                storage_provider_id.0,
+                sector_id.0,
+                err
+            );
+            processed.errors.push(err);
+        }
+    }
+
+    Ok(processed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{failure::FailureCategory, MockSealingJobManagerClient};
+    use filecoin_spec::{RegisteredSealProof, SectorId, StorageProviderId};
+    use job::{sealing::PC2Input, test_utils::pc1};
+
+    struct Pc1Only;
+
+    #[async_trait]
+    impl JobDispatcher for Pc1Only {
+        fn job_types(&self) -> Vec<JobType> {
+            vec![JobType::PC1]
+        }
+
+        async fn pc1(&self, job: PC1) -> Result<PC1Output, JobFailure> {
+            Ok(PC1Output(vec![job.input.sector_id.0 as u8]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_jobs_dispatches_by_type() {
+        let mut client = MockSealingJobManagerClient::new();
+        client
+            .expect_request_any_jobs()
+            .withf(|job_types, count| job_types == [JobType::PC1] && *count == 2)
+            .times(1)
+            .returning(|_, _| {
+                Ok(vec![
+                    JobHttp::PC1(pc1(1)).into(),
+                    JobHttp::PC2(PC2 {
+                        input: PC2Input {
+                            pc1_output: PC1Output(vec![]),
+                            sector_id: SectorId(2),
+                            storage_provider_id: StorageProviderId(1000),
+                            registered_proof: RegisteredSealProof::StackedDrg2KiBV1,
+                        },
+                    })
+                    .into(),
+                ])
+            });
+        client
+            .expect_submit_any_job_output()
+            .withf(|_, sector_id, output| {
+                *sector_id == SectorId(1)
+                    && matches!(output, JobOutputHttp::PC1(PC1Output(out)) if out == &[1])
+            })
+            .times(1)
+            .returning(|_, _, _| Err(Error::CircuitOpen));
+        client
+            .expect_fail_any_job()
+            .withf(|_, sector_id, job_type, failure| {
+                *sector_id == SectorId(2)
+                    && *job_type == JobType::PC2
+                    && failure.category == FailureCategory::InvalidInput
+                    && !failure.retryable
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+
+        // The rejected output doesn't stop the second job from being reported.
+        let processed = process_jobs(&client, &Pc1Only, 2).await.unwrap();
+        assert_eq!(processed.processed, 2);
+        assert!(matches!(processed.errors[..], [Error::CircuitOpen]));
+    }
+}
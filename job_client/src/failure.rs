@@ -0,0 +1,130 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureCategory {
+    /// Network hiccups, restarts and other errors expected to go away.
+    Transient,
+    /// The job input can never produce an output, e.g. a bad ticket.
+    InvalidInput,
+    /// The worker ran out of memory, disk or GPU.
+    ResourceExhausted,
+    ProofVerificationFailed,
+    /// The job outlived its ticket or deal.
+    Expired,
+    Cancelled,
+}
+
+impl FailureCategory {
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            FailureCategory::Transient | FailureCategory::ResourceExhausted
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JobFailure {
+    pub category: FailureCategory,
+    pub retryable: bool,
+    /// Machine readable code, e.g. `OOM` or `TICKET_EXPIRED`.
+    pub code: Option<String>,
+    pub details: String,
+}
+
+impl JobFailure {
+    /// Creates a failure whose `retryable` flag follows the category.
+    pub fn new(category: FailureCategory, details: impl Into<String>) -> Self {
+        Self {
+            category,
+            retryable: category.is_retryable(),
+            code: None,
+            details: details.into(),
+        }
+    }
+
+    pub fn transient(details: impl Into<String>) -> Self {
+        Self::new(FailureCategory::Transient, details)
+    }
+
+    pub fn invalid_input(details: impl Into<String>) -> Self {
+        Self::new(FailureCategory::InvalidInput, details)
+    }
+
+    pub fn resource_exhausted(details: impl Into<String>) -> Self {
+        Self::new(FailureCategory::ResourceExhausted, details)
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+}
+
+impl Display for JobFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.category)?;
+        if let Some(code) = &self.code {
+            write!(f, "({})", code)?;
+        }
+        write!(f, ": {}", self.details)
+    }
+}
+
+impl std::error::Error for JobFailure {}
+
+/// Peers from before structured failures send the error as a plain message.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FailureOrMessage {
+    Failure(JobFailure),
+    Message(String),
+}
+
+impl From<FailureOrMessage> for JobFailure {
+    fn from(failure: FailureOrMessage) -> Self {
+        match failure {
+            FailureOrMessage::Failure(failure) => failure,
+            FailureOrMessage::Message(message) => JobFailure::transient(message),
+        }
+    }
+}
+
+/// Deserializes a [`JobFailure`], mapping a legacy string error to a transient
+/// failure. For `#[serde(deserialize_with)]`.
+pub fn deserialize_compat<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<JobFailure, D::Error> {
+    FailureOrMessage::deserialize(deserializer).map(Into::into)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FailJob;
+
+    #[test]
+    fn test_legacy_string_failure() {
+        let legacy: FailJob = serde_json::from_str(
+            r#"{"storage_provider_id":1000,"sector_id":1,"job_type":"PC1","error":"out of memory"}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.error, JobFailure::transient("out of memory"));
+
+        let failure = JobFailure::invalid_input("bad ticket").with_code("TICKET");
+        let json = serde_json::to_string(&FailJob {
+            error: failure.clone(),
+            ..legacy
+        })
+        .unwrap();
+        let current: FailJob = serde_json::from_str(&json).unwrap();
+        assert_eq!(current.error, failure);
+    }
+}
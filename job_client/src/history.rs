@@ -0,0 +1,175 @@
+use std::io::Write;
+
+use filecoin_spec::{SectorId, StorageProviderId};
+use job::{JobId, JobType};
+use serde::{Deserialize, Serialize};
+
+use crate::{failure::JobFailure, worker::WorkerId, Error, JobProgress};
+
+/// Identifies a job either by its sector and type or by its id.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobRef {
+    Sector {
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    },
+    Id(JobId),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobHistoryEventKind {
+    Added,
+    Claimed {
+        worker_id: Option<WorkerId>,
+    },
+    Progress {
+        progress: JobProgress,
+    },
+    OutputSubmitted {
+        digest: String,
+    },
+    Failed {
+        error: JobFailure,
+    },
+    Cancelled {
+        reason: Option<String>,
+    },
+    /// The worker gave the job back unfinished, the attempt doesn't count.
+    Released,
+    /// The worker stopped renewing its claim.
+    LeaseExpired,
+    Requeued,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JobHistoryEvent {
+    pub job_id: JobId,
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub job_type: JobType,
+    /// Milliseconds since the unix epoch.
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub kind: JobHistoryEventKind,
+}
+
+/// A single claim of a job by a worker and how it ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobAttempt {
+    pub worker_id: Option<WorkerId>,
+    pub claimed_at_ms: u64,
+    pub finished_at_ms: Option<u64>,
+    pub outcome: Option<JobHistoryEventKind>,
+}
+
+impl JobAttempt {
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.finished_at_ms
+            .map(|finished| finished.saturating_sub(self.claimed_at_ms))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JobHistory {
+    /// Ordered from oldest to newest.
+    pub events: Vec<JobHistoryEvent>,
+}
+
+impl JobHistory {
+    pub fn attempts(&self) -> Vec<JobAttempt> {
+        let mut attempts: Vec<JobAttempt> = vec![];
+
+        for event in &self.events {
+            match &event.kind {
+                JobHistoryEventKind::Claimed { worker_id } => attempts.push(JobAttempt {
+                    worker_id: *worker_id,
+                    claimed_at_ms: event.timestamp_ms,
+                    finished_at_ms: None,
+                    outcome: None,
+                }),
+                JobHistoryEventKind::OutputSubmitted { .. }
+                | JobHistoryEventKind::Failed { .. }
+                | JobHistoryEventKind::Cancelled { .. }
+                | JobHistoryEventKind::Released
+                | JobHistoryEventKind::LeaseExpired
+                | JobHistoryEventKind::Requeued => {
+                    if let Some(attempt) = attempts
+                        .last_mut()
+                        .filter(|attempt| attempt.outcome.is_none())
+                    {
+                        attempt.finished_at_ms = Some(event.timestamp_ms);
+                        attempt.outcome = Some(event.kind.clone());
+                    }
+                }
+                JobHistoryEventKind::Added | JobHistoryEventKind::Progress { .. } => {}
+            }
+        }
+
+        attempts
+    }
+
+    /// Writes one JSON encoded event per line.
+    pub fn write_json_lines<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        for event in &self.events {
+            serde_json::to_writer(&mut writer, event)?;
+            writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn to_json_lines(&self) -> Result<String, Error> {
+        let mut buffer = vec![];
+        self.write_json_lines(&mut buffer)?;
+
+        Ok(String::from_utf8(buffer).expect("serde_json produces valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(timestamp_ms: u64, kind: JobHistoryEventKind) -> JobHistoryEvent {
+        JobHistoryEvent {
+            job_id: JobId::default(),
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(1),
+            job_type: JobType::PC1,
+            timestamp_ms,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_job_history_attempts() {
+        let history = JobHistory {
+            events: vec![
+                event(0, JobHistoryEventKind::Added),
+                event(10, JobHistoryEventKind::Claimed { worker_id: None }),
+                event(
+                    50,
+                    JobHistoryEventKind::Failed {
+                        error: JobFailure::resource_exhausted("out of memory"),
+                    },
+                ),
+                event(60, JobHistoryEventKind::Claimed { worker_id: None }),
+            ],
+        };
+
+        let attempts = history.attempts();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].duration_ms(), Some(40));
+        assert_eq!(attempts[1].duration_ms(), None);
+
+        let lines = history.to_json_lines().unwrap();
+        assert_eq!(lines.lines().count(), 4);
+        assert!(lines
+            .lines()
+            .nth(2)
+            .unwrap()
+            .contains("\"kind\":\"failed\""));
+    }
+}
@@ -0,0 +1,1542 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use filecoin_spec::{RegisteredSealProof, SectorId, StorageProviderId, Ticket};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use hyper::StatusCode;
+use job::{sealing::SealingJob, JobId, JobType};
+use rand::Rng;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::{
+    api_error::{ApiError, ALREADY_SUBMITTED_CODE},
+    failure::JobFailure,
+    history::{JobHistory, JobHistoryEvent, JobHistoryEventKind, JobRef},
+    query::{JobQuery, Page, PageCursor, SortField, SortOrder, TimeRange},
+    retry::{self, Operation},
+    sector::{self, GeneratedTicket, SectorPaths},
+    worker::{RegisteredWorker, WorkerId, WorkerInfo},
+    BatchResult, Error, EventCursor, Filter, JobEnvelope, JobHttp, JobKey, JobOutput,
+    JobOutputHttp, JobProgress, JobState, JobStateEvent, JobStatus, SealingJobManagerClient,
+    SubmitSealingJobOutput,
+};
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// How long a claim lasts without progress reports or other requests from
+/// the worker holding it.
+pub const DEFAULT_CLAIM_LEASE: Duration = Duration::from_secs(5 * 60);
+
+pub const LEASE_EXPIRED_CODE: &str = "LEASE_EXPIRED";
+
+pub const INJECTED_FAULT_CODE: &str = "INJECTED_FAULT";
+
+/// Faults applied to calls made against an [`InMemorySealingJobManager`].
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    /// Added before every affected call.
+    pub delay: Option<Duration>,
+    /// Probability in `0.0..=1.0` that an affected call fails with a 503.
+    pub error_rate: f64,
+    /// Probability that a claimed job is lost on its way to the worker. It
+    /// stays claimed until its lease runs out, see
+    /// [`InMemorySealingJobManager::with_lease`].
+    pub drop_rate: f64,
+    /// Operations the delay and errors apply to, all of them when empty.
+    pub operations: HashSet<Operation>,
+}
+
+impl Faults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn error_rate(mut self, error_rate: f64) -> Self {
+        self.error_rate = error_rate;
+        self
+    }
+
+    pub fn drop_rate(mut self, drop_rate: f64) -> Self {
+        self.drop_rate = drop_rate;
+        self
+    }
+
+    pub fn only(mut self, operation: Operation) -> Self {
+        self.operations.insert(operation);
+        self
+    }
+
+    fn applies_to(&self, operation: Operation) -> bool {
+        self.operations.is_empty() || self.operations.contains(&operation)
+    }
+}
+
+type Key = (StorageProviderId, SectorId, JobType);
+
+struct Claim {
+    worker_id: Option<WorkerId>,
+    /// Milliseconds since the unix epoch.
+    expires_ms: u64,
+}
+
+struct Entry {
+    id: JobId,
+    seq: u64,
+    registered_proof: RegisteredSealProof,
+    // Jobs and outputs aren't `Clone`, they are kept in their wire format.
+    job: serde_json::Value,
+    output: Option<serde_json::Value>,
+    state: JobState,
+    claim: Option<Claim>,
+    attempts: u32,
+    progress: Option<JobProgress>,
+    error: Option<JobFailure>,
+    created_ms: u64,
+    updated_ms: u64,
+    history: Vec<JobHistoryEvent>,
+}
+
+impl Entry {
+    fn status(&self) -> JobStatus {
+        JobStatus {
+            state: self.state.clone(),
+            progress: self.progress.clone(),
+        }
+    }
+
+    fn job(&self) -> Result<JobHttp, Error> {
+        Ok(serde_json::from_value(self.job.clone())?)
+    }
+
+    fn envelope(&self) -> Result<JobEnvelope, Error> {
+        Ok(serde_json::from_value(self.job.clone())?)
+    }
+
+    fn output(&self) -> Result<Option<Result<JobOutputHttp, JobFailure>>, Error> {
+        match (&self.state, &self.output, &self.error) {
+            (JobState::Done, Some(output), _) => {
+                Ok(Some(Ok(serde_json::from_value(output.clone())?)))
+            }
+            (JobState::Failed, _, Some(error)) => Ok(Some(Err(error.clone()))),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    jobs: HashMap<Key, Entry>,
+    queue: VecDeque<Key>,
+    next_seq: u64,
+    events: Vec<JobStateEvent>,
+    workers: HashMap<WorkerId, RegisteredWorker>,
+    faults: Faults,
+    fail_next: HashMap<Operation, usize>,
+}
+
+impl State {
+    fn entry(&mut self, key: Key) -> Result<&mut Entry, Error> {
+        self.jobs.get_mut(&key).ok_or_else(|| not_found(key))
+    }
+
+    fn record(&mut self, key: Key, kind: JobHistoryEventKind) {
+        let now = now_ms();
+        if let Some(entry) = self.jobs.get_mut(&key) {
+            entry.updated_ms = now;
+            entry.history.push(JobHistoryEvent {
+                job_id: entry.id,
+                storage_provider_id: key.0,
+                sector_id: key.1,
+                job_type: key.2,
+                timestamp_ms: now,
+                kind,
+            });
+        }
+    }
+
+    fn transition(&mut self, key: Key, new_state: JobState) {
+        let Some(entry) = self.jobs.get_mut(&key) else {
+            return;
+        };
+        let old_state = std::mem::replace(&mut entry.state, new_state.clone());
+        let error = entry.error.clone();
+
+        let cursor = EventCursor(self.events.len() as u64 + 1);
+        self.events.push(JobStateEvent {
+            cursor,
+            storage_provider_id: key.0,
+            sector_id: key.1,
+            job_type: key.2,
+            old_state: Some(old_state),
+            new_state,
+            timestamp_ms: now_ms(),
+            error,
+        });
+    }
+}
+
+/// Job manager kept entirely in memory, for tests of code built on top of
+/// [`SealingJobManagerClient`]. Clones share the same jobs.
+#[derive(Clone)]
+pub struct InMemorySealingJobManager {
+    state: Arc<Mutex<State>>,
+    events: Arc<watch::Sender<u64>>,
+    worker_id: Arc<RwLock<Option<WorkerId>>>,
+    max_attempts: u32,
+    lease: Duration,
+    sector_root: PathBuf,
+}
+
+impl Default for InMemorySealingJobManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemorySealingJobManager {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::default())),
+            events: Arc::new(watch::channel(0).0),
+            worker_id: Arc::new(RwLock::new(None)),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            lease: DEFAULT_CLAIM_LEASE,
+            sector_root: PathBuf::from("/var/lib/sectors"),
+        }
+    }
+
+    /// Number of claims after which a retryable failure fails the job for good.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// How long claims last without being renewed, see [`DEFAULT_CLAIM_LEASE`].
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Root of the lotus style layout returned by `get_sector_paths`.
+    pub fn with_sector_root(mut self, sector_root: impl Into<PathBuf>) -> Self {
+        self.sector_root = sector_root.into();
+        self
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        self.lock().faults = faults;
+    }
+
+    /// Fails the next `count` calls of `operation`, regardless of `Faults`.
+    pub fn fail_next(&self, operation: Operation, count: usize) {
+        self.lock().fail_next.insert(operation, count);
+    }
+
+    /// Returns every claimed but unfinished job to the queue, as if its lease
+    /// expired. Returns the number of requeued jobs.
+    pub fn requeue_claimed(&self) -> usize {
+        let mut state = self.lock();
+        let mut claimed: Vec<(u64, Key)> = state
+            .jobs
+            .iter()
+            .filter(|(_, entry)| entry.claim.is_some() && entry.state == JobState::Pending)
+            .map(|(key, entry)| (entry.seq, *key))
+            .collect();
+        claimed.sort_by_key(|(seq, _)| *seq);
+
+        for (_, key) in &claimed {
+            if let Some(entry) = state.jobs.get_mut(key) {
+                entry.claim = None;
+            }
+            state.queue.push_back(*key);
+            state.record(*key, JobHistoryEventKind::Requeued);
+        }
+
+        claimed.len()
+    }
+
+    /// Requeues jobs whose claim ran out, or fails them for good once they
+    /// are out of attempts.
+    fn expire_leases(&self, state: &mut State) {
+        let now = now_ms();
+        let mut expired: Vec<(u64, Key)> = state
+            .jobs
+            .iter()
+            .filter(|(_, entry)| {
+                entry.state == JobState::Pending
+                    && entry
+                        .claim
+                        .as_ref()
+                        .is_some_and(|claim| claim.expires_ms <= now)
+            })
+            .map(|(key, entry)| (entry.seq, *key))
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        expired.sort_by_key(|(seq, _)| *seq);
+
+        for (_, key) in expired {
+            let Some(entry) = state.jobs.get_mut(&key) else {
+                continue;
+            };
+            entry.claim = None;
+            let requeue = entry.attempts < self.max_attempts;
+            if !requeue {
+                entry.error = Some(
+                    JobFailure::transient("claim lease expired").with_code(LEASE_EXPIRED_CODE),
+                );
+            }
+            tracing::debug!("Claim of {} job for sector {} expired", key.2, key.1 .0);
+            state.record(key, JobHistoryEventKind::LeaseExpired);
+
+            if requeue {
+                state.queue.push_back(key);
+                state.record(key, JobHistoryEventKind::Requeued);
+            } else {
+                state.transition(key, JobState::Failed);
+            }
+        }
+        self.publish(state);
+    }
+
+    fn lease_expiry(&self) -> u64 {
+        now_ms().saturating_add(self.lease.as_millis() as u64)
+    }
+
+    pub fn worker_id(&self) -> Option<WorkerId> {
+        *self.worker_id.read().unwrap()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn publish(&self, state: &State) {
+        self.events.send_replace(state.events.len() as u64);
+    }
+
+    async fn inject(&self, operation: Operation) -> Result<(), Error> {
+        let (delay, fail) = {
+            let mut state = self.lock();
+            let forced = match state.fail_next.get_mut(&operation) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            };
+
+            let faults = &state.faults;
+            if faults.applies_to(operation) {
+                let random = faults.error_rate > 0.0
+                    && rand::thread_rng().gen_bool(faults.error_rate.min(1.0));
+                (faults.delay, forced || random)
+            } else {
+                (None, forced)
+            }
+        };
+
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        if fail {
+            tracing::debug!("Injecting {:?} failure", operation);
+            return Err(Error::Server(api_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                Some(INJECTED_FAULT_CODE),
+                format!("injected {:?} failure", operation),
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn add(&self, job: JobHttp) -> Result<(), Error> {
+        let mut job = JobEnvelope::from(job);
+        crate::trace::inject(&mut job);
+        self.insert(job)
+    }
+
+    async fn claim(&self, job_types: &[JobType], count: usize) -> Result<Vec<JobEnvelope>, Error> {
+        self.inject(Operation::RequestJobs).await?;
+
+        // Like the job server, a registered worker only gets jobs it has the
+        // proofs for.
+        let worker_id = self.worker_id();
+        let registered_proofs = worker_id
+            .and_then(|worker_id| self.worker(worker_id))
+            .map(|worker| worker.info.registered_proofs)
+            .unwrap_or_default();
+        let jobs = self.claim_jobs(job_types, &registered_proofs, count, worker_id)?;
+        if jobs.is_empty() {
+            return Err(Error::NotEnoughJobs(count));
+        }
+
+        let drop_rate = self.lock().faults.drop_rate;
+        Ok(jobs
+            .into_iter()
+            .filter(|job| {
+                let dropped = drop_rate > 0.0 && rand::thread_rng().gen_bool(drop_rate.min(1.0));
+                if dropped {
+                    tracing::debug!(
+                        "Dropping {} job for sector {}",
+                        job.job.job_type(),
+                        job.job.sector_id().0
+                    );
+                }
+                !dropped
+            })
+            .collect())
+    }
+}
+
+/// Job store operations without fault injection, with the job type known
+/// only at runtime. The reference job server keeps its in-memory jobs here.
+impl InMemorySealingJobManager {
+    /// Adding a job that already exists is a no-op.
+    pub fn insert(&self, job: JobEnvelope) -> Result<(), Error> {
+        let key = key(&job.job);
+        let mut state = self.lock();
+        if let Some(entry) = state.jobs.get(&key) {
+            // Adding a job again is a no-op, unless it ended without an
+            // output. Then it has to be retried instead.
+            return match entry.state {
+                JobState::Pending | JobState::Done => {
+                    tracing::debug!("{} job already exists for sector {}", key.2, key.1 .0);
+                    Ok(())
+                }
+                JobState::Failed | JobState::Cancelled => Err(conflict(key, &entry.state)),
+            };
+        }
+
+        let now = now_ms();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.jobs.insert(
+            key,
+            Entry {
+                id: JobId::new(),
+                seq,
+                registered_proof: job.job.registered_proof(),
+                job: serde_json::to_value(&job)?,
+                output: None,
+                state: JobState::Pending,
+                claim: None,
+                attempts: 0,
+                progress: None,
+                error: None,
+                created_ms: now,
+                updated_ms: now,
+                history: vec![],
+            },
+        );
+        state.queue.push_back(key);
+        state.record(key, JobHistoryEventKind::Added);
+
+        Ok(())
+    }
+
+    /// Claims up to `count` pending jobs of `job_types` for `worker_id`,
+    /// oldest first. Any proof matches when `registered_proofs` is empty.
+    /// Claims that weren't renewed in time are requeued first.
+    pub fn claim_jobs(
+        &self,
+        job_types: &[JobType],
+        registered_proofs: &[RegisteredSealProof],
+        count: usize,
+        worker_id: Option<WorkerId>,
+    ) -> Result<Vec<JobEnvelope>, Error> {
+        let mut state = self.lock();
+        self.expire_leases(&mut state);
+
+        let mut claimed = vec![];
+        let mut remaining = VecDeque::new();
+        while let Some(key) = state.queue.pop_front() {
+            let proof_matches = registered_proofs.is_empty()
+                || state
+                    .jobs
+                    .get(&key)
+                    .is_some_and(|entry| registered_proofs.contains(&entry.registered_proof));
+            if claimed.len() < count && job_types.contains(&key.2) && proof_matches {
+                claimed.push(key);
+            } else {
+                remaining.push_back(key);
+            }
+        }
+        state.queue = remaining;
+
+        let expires_ms = self.lease_expiry();
+        let mut jobs = vec![];
+        for key in claimed {
+            let entry = state.entry(key)?;
+            entry.claim = Some(Claim {
+                worker_id,
+                expires_ms,
+            });
+            entry.attempts += 1;
+            jobs.push(entry.envelope()?);
+            state.record(key, JobHistoryEventKind::Claimed { worker_id });
+        }
+
+        Ok(jobs)
+    }
+
+    pub fn query(&self, job_type: JobType, query: &JobQuery) -> Result<Page<JobHttp>, Error> {
+        let state = self.lock();
+        let mut matching: Vec<(&Key, &Entry)> = state
+            .jobs
+            .iter()
+            .filter(|(key, entry)| key.2 == job_type && matches_query(query, key, entry))
+            .collect();
+        matching.sort_by_key(|(_, entry)| entry.seq);
+        if let Some(sort) = query.sort {
+            matching.sort_by(|(a_key, a), (b_key, b)| {
+                let ordering = match sort.field {
+                    SortField::StorageProviderId => a_key.0 .0.cmp(&b_key.0 .0),
+                    SortField::SectorId => a_key.1 .0.cmp(&b_key.1 .0),
+                    SortField::CreatedAt => a.created_ms.cmp(&b.created_ms),
+                    SortField::UpdatedAt => a.updated_ms.cmp(&b.updated_ms),
+                };
+                match sort.order {
+                    SortOrder::Asc => ordering,
+                    SortOrder::Desc => ordering.reverse(),
+                }
+            });
+        }
+
+        // Unsorted queries are in seq order, which doubles as the sort key.
+        let sort_key = |key: &Key, entry: &Entry| match query.sort.map(|sort| sort.field) {
+            Some(SortField::StorageProviderId) => key.0 .0,
+            Some(SortField::SectorId) => key.1 .0,
+            Some(SortField::CreatedAt) => entry.created_ms,
+            Some(SortField::UpdatedAt) => entry.updated_ms,
+            None => entry.seq,
+        };
+        if let Some(cursor) = &query.cursor {
+            let (last_key, last_seq) = cursor.position().ok_or_else(|| {
+                Error::Validation(api_error(
+                    StatusCode::BAD_REQUEST,
+                    None,
+                    format!("invalid cursor {}", cursor.0),
+                ))
+            })?;
+            let descending = query.sort.is_some_and(|sort| sort.order == SortOrder::Desc);
+            matching.retain(|(key, entry)| {
+                let sort_key = sort_key(key, entry);
+                match sort_key.cmp(&last_key) {
+                    Ordering::Equal => entry.seq > last_seq,
+                    ordering => (ordering == Ordering::Less) == descending,
+                }
+            });
+        }
+
+        let page_size = query.page_size.max(1) as usize;
+        let next_cursor = matching
+            .get(page_size)
+            .and(matching.get(page_size - 1))
+            .map(|(key, entry)| PageCursor::after(sort_key(key, entry), entry.seq));
+
+        Ok(Page {
+            items: matching
+                .iter()
+                .take(page_size)
+                .map(|(_, entry)| entry.job())
+                .collect::<Result<_, _>>()?,
+            next_cursor,
+        })
+    }
+
+    pub fn job(&self, key: JobKey, job_type: JobType) -> Result<Option<JobHttp>, Error> {
+        self.lock()
+            .jobs
+            .get(&slot(key, job_type))
+            .map(Entry::job)
+            .transpose()
+    }
+
+    pub fn status(&self, key: JobKey, job_type: JobType) -> Option<JobStatus> {
+        self.lock()
+            .jobs
+            .get(&slot(key, job_type))
+            .map(Entry::status)
+    }
+
+    pub fn submit(&self, request: SubmitSealingJobOutput) -> Result<(), Error> {
+        let key = (
+            request.storage_provider_id,
+            request.sector_id,
+            request.job.job_type(),
+        );
+        let output = serde_json::to_value(&request.job)?;
+        let digest = retry::digest_key(&output.to_string());
+
+        let mut state = self.lock();
+        let entry = state.entry(key)?;
+        match entry.state {
+            JobState::Pending => {}
+            JobState::Done => {
+                return Err(Error::AlreadySubmitted(api_error(
+                    StatusCode::CONFLICT,
+                    Some(ALREADY_SUBMITTED_CODE),
+                    format!(
+                        "{} output already submitted for storage_provider_id: {}, sector_id: {}",
+                        key.2, key.0 .0, key.1 .0
+                    ),
+                )))
+            }
+            JobState::Failed | JobState::Cancelled => {
+                return Err(conflict(key, &entry.state));
+            }
+        }
+
+        entry.output = Some(output);
+        entry.claim = None;
+        state.queue.retain(|queued| *queued != key);
+        state.record(key, JobHistoryEventKind::OutputSubmitted { digest });
+        state.transition(key, JobState::Done);
+        self.publish(&state);
+
+        Ok(())
+    }
+
+    /// `None` until the job is done or has failed for good.
+    pub fn output(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+    ) -> Result<Option<Result<JobOutputHttp, JobFailure>>, Error> {
+        match self.lock().jobs.get(&slot(key, job_type)) {
+            Some(entry) => entry.output(),
+            None => Ok(None),
+        }
+    }
+
+    /// Requeues the job when the failure is retryable and attempts are left.
+    /// With a `worker_id`, only that worker's claim can be failed.
+    pub fn fail(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+        error: JobFailure,
+        worker_id: Option<WorkerId>,
+    ) -> Result<(), Error> {
+        let key = slot(key, job_type);
+        let mut state = self.lock();
+        let entry = state.entry(key)?;
+        if entry.state != JobState::Pending {
+            return Err(conflict(key, &entry.state));
+        }
+        if let Some(worker_id) = worker_id {
+            if entry.claim.as_ref().and_then(|claim| claim.worker_id) != Some(worker_id) {
+                return Err(not_claimed(key, worker_id));
+            }
+        }
+
+        let requeue = error.retryable && entry.attempts < self.max_attempts;
+        entry.claim = None;
+        entry.error = Some(error.clone());
+        state.queue.retain(|queued| *queued != key);
+        state.record(key, JobHistoryEventKind::Failed { error });
+
+        if requeue {
+            state.queue.push_back(key);
+            state.record(key, JobHistoryEventKind::Requeued);
+        } else {
+            state.transition(key, JobState::Failed);
+            self.publish(&state);
+        }
+
+        Ok(())
+    }
+
+    /// Requeues a job that failed for good with its attempts reset, a no-op
+    /// for pending jobs.
+    pub fn retry(&self, key: JobKey, job_type: JobType) -> Result<(), Error> {
+        let key = slot(key, job_type);
+        let mut state = self.lock();
+        let entry = state.entry(key)?;
+        match entry.state {
+            JobState::Failed => {}
+            JobState::Pending => return Ok(()),
+            JobState::Done | JobState::Cancelled => return Err(conflict(key, &entry.state)),
+        }
+
+        entry.attempts = 0;
+        entry.claim = None;
+        entry.error = None;
+        entry.progress = None;
+        state.queue.push_back(key);
+        state.record(key, JobHistoryEventKind::Requeued);
+        state.transition(key, JobState::Pending);
+        self.publish(&state);
+
+        Ok(())
+    }
+
+    /// Pending jobs claimed by `worker_id`, in claim order.
+    pub fn claimed_jobs(&self, worker_id: WorkerId) -> Result<Vec<JobEnvelope>, Error> {
+        let state = self.lock();
+        let mut claimed: Vec<&Entry> = state
+            .jobs
+            .values()
+            .filter(|entry| {
+                entry.state == JobState::Pending
+                    && entry
+                        .claim
+                        .as_ref()
+                        .is_some_and(|claim| claim.worker_id == Some(worker_id))
+            })
+            .collect();
+        claimed.sort_by_key(|entry| entry.seq);
+
+        claimed.into_iter().map(Entry::envelope).collect()
+    }
+
+    /// Requeues a claimed job without counting the attempt, a no-op when
+    /// the job isn't claimed. With a `worker_id`, another worker's claim is
+    /// a conflict.
+    pub fn release(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+        worker_id: Option<WorkerId>,
+    ) -> Result<(), Error> {
+        let key = slot(key, job_type);
+        let mut state = self.lock();
+        let entry = state.entry(key)?;
+        if entry.state != JobState::Pending {
+            return Ok(());
+        }
+        match (&entry.claim, worker_id) {
+            (None, _) => return Ok(()),
+            (Some(claim), Some(worker_id)) if claim.worker_id != Some(worker_id) => {
+                return Err(not_claimed(key, worker_id));
+            }
+            _ => entry.claim = None,
+        }
+
+        entry.attempts = entry.attempts.saturating_sub(1);
+        state.record(key, JobHistoryEventKind::Released);
+        // It keeps its place at the head of the queue.
+        state.queue.push_front(key);
+
+        Ok(())
+    }
+
+    pub fn progress(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+        progress: JobProgress,
+    ) -> Result<(), Error> {
+        let key = slot(key, job_type);
+        let expires_ms = self.lease_expiry();
+        let mut state = self.lock();
+        let entry = state.entry(key)?;
+        entry.progress = Some(progress.clone());
+        if let Some(claim) = &mut entry.claim {
+            claim.expires_ms = expires_ms;
+        }
+        state.record(key, JobHistoryEventKind::Progress { progress });
+
+        Ok(())
+    }
+
+    /// Cancelling a cancelled job is a no-op.
+    pub fn cancel(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+        reason: Option<String>,
+    ) -> Result<(), Error> {
+        let key = slot(key, job_type);
+        let mut state = self.lock();
+        let entry = state.entry(key)?;
+        match entry.state {
+            JobState::Pending => {}
+            JobState::Cancelled => return Ok(()),
+            JobState::Done | JobState::Failed => return Err(conflict(key, &entry.state)),
+        }
+
+        entry.claim = None;
+        state.queue.retain(|queued| *queued != key);
+        state.record(key, JobHistoryEventKind::Cancelled { reason });
+        state.transition(key, JobState::Cancelled);
+        self.publish(&state);
+
+        Ok(())
+    }
+
+    pub fn history(&self, job: JobRef) -> Option<JobHistory> {
+        let state = self.lock();
+        let entry = match job {
+            JobRef::Sector {
+                storage_provider_id,
+                sector_id,
+                job_type,
+            } => state.jobs.get(&(storage_provider_id, sector_id, job_type)),
+            JobRef::Id(id) => state.jobs.values().find(|entry| entry.id == id),
+        };
+
+        entry.map(|entry| JobHistory {
+            events: entry.history.clone(),
+        })
+    }
+
+    /// State changes of `job_type` jobs matching `filter`, after `cursor`.
+    pub fn events_after(
+        &self,
+        job_type: JobType,
+        filter: &Filter,
+        cursor: EventCursor,
+    ) -> Vec<JobStateEvent> {
+        let state = self.lock();
+        state
+            .events
+            .iter()
+            .skip(cursor.0 as usize)
+            .filter(|event| matches_event(&state, job_type, filter, event))
+            .cloned()
+            .collect()
+    }
+
+    /// Cursor of the most recent event.
+    pub fn latest_cursor(&self) -> EventCursor {
+        EventCursor(self.lock().events.len() as u64)
+    }
+
+    /// Registers a worker other than this client, e.g. one served by a job
+    /// server built on this store.
+    pub fn add_worker(&self, info: WorkerInfo) -> WorkerId {
+        let now = now_ms();
+        let worker_id = WorkerId(Uuid::new_v4());
+        self.lock().workers.insert(
+            worker_id,
+            RegisteredWorker {
+                worker_id,
+                info,
+                registered_at_ms: now,
+                last_seen_ms: now,
+            },
+        );
+
+        worker_id
+    }
+
+    /// Records that the worker is alive and renews its claims.
+    pub fn worker_seen(&self, worker_id: WorkerId) {
+        let expires_ms = self.lease_expiry();
+        let mut state = self.lock();
+        if let Some(worker) = state.workers.get_mut(&worker_id) {
+            worker.last_seen_ms = now_ms();
+        }
+        for claim in state
+            .jobs
+            .values_mut()
+            .filter_map(|entry| entry.claim.as_mut())
+        {
+            if claim.worker_id == Some(worker_id) {
+                claim.expires_ms = expires_ms;
+            }
+        }
+    }
+
+    /// Registered workers, oldest first.
+    pub fn worker(&self, worker_id: WorkerId) -> Option<RegisteredWorker> {
+        self.lock().workers.get(&worker_id).cloned()
+    }
+
+    pub fn workers(&self) -> Vec<RegisteredWorker> {
+        let mut workers: Vec<_> = self.lock().workers.values().cloned().collect();
+        workers.sort_by_key(|worker| worker.registered_at_ms);
+        workers
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn key(job: &JobHttp) -> Key {
+    (job.storage_provider_id(), job.sector_id(), job.job_type())
+}
+
+fn slot(key: JobKey, job_type: JobType) -> Key {
+    (key.storage_provider_id, key.sector_id, job_type)
+}
+
+fn api_error(status: StatusCode, code: Option<&str>, message: String) -> ApiError {
+    ApiError {
+        status,
+        request_id: None,
+        code: code.map(str::to_string),
+        message,
+    }
+}
+
+fn not_found(key: Key) -> Error {
+    Error::NotFound(api_error(
+        StatusCode::NOT_FOUND,
+        None,
+        format!(
+            "{} job not found for storage_provider_id: {}, sector_id: {}",
+            key.2, key.0 .0, key.1 .0
+        ),
+    ))
+}
+
+fn conflict(key: Key, state: &JobState) -> Error {
+    Error::Conflict(api_error(
+        StatusCode::CONFLICT,
+        None,
+        format!("{} job for sector {} is {:?}", key.2, key.1 .0, state),
+    ))
+}
+
+fn not_claimed(key: Key, worker_id: WorkerId) -> Error {
+    Error::Conflict(api_error(
+        StatusCode::CONFLICT,
+        None,
+        format!(
+            "{} job for sector {} isn't claimed by worker {}",
+            key.2, key.1 .0, worker_id.0
+        ),
+    ))
+}
+
+fn in_range(range: &Option<TimeRange>, value: u64) -> bool {
+    range.is_none_or(|range| {
+        range.from_ms.is_none_or(|from| value >= from) && range.to_ms.is_none_or(|to| value <= to)
+    })
+}
+
+fn matches_event(state: &State, job_type: JobType, filter: &Filter, event: &JobStateEvent) -> bool {
+    let key = (event.storage_provider_id, event.sector_id, event.job_type);
+    key.2 == job_type
+        && filter.storage_provider_id.is_none_or(|id| id == key.0)
+        && filter.sector_id.is_none_or(|id| id == key.1)
+        && filter
+            .state
+            .as_ref()
+            .is_none_or(|state| *state == event.new_state)
+        && filter.registered_proof.is_none_or(|proof| {
+            state
+                .jobs
+                .get(&key)
+                .is_some_and(|entry| entry.registered_proof == proof)
+        })
+}
+
+fn matches_query(query: &JobQuery, key: &Key, entry: &Entry) -> bool {
+    (query.storage_provider_ids.is_empty() || query.storage_provider_ids.contains(&key.0))
+        && (query.sector_ids.is_empty() || query.sector_ids.contains(&key.1))
+        && query
+            .sector_range
+            .is_none_or(|range| key.1 .0 >= range.start.0 && key.1 .0 <= range.end.0)
+        && (query.states.is_empty() || query.states.contains(&entry.state))
+        && (query.registered_proofs.is_empty()
+            || query.registered_proofs.contains(&entry.registered_proof))
+        && in_range(&query.created, entry.created_ms)
+        && in_range(&query.updated, entry.updated_ms)
+}
+
+#[async_trait]
+impl SealingJobManagerClient for InMemorySealingJobManager {
+    async fn add_job<SealingJobT: SealingJob + Into<JobHttp> + 'static>(
+        &self,
+        job: SealingJobT,
+    ) -> Result<(), Error> {
+        self.inject(Operation::AddJob).await?;
+        self.add(job.into())
+    }
+
+    async fn request_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
+        &self,
+        count: usize,
+    ) -> Result<Vec<SealingJobT>, Error> {
+        self.claim(&[SealingJobT::job_type()], count)
+            .await?
+            .into_iter()
+            .map(|envelope| SealingJobT::try_from(envelope.job))
+            .collect()
+    }
+
+    async fn request_any_jobs(
+        &self,
+        job_types: &[JobType],
+        count: usize,
+    ) -> Result<Vec<JobEnvelope>, Error> {
+        self.claim(job_types, count).await
+    }
+
+    async fn filter_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
+        &self,
+        filter: Filter,
+    ) -> Result<Vec<SealingJobT>, Error> {
+        self.inject(Operation::FilterJobs).await?;
+
+        let query = match filter.limit {
+            Some(_) => JobQuery::from(filter),
+            None => JobQuery::from(filter).page_size(u32::MAX),
+        };
+        self.query(SealingJobT::job_type(), &query)?
+            .items
+            .into_iter()
+            .map(SealingJobT::try_from)
+            .collect()
+    }
+
+    async fn query_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
+        &self,
+        query: JobQuery,
+    ) -> Result<Page<SealingJobT>, Error> {
+        self.inject(Operation::QueryJobs).await?;
+
+        let page = self.query(SealingJobT::job_type(), &query)?;
+        Ok(Page {
+            items: page
+                .items
+                .into_iter()
+                .map(SealingJobT::try_from)
+                .collect::<Result<_, _>>()?,
+            next_cursor: page.next_cursor,
+        })
+    }
+
+    async fn submit_job_output<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        output: SealingJobT::Output,
+    ) -> Result<(), Error>
+    where
+        JobOutputHttp: From<SealingJobT::Output>,
+    {
+        self.submit_any_job_output(storage_provider_id, sector_id, output.into())
+            .await
+    }
+
+    async fn get_job_output<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Option<JobOutput<SealingJobT>>, Error>
+    where
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>,
+    {
+        self.inject(Operation::GetJobOutput).await?;
+
+        let key = (storage_provider_id, sector_id).into();
+        Ok(match self.output(key, SealingJobT::job_type())? {
+            Some(Ok(output)) => Some(JobOutput(Ok(SealingJobT::Output::try_from(output)?))),
+            Some(Err(error)) => Some(JobOutput(Err(error))),
+            None => None,
+        })
+    }
+
+    async fn get_job_input<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Option<SealingJobT>, Error>
+    where
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>,
+    {
+        self.get_any_job_input(storage_provider_id, sector_id, SealingJobT::job_type())
+            .await?
+            .map(SealingJobT::try_from)
+            .transpose()
+    }
+
+    async fn get_any_job_input(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<Option<JobHttp>, Error> {
+        self.inject(Operation::GetJobInput).await?;
+        self.job((storage_provider_id, sector_id).into(), job_type)
+    }
+
+    async fn submit_any_job_output(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        output: JobOutputHttp,
+    ) -> Result<(), Error> {
+        self.inject(Operation::SubmitOutput).await?;
+        self.submit(SubmitSealingJobOutput {
+            storage_provider_id,
+            sector_id,
+            job: output,
+        })
+    }
+
+    async fn fail_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        error: JobFailure,
+    ) -> Result<(), Error> {
+        self.fail_any_job(
+            storage_provider_id,
+            sector_id,
+            SealingJobT::job_type(),
+            error,
+        )
+        .await
+    }
+
+    async fn fail_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+        error: JobFailure,
+    ) -> Result<(), Error> {
+        self.inject(Operation::FailJob).await?;
+        self.fail(
+            (storage_provider_id, sector_id).into(),
+            job_type,
+            error,
+            self.worker_id(),
+        )
+    }
+
+    async fn release_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<(), Error> {
+        self.release_any_job(storage_provider_id, sector_id, SealingJobT::job_type())
+            .await
+    }
+
+    async fn release_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<(), Error> {
+        self.inject(Operation::ReleaseJob).await?;
+        self.release(
+            (storage_provider_id, sector_id).into(),
+            job_type,
+            self.worker_id(),
+        )
+    }
+
+    async fn retry_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<(), Error> {
+        self.retry_any_job(storage_provider_id, sector_id, SealingJobT::job_type())
+            .await
+    }
+
+    async fn retry_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<(), Error> {
+        self.inject(Operation::RetryJob).await?;
+        self.retry((storage_provider_id, sector_id).into(), job_type)
+    }
+
+    async fn get_job_state<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Option<JobStatus>, Error> {
+        self.inject(Operation::GetJobState).await?;
+        // Like the job server, a worker polling its job renews its claims.
+        if let Some(worker_id) = self.worker_id() {
+            self.worker_seen(worker_id);
+        }
+        Ok(self.status(
+            (storage_provider_id, sector_id).into(),
+            SealingJobT::job_type(),
+        ))
+    }
+
+    async fn report_progress<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        progress: JobProgress,
+    ) -> Result<(), Error> {
+        self.inject(Operation::ReportProgress).await?;
+        self.progress(
+            (storage_provider_id, sector_id).into(),
+            SealingJobT::job_type(),
+            progress,
+        )
+    }
+
+    async fn cancel_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        reason: Option<String>,
+    ) -> Result<(), Error> {
+        self.inject(Operation::CancelJob).await?;
+        self.cancel(
+            (storage_provider_id, sector_id).into(),
+            SealingJobT::job_type(),
+            reason,
+        )
+    }
+
+    async fn get_job_history(&self, job: JobRef) -> Result<Option<JobHistory>, Error> {
+        self.inject(Operation::GetJobHistory).await?;
+        Ok(self.history(job))
+    }
+
+    async fn register_worker(&self, info: WorkerInfo) -> Result<WorkerId, Error> {
+        self.inject(Operation::RegisterWorker).await?;
+
+        let worker_id = self.add_worker(info);
+        *self.worker_id.write().unwrap() = Some(worker_id);
+
+        Ok(worker_id)
+    }
+
+    async fn list_workers(&self) -> Result<Vec<RegisteredWorker>, Error> {
+        self.inject(Operation::ListWorkers).await?;
+        Ok(self.workers())
+    }
+
+    async fn generate_ticket(
+        &self,
+        _storage_provider_id: StorageProviderId,
+    ) -> Result<GeneratedTicket, Error> {
+        self.inject(Operation::GenerateTicket).await?;
+
+        Ok(GeneratedTicket {
+            ticket: Ticket(rand::thread_rng().gen()),
+            ticket_epoch: sector::mainnet_epoch(now_ms()),
+        })
+    }
+
+    async fn get_sector_paths(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<SectorPaths, Error> {
+        self.inject(Operation::GetSectorPaths).await?;
+
+        Ok(SectorPaths::under(
+            &self.sector_root,
+            storage_provider_id,
+            sector_id,
+        ))
+    }
+
+    async fn add_jobs(&self, jobs: Vec<JobHttp>) -> Result<BatchResult<()>, Error> {
+        self.inject(Operation::AddJobs).await?;
+
+        Ok(jobs
+            .into_iter()
+            .map(|job| self.add(job).map_err(|err| err.to_string()))
+            .collect())
+    }
+
+    async fn submit_job_outputs(
+        &self,
+        outputs: Vec<SubmitSealingJobOutput>,
+    ) -> Result<BatchResult<()>, Error> {
+        self.inject(Operation::SubmitOutputs).await?;
+
+        Ok(outputs
+            .into_iter()
+            .map(|output| self.submit(output).map_err(|err| err.to_string()))
+            .collect())
+    }
+
+    async fn get_job_states<SealingJobT: SealingJob + 'static>(
+        &self,
+        jobs: Vec<(StorageProviderId, SectorId)>,
+    ) -> Result<BatchResult<Option<JobStatus>>, Error> {
+        self.inject(Operation::GetJobStates).await?;
+
+        Ok(jobs
+            .into_iter()
+            .map(|(storage_provider_id, sector_id)| {
+                Ok(self.status(
+                    (storage_provider_id, sector_id).into(),
+                    SealingJobT::job_type(),
+                ))
+            })
+            .collect())
+    }
+
+    async fn get_job_outputs<SealingJobT: SealingJob + 'static>(
+        &self,
+        jobs: Vec<(StorageProviderId, SectorId)>,
+    ) -> Result<BatchResult<Option<JobOutput<SealingJobT>>>, Error>
+    where
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>,
+    {
+        self.inject(Operation::GetJobOutputs).await?;
+
+        Ok(jobs
+            .into_iter()
+            .map(|(storage_provider_id, sector_id)| {
+                let key = (storage_provider_id, sector_id).into();
+                let output = match self.output(key, SealingJobT::job_type()) {
+                    Ok(Some(Ok(output))) => Some(JobOutput(Ok(SealingJobT::Output::try_from(
+                        output,
+                    )
+                    .map_err(|err| err.to_string())?))),
+                    Ok(Some(Err(error))) => Some(JobOutput(Err(error))),
+                    Ok(None) => None,
+                    Err(err) => return Err(err.to_string()),
+                };
+                Ok(output)
+            })
+            .collect())
+    }
+
+    fn watch_jobs<SealingJobT: SealingJob + 'static>(
+        &self,
+        filter: Filter,
+        cursor: Option<EventCursor>,
+    ) -> BoxStream<'static, Result<JobStateEvent, Error>> {
+        let state = self.state.clone();
+        let receiver = self.events.subscribe();
+        let next = cursor.map_or_else(|| self.lock().events.len() as u64, |cursor| cursor.0);
+
+        stream::unfold((next, receiver), move |(mut next, mut receiver)| {
+            let state = state.clone();
+            let filter = filter.clone();
+            async move {
+                loop {
+                    {
+                        let state = state.lock().unwrap();
+                        let event = state.events.iter().skip(next as usize).find(|event| {
+                            matches_event(&state, SealingJobT::job_type(), &filter, event)
+                        });
+                        if let Some(event) = event {
+                            next = event.cursor.0;
+                            return Some((Ok(event.clone()), (next, receiver)));
+                        }
+                        next = state.events.len() as u64;
+                    }
+
+                    if receiver.changed().await.is_err() {
+                        return None;
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use job::{
+        sealing::{PC1Output, PC1},
+        test_utils::pc1,
+    };
+
+    #[tokio::test]
+    async fn test_in_memory_job_lifecycle() {
+        let manager = InMemorySealingJobManager::new().with_max_attempts(2);
+        manager.add_job(pc1(1)).await.unwrap();
+        manager.add_job(pc1(2)).await.unwrap();
+
+        let mut events = manager.watch_jobs::<PC1>(Filter::default(), None);
+
+        let jobs: Vec<PC1> = manager.request_jobs(2).await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert!(matches!(
+            manager.request_jobs::<PC1>(1).await,
+            Err(Error::NotEnoughJobs(1))
+        ));
+
+        manager
+            .submit_job_output::<PC1>(StorageProviderId(1000), SectorId(1), PC1Output(vec![1]))
+            .await
+            .unwrap();
+        assert!(matches!(
+            manager
+                .submit_job_output::<PC1>(StorageProviderId(1000), SectorId(1), PC1Output(vec![1]))
+                .await,
+            Err(Error::AlreadySubmitted(_))
+        ));
+
+        // A retryable failure requeues the job until it runs out of attempts.
+        let failure = JobFailure::transient("worker restarted");
+        manager
+            .fail_job::<PC1>(StorageProviderId(1000), SectorId(2), failure.clone())
+            .await
+            .unwrap();
+        let jobs: Vec<PC1> = manager.request_jobs(1).await.unwrap();
+        assert_eq!(jobs[0].input.sector_id, SectorId(2));
+        manager
+            .fail_job::<PC1>(StorageProviderId(1000), SectorId(2), failure.clone())
+            .await
+            .unwrap();
+
+        let output = manager
+            .get_job_output::<PC1>(StorageProviderId(1000), SectorId(2))
+            .await
+            .unwrap();
+        assert!(matches!(output, Some(JobOutput(Err(err))) if err == failure));
+
+        // Adding a done job again is a no-op, a failed one has to be retried.
+        manager.add_job(pc1(1)).await.unwrap();
+        assert!(matches!(
+            manager.add_job(pc1(2)).await,
+            Err(Error::Conflict(_))
+        ));
+
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.sector_id, SectorId(1));
+        assert_eq!(event.new_state, JobState::Done);
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.sector_id, SectorId(2));
+        assert_eq!(event.new_state, JobState::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_fault_injection() {
+        let manager = InMemorySealingJobManager::new();
+        manager.add_job(pc1(1)).await.unwrap();
+
+        manager.fail_next(Operation::RequestJobs, 1);
+        assert!(matches!(
+            manager.request_jobs::<PC1>(1).await,
+            Err(Error::Server(err)) if err.code.as_deref() == Some(INJECTED_FAULT_CODE)
+        ));
+
+        manager.set_faults(Faults::new().drop_rate(1.0));
+        assert!(manager.request_jobs::<PC1>(1).await.unwrap().is_empty());
+
+        manager.set_faults(Faults::new());
+        assert_eq!(manager.requeue_claimed(), 1);
+        assert_eq!(manager.request_jobs::<PC1>(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_release_and_lease_expiry() {
+        let manager = InMemorySealingJobManager::new()
+            .with_max_attempts(1)
+            .with_lease(Duration::ZERO);
+        manager.add_job(pc1(1)).await.unwrap();
+
+        // Released jobs don't use up an attempt.
+        assert_eq!(manager.request_jobs::<PC1>(1).await.unwrap().len(), 1);
+        manager
+            .release_job::<PC1>(StorageProviderId(1000), SectorId(1))
+            .await
+            .unwrap();
+        assert_eq!(manager.request_jobs::<PC1>(1).await.unwrap().len(), 1);
+
+        // The next claim finds the lease expired and the job out of attempts.
+        assert!(matches!(
+            manager.request_jobs::<PC1>(1).await,
+            Err(Error::NotEnoughJobs(1))
+        ));
+        let output = manager
+            .get_job_output::<PC1>(StorageProviderId(1000), SectorId(1))
+            .await
+            .unwrap();
+        assert!(matches!(
+            output,
+            Some(JobOutput(Err(err))) if err.code.as_deref() == Some(LEASE_EXPIRED_CODE)
+        ));
+
+        // Only the worker holding the claim can release or fail it.
+        let manager = InMemorySealingJobManager::new().with_lease(Duration::ZERO);
+        manager.add_job(pc1(2)).await.unwrap();
+        let key = (StorageProviderId(1000), SectorId(2)).into();
+        let (a, b) = (WorkerId(Uuid::new_v4()), WorkerId(Uuid::new_v4()));
+        for worker_id in [a, b] {
+            let claimed = manager.claim_jobs(&[JobType::PC1], &[], 1, Some(worker_id));
+            assert_eq!(claimed.unwrap().len(), 1);
+        }
+        assert!(matches!(
+            manager.release(key, JobType::PC1, Some(a)),
+            Err(Error::Conflict(_))
+        ));
+        assert!(matches!(
+            manager.fail(key, JobType::PC1, JobFailure::transient("late"), Some(a)),
+            Err(Error::Conflict(_))
+        ));
+        manager.release(key, JobType::PC1, Some(b)).unwrap();
+        assert!(manager.claimed_jobs(b).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_query_pages_by_keyset() {
+        let manager = InMemorySealingJobManager::new();
+        for sector_id in 1..=3 {
+            manager.add_job(pc1(sector_id)).await.unwrap();
+        }
+        let query = JobQuery::new()
+            .sort_by(SortField::SectorId, SortOrder::Desc)
+            .page_size(2);
+        let sectors = |page: &Page<PC1>| -> Vec<u64> {
+            page.items.iter().map(|job| job.input.sector_id.0).collect()
+        };
+
+        let page = manager.query_jobs::<PC1>(query.clone()).await.unwrap();
+        assert_eq!(sectors(&page), vec![3, 2]);
+
+        // A job sorted before the cursor doesn't shift the next page.
+        manager.add_job(pc1(4)).await.unwrap();
+        let page = manager
+            .query_jobs::<PC1>(query.cursor(page.next_cursor.unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(sectors(&page), vec![1]);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_claims_match_registered_proofs() {
+        let manager = InMemorySealingJobManager::new();
+        manager.add_job(pc1(1)).await.unwrap();
+        manager
+            .register_worker(WorkerInfo {
+                hostname: "worker".to_string(),
+                job_types: vec![JobType::PC1],
+                registered_proofs: vec![RegisteredSealProof::StackedDrg32GiBV1],
+                resources: Default::default(),
+                versions: Default::default(),
+            })
+            .await
+            .unwrap();
+
+        // The job is for 2KiB sectors.
+        assert!(matches!(
+            manager.request_jobs::<PC1>(1).await,
+            Err(Error::NotEnoughJobs(1))
+        ));
+    }
+}
@@ -1,5 +1,30 @@
+pub mod api_error;
+pub mod auth;
+pub mod builder;
+pub mod dispatch;
+pub mod failure;
+pub mod history;
+pub mod in_memory;
+pub mod metrics;
+pub mod query;
+pub mod retry;
+pub mod sector;
+pub mod sector_storage;
+pub mod subscription;
+pub mod trace;
+pub mod worker;
+
+use api_error::ApiError;
 use async_trait::async_trait;
+use auth::Auth;
+use builder::SealingJobManagerHttpClientBuilder;
+use failure::JobFailure;
 use filecoin_spec::{RegisteredSealProof, SectorId, StorageProviderId};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+use history::{JobHistory, JobRef};
 use hyper::{http, StatusCode};
 use job::{
     sealing::{
@@ -7,19 +32,50 @@ use job::{
     },
     JobType,
 };
+use metrics::{ErrorCategory, MetricsObserver, NoopMetricsObserver, RequestMetrics};
 use mockall::automock;
-use serde::{Deserialize, Serialize};
+use query::{JobHttpPage, JobQuery, Page};
+use retry::{Operation, Retrier, RetryConfig};
+use sector::{GenerateTicket, GeneratedTicket, GetSectorPaths, SectorPaths};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use subscription::ReconnectBackoff;
+use worker::{
+    ListWorkersResponse, RegisterWorkerResponse, RegisteredWorker, WorkerId, WorkerInfo,
+    WORKER_ID_HEADER,
+};
 
 pub const ADD_JOBS_URL: &str = "/job";
 pub const GET_JOBS_URL: &str = "/job/:count/:job_type";
+pub const REQUEST_ANY_JOBS_URL: &str = "/job/any/:count";
 pub const GET_ALL_JOBS_URL: &str = "/job/all/:storage_provider_id/:job_type";
 pub const GET_JOB_INPUT_URI: &str = "/job/input/:storage_provider_id/:sector_id/:job_type";
 pub const GET_JOB_STATE_URL: &str = "/job/state/:storage_provider_id/:sector_id/:job_type";
-pub const FILTER_JOBS_URL: &str = "/job/filter/:job_type";
+pub const QUERY_JOBS_URL: &str = "/job/query/:job_type";
 
 pub const SUBMIT_OUTPUT_URL: &str = "/job/output";
 pub const GET_OUTPUT_URL: &str = "/job/output/:storage_provider_id/:sector_id/:job_type";
 pub const FAIL_JOB_URL: &str = "/job/fail";
+pub const RELEASE_JOB_URL: &str = "/job/release";
+pub const RETRY_JOB_URL: &str = "/job/retry";
+pub const REPORT_PROGRESS_URL: &str = "/job/progress";
+pub const CANCEL_JOB_URL: &str = "/job/cancel";
+pub const GET_JOB_HISTORY_URL: &str = "/job/history/:storage_provider_id/:sector_id/:job_type";
+pub const GET_JOB_HISTORY_BY_ID_URL: &str = "/job/history/id/:job_id";
+pub const SUBSCRIBE_JOBS_URL: &str = "/job/subscribe";
+pub const WATCH_JOBS_URL: &str = "/job/events/:job_type";
+
+pub const REGISTER_WORKER_URL: &str = "/worker";
+pub const LIST_WORKERS_URL: &str = "/worker/all";
+
+pub const ADD_JOBS_BATCH_URL: &str = "/job/batch";
+pub const SUBMIT_OUTPUTS_BATCH_URL: &str = "/job/output/batch";
+pub const GET_OUTPUTS_BATCH_URL: &str = "/job/output/batch/:job_type";
+pub const GET_JOB_STATES_BATCH_URL: &str = "/job/state/batch/:job_type";
 
 pub const GENERATE_TICKET_URL: &str = "/job/ticket/:storage_provider_id";
 pub const GET_SECTOR_PATHS_URL: &str = "/sector/paths/:storage_provider_id/:sector_id";
@@ -32,29 +88,71 @@ pub enum Error {
     #[error("{0}")]
     Reqwest(#[from] reqwest::Error),
 
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
     #[error("Job doesn't exist")]
     JobNotExist,
 
     #[error("Not enough jobs({0})")]
     NotEnoughJobs(usize),
 
-    #[error("Error while fetching jobs: {0}")]
-    FetchJobs(String),
+    #[error("Not found: {0}")]
+    NotFound(ApiError),
+
+    #[error("Conflict: {0}")]
+    Conflict(ApiError),
+
+    #[error("Already submitted: {0}")]
+    AlreadySubmitted(ApiError),
+
+    #[error("Validation failed: {0}")]
+    Validation(ApiError),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(ApiError),
+
+    #[error("Rate limited: {error}")]
+    RateLimited {
+        error: ApiError,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Server error: {0}")]
+    Server(ApiError),
+
+    #[error("Expected {expected} job but got {got}")]
+    UnexpectedJobType { expected: JobType, got: JobType },
+
+    #[error("Job manager circuit is open")]
+    CircuitOpen,
+
+    #[error("Batch response has {got} results for {expected} items")]
+    BatchSizeMismatch { expected: usize, got: usize },
+
+    #[error("Invalid sector file: {0}")]
+    InvalidSectorFile(String),
 
-    #[error("Error while submitting output: {0}")]
-    SubmitOutput(String),
+    #[error("Transfer expected to continue at byte {expected} but got {got}")]
+    TransferOffset { expected: u64, got: u64 },
 
-    #[error("Error while fetching output: {0}")]
-    FetchOutput(String),
+    #[error("Range requests are not supported by {0}")]
+    RangeNotSupported(String),
 
-    #[error("Error while submitting job failure: {0}")]
-    FailJob(String),
+    #[error("Cache directory listings are not served by {0}")]
+    ListingNotSupported(String),
 
-    #[error("Error while fetching job state: {0}")]
-    GetState(String),
+    #[error("No Content-Length in the response from {0}")]
+    MissingContentLength(String),
+
+    #[error("Checksum mismatch, expected {expected} but got {got}")]
+    ChecksumMismatch {
+        expected: sector_storage::Checksum,
+        got: sector_storage::Checksum,
+    },
 }
 
-pub struct JobOutput<SealingJobT: SealingJob>(pub Result<SealingJobT::Output, String>);
+pub struct JobOutput<SealingJobT: SealingJob>(pub Result<SealingJobT::Output, JobFailure>);
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "job_type")]
@@ -66,6 +164,68 @@ pub enum JobHttp {
     PC(PC),
 }
 
+impl JobHttp {
+    pub fn job_type(&self) -> JobType {
+        match self {
+            JobHttp::PC1(_) => JobType::PC1,
+            JobHttp::PC2(_) => JobType::PC2,
+            JobHttp::C1(_) => JobType::C1,
+            JobHttp::C2(_) => JobType::C2,
+            JobHttp::PC(_) => JobType::PC,
+        }
+    }
+
+    pub fn storage_provider_id(&self) -> StorageProviderId {
+        match self {
+            JobHttp::PC1(job) => job.storage_provider_id(),
+            JobHttp::PC2(job) => job.storage_provider_id(),
+            JobHttp::C1(job) => job.storage_provider_id(),
+            JobHttp::C2(job) => job.storage_provider_id(),
+            JobHttp::PC(job) => job.storage_provider_id(),
+        }
+    }
+
+    pub fn sector_id(&self) -> SectorId {
+        match self {
+            JobHttp::PC1(job) => job.sector_id(),
+            JobHttp::PC2(job) => job.sector_id(),
+            JobHttp::C1(job) => job.sector_id(),
+            JobHttp::C2(job) => job.sector_id(),
+            JobHttp::PC(job) => job.sector_id(),
+        }
+    }
+
+    pub fn registered_proof(&self) -> RegisteredSealProof {
+        match self {
+            JobHttp::PC1(job) => job.registered_proof(),
+            JobHttp::PC2(job) => job.registered_proof(),
+            JobHttp::C1(job) => job.registered_proof(),
+            JobHttp::C2(job) => job.registered_proof(),
+            JobHttp::PC(job) => job.registered_proof(),
+        }
+    }
+}
+
+/// A job with the W3C `traceparent` of the trace it was added in, so whoever
+/// runs it can continue that trace. On the wire it's the job with an extra
+/// `trace_parent` field.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JobEnvelope {
+    #[serde(flatten)]
+    pub job: JobHttp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_parent: Option<String>,
+}
+
+impl From<JobHttp> for JobEnvelope {
+    fn from(job: JobHttp) -> Self {
+        Self {
+            job,
+            trace_parent: None,
+        }
+    }
+}
+
 impl From<PC1> for JobHttp {
     fn from(job: PC1) -> Self {
         JobHttp::PC1(job)
@@ -96,47 +256,72 @@ impl From<PC> for JobHttp {
     }
 }
 
-impl From<JobHttp> for PC1 {
-    fn from(value: JobHttp) -> Self {
+impl TryFrom<JobHttp> for PC1 {
+    type Error = Error;
+
+    fn try_from(value: JobHttp) -> Result<Self, Self::Error> {
         match value {
-            JobHttp::PC1(job) => job,
-            _ => panic!("Invalid job PC1 job {:?}", value),
+            JobHttp::PC1(job) => Ok(job),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::PC1,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-impl From<JobHttp> for PC2 {
-    fn from(value: JobHttp) -> Self {
+impl TryFrom<JobHttp> for PC2 {
+    type Error = Error;
+
+    fn try_from(value: JobHttp) -> Result<Self, Self::Error> {
         match value {
-            JobHttp::PC2(job) => job,
-            _ => panic!("Invalid job PC2 job {:?}", value),
+            JobHttp::PC2(job) => Ok(job),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::PC2,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-impl From<JobHttp> for C1 {
-    fn from(value: JobHttp) -> Self {
+impl TryFrom<JobHttp> for C1 {
+    type Error = Error;
+
+    fn try_from(value: JobHttp) -> Result<Self, Self::Error> {
         match value {
-            JobHttp::C1(job) => job,
-            _ => panic!("Invalid job C1 job {:?}", value),
+            JobHttp::C1(job) => Ok(job),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::C1,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-impl From<JobHttp> for C2 {
-    fn from(value: JobHttp) -> Self {
+impl TryFrom<JobHttp> for C2 {
+    type Error = Error;
+
+    fn try_from(value: JobHttp) -> Result<Self, Self::Error> {
         match value {
-            JobHttp::C2(job) => job,
-            _ => panic!("Invalid job C2 job {:?}", value),
+            JobHttp::C2(job) => Ok(job),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::C2,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-impl From<JobHttp> for PC {
-    fn from(value: JobHttp) -> Self {
+impl TryFrom<JobHttp> for PC {
+    type Error = Error;
+
+    fn try_from(value: JobHttp) -> Result<Self, Self::Error> {
         match value {
-            JobHttp::PC(job) => job,
-            _ => panic!("Invalid job PC job {:?}", value),
+            JobHttp::PC(job) => Ok(job),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::PC,
+                got: value.job_type(),
+            }),
         }
     }
 }
@@ -151,6 +336,18 @@ pub enum JobOutputHttp {
     PC(PCOutput),
 }
 
+impl JobOutputHttp {
+    pub fn job_type(&self) -> JobType {
+        match self {
+            JobOutputHttp::PC1(_) => JobType::PC1,
+            JobOutputHttp::PC2(_) => JobType::PC2,
+            JobOutputHttp::C1(_) => JobType::C1,
+            JobOutputHttp::C2(_) => JobType::C2,
+            JobOutputHttp::PC(_) => JobType::PC,
+        }
+    }
+}
+
 impl From<PC1Output> for JobOutputHttp {
     fn from(output: PC1Output) -> Self {
         JobOutputHttp::PC1(output)
@@ -181,48 +378,82 @@ impl From<PCOutput> for JobOutputHttp {
     }
 }
 
-impl From<JobOutputHttp> for PC1Output {
-    fn from(value: JobOutputHttp) -> Self {
+impl TryFrom<JobOutputHttp> for PC1Output {
+    type Error = Error;
+
+    fn try_from(value: JobOutputHttp) -> Result<Self, Self::Error> {
+        match value {
+            JobOutputHttp::PC1(output) => Ok(output),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::PC1,
+                got: value.job_type(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<JobOutputHttp> for PC2Output {
+    type Error = Error;
+
+    fn try_from(value: JobOutputHttp) -> Result<Self, Self::Error> {
         match value {
-            JobOutputHttp::PC1(output) => output,
-            _ => panic!("Invalid PC1 output {:?}", value),
+            JobOutputHttp::PC2(output) => Ok(output),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::PC2,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-impl From<JobOutputHttp> for PC2Output {
-    fn from(value: JobOutputHttp) -> Self {
+impl TryFrom<JobOutputHttp> for C1Output {
+    type Error = Error;
+
+    fn try_from(value: JobOutputHttp) -> Result<Self, Self::Error> {
         match value {
-            JobOutputHttp::PC2(output) => output,
-            _ => panic!("Invalid PC2 output {:?}", value),
+            JobOutputHttp::C1(output) => Ok(output),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::C1,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-impl From<JobOutputHttp> for C1Output {
-    fn from(value: JobOutputHttp) -> Self {
+impl TryFrom<JobOutputHttp> for C2Output {
+    type Error = Error;
+
+    fn try_from(value: JobOutputHttp) -> Result<Self, Self::Error> {
         match value {
-            JobOutputHttp::C1(output) => output,
-            _ => panic!("Invalid C1 output {:?}", value),
+            JobOutputHttp::C2(output) => Ok(output),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::C2,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-impl From<JobOutputHttp> for C2Output {
-    fn from(value: JobOutputHttp) -> Self {
+impl TryFrom<JobOutputHttp> for PCOutput {
+    type Error = Error;
+
+    fn try_from(value: JobOutputHttp) -> Result<Self, Self::Error> {
         match value {
-            JobOutputHttp::C2(output) => output,
-            _ => panic!("Invalid C2 output {:?}", value),
+            JobOutputHttp::PC(output) => Ok(output),
+            _ => Err(Error::UnexpectedJobType {
+                expected: JobType::PC,
+                got: value.job_type(),
+            }),
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct GetSealingJobsResponse {
-    pub jobs: Vec<JobHttp>,
+    pub jobs: Vec<JobEnvelope>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct SubmitSealingJobOutput {
     pub storage_provider_id: StorageProviderId,
     pub sector_id: SectorId,
@@ -236,7 +467,32 @@ pub struct FailJob {
     pub storage_provider_id: StorageProviderId,
     pub sector_id: SectorId,
     pub job_type: JobType,
-    pub error: String,
+    #[serde(deserialize_with = "failure::deserialize_compat")]
+    pub error: JobFailure,
+}
+
+/// Gives a claimed job back without counting the attempt.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReleaseJob {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub job_type: JobType,
+}
+
+/// Requeues a job that failed for good, with its attempts reset.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryJob {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub job_type: JobType,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CancelJob {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub job_type: JobType,
+    pub reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -244,6 +500,118 @@ pub enum JobState {
     Pending,
     Done,
     Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum JobStage {
+    Fetching,
+    Labeling,
+    TreeBuilding,
+    Proving,
+    Submitting,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct JobProgress {
+    pub stage: JobStage,
+    /// Completion of the whole job in the `0.0..=100.0` range.
+    pub percent: f32,
+    /// Layer currently being labeled, only reported by PC1.
+    pub current_layer: Option<u32>,
+    pub eta_secs: Option<u64>,
+    pub message: Option<String>,
+}
+
+impl JobProgress {
+    pub fn new(stage: JobStage, percent: f32) -> Self {
+        Self {
+            stage,
+            percent: percent.clamp(0.0, 100.0),
+            current_layer: None,
+            eta_secs: None,
+            message: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReportProgress {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub job_type: JobType,
+    pub progress: JobProgress,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct JobStatus {
+    pub state: JobState,
+    #[serde(default)]
+    pub progress: Option<JobProgress>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobKey {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+}
+
+impl From<(StorageProviderId, SectorId)> for JobKey {
+    fn from((storage_provider_id, sector_id): (StorageProviderId, SectorId)) -> Self {
+        Self {
+            storage_provider_id,
+            sector_id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatchRequest<T> {
+    pub items: Vec<T>,
+}
+
+/// Results are returned in request order, a failing item doesn't fail the batch.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatchResponse<T> {
+    pub results: Vec<Result<T, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobOutputEntry {
+    Missing,
+    Failed {
+        #[serde(deserialize_with = "failure::deserialize_compat")]
+        err: JobFailure,
+    },
+    Done {
+        output: JobOutputHttp,
+    },
+}
+
+pub type BatchResult<T> = Vec<Result<T, String>>;
+
+/// Position in the server's job event log, events carry strictly increasing cursors.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct EventCursor(pub u64);
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct JobStateEvent {
+    pub cursor: EventCursor,
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub job_type: JobType,
+    pub old_state: Option<JobState>,
+    pub new_state: JobState,
+    /// Milliseconds since the unix epoch.
+    pub timestamp_ms: u64,
+    pub error: Option<JobFailure>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WatchJobs {
+    pub filter: Filter,
+    pub cursor: Option<EventCursor>,
 }
 
 #[derive(Serialize, Deserialize, Default, PartialEq, Debug, Clone)]
@@ -263,16 +631,29 @@ pub trait SealingJobManagerClient: Clone + Send + Sync {
         job: SealingJobT,
     ) -> Result<(), Error>;
 
-    async fn request_jobs<SealingJobT: SealingJob + From<JobHttp> + 'static>(
+    async fn request_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
         &self,
         count: usize,
     ) -> Result<Vec<SealingJobT>, Error>;
 
-    async fn filter_jobs<SealingJobT: SealingJob + From<JobHttp> + 'static>(
+    /// Claims up to `count` jobs of any of `job_types`, for workers that run
+    /// more than one kind of job. Each comes with the trace it was added in.
+    async fn request_any_jobs(
+        &self,
+        job_types: &[JobType],
+        count: usize,
+    ) -> Result<Vec<JobEnvelope>, Error>;
+
+    async fn filter_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
         &self,
         filter: Filter,
     ) -> Result<Vec<SealingJobT>, Error>;
 
+    async fn query_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
+        &self,
+        query: JobQuery,
+    ) -> Result<Page<SealingJobT>, Error>;
+
     async fn submit_job_output<SealingJobT: SealingJob + 'static>(
         &self,
         storage_provider_id: StorageProviderId,
@@ -288,33 +669,199 @@ pub trait SealingJobManagerClient: Clone + Send + Sync {
         sector_id: SectorId,
     ) -> Result<Option<JobOutput<SealingJobT>>, Error>
     where
-        SealingJobT::Output: From<JobOutputHttp>;
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>;
 
-    async fn get_job_input<SealingJobT: SealingJob + From<JobHttp> + 'static>(
+    async fn get_job_input<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
         &self,
         storage_provider_id: StorageProviderId,
         sector_id: SectorId,
     ) -> Result<Option<SealingJobT>, Error>
     where
-        SealingJobT::Output: From<JobOutputHttp>;
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>;
+
+    async fn get_any_job_input(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<Option<JobHttp>, Error>;
+
+    async fn submit_any_job_output(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        output: JobOutputHttp,
+    ) -> Result<(), Error>;
 
     async fn fail_job<SealingJobT: SealingJob + 'static>(
         &self,
         storage_provider_id: StorageProviderId,
         sector_id: SectorId,
-        error: &str,
+        error: JobFailure,
+    ) -> Result<(), Error>;
+
+    async fn fail_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+        error: JobFailure,
+    ) -> Result<(), Error>;
+
+    /// Returns a claimed job to the queue without counting the attempt, e.g.
+    /// when the worker shuts down before it could finish the job. Releasing a
+    /// job that isn't claimed is a no-op.
+    async fn release_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<(), Error>;
+
+    async fn release_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<(), Error>;
+
+    /// Requeues a job that failed for good with a fresh set of attempts, so
+    /// its input is computed again. Retrying a pending job is a no-op, a done
+    /// or cancelled job is a conflict.
+    async fn retry_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<(), Error>;
+
+    async fn retry_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
     ) -> Result<(), Error>;
 
     async fn get_job_state<SealingJobT: SealingJob + 'static>(
         &self,
         storage_provider_id: StorageProviderId,
         sector_id: SectorId,
-    ) -> Result<Option<JobState>, Error>;
+    ) -> Result<Option<JobStatus>, Error>;
+
+    async fn report_progress<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        progress: JobProgress,
+    ) -> Result<(), Error>;
+
+    async fn cancel_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        reason: Option<String>,
+    ) -> Result<(), Error>;
+
+    async fn get_job_history(&self, job: JobRef) -> Result<Option<JobHistory>, Error>;
+
+    /// Registers the worker's capabilities, the returned id is sent with every
+    /// later request made by this client.
+    async fn register_worker(&self, info: WorkerInfo) -> Result<WorkerId, Error>;
+
+    async fn list_workers(&self) -> Result<Vec<RegisteredWorker>, Error>;
+
+    /// Draws sealing randomness for a new sector of `storage_provider_id`.
+    async fn generate_ticket(
+        &self,
+        storage_provider_id: StorageProviderId,
+    ) -> Result<GeneratedTicket, Error>;
+
+    async fn get_sector_paths(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<SectorPaths, Error>;
+
+    async fn add_jobs(&self, jobs: Vec<JobHttp>) -> Result<BatchResult<()>, Error>;
+
+    async fn submit_job_outputs(
+        &self,
+        outputs: Vec<SubmitSealingJobOutput>,
+    ) -> Result<BatchResult<()>, Error>;
+
+    async fn get_job_states<SealingJobT: SealingJob + 'static>(
+        &self,
+        jobs: Vec<(StorageProviderId, SectorId)>,
+    ) -> Result<BatchResult<Option<JobStatus>>, Error>;
+
+    async fn get_job_outputs<SealingJobT: SealingJob + 'static>(
+        &self,
+        jobs: Vec<(StorageProviderId, SectorId)>,
+    ) -> Result<BatchResult<Option<JobOutput<SealingJobT>>>, Error>
+    where
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>;
+
+    /// Streams state transitions of jobs matching `filter`, starting after
+    /// `cursor` or from now on when `None`.
+    fn watch_jobs<SealingJobT: SealingJob + 'static>(
+        &self,
+        filter: Filter,
+        cursor: Option<EventCursor>,
+    ) -> BoxStream<'static, Result<JobStateEvent, Error>>;
+
+    async fn is_job_cancelled<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<bool, Error> {
+        let status = self
+            .get_job_state::<SealingJobT>(storage_provider_id, sector_id)
+            .await?;
+
+        Ok(matches!(
+            status,
+            Some(JobStatus {
+                state: JobState::Cancelled,
+                ..
+            })
+        ))
+    }
+}
+
+/// Resolves once the job has been cancelled, polling its state every `interval`.
+/// Meant to be raced against the computation, e.g. with `tokio::select!`.
+pub async fn wait_for_cancellation<SealingJobT, Client>(
+    client: &Client,
+    storage_provider_id: StorageProviderId,
+    sector_id: SectorId,
+    interval: Duration,
+) -> Result<(), Error>
+where
+    SealingJobT: SealingJob + 'static,
+    Client: SealingJobManagerClient,
+{
+    loop {
+        if client
+            .is_job_cancelled::<SealingJobT>(storage_provider_id, sector_id)
+            .await?
+        {
+            tracing::info!(
+                "{} job cancelled for storage_provider_id: {}, sector_id: {}",
+                SealingJobT::job_type(),
+                storage_provider_id.0,
+                sector_id.0
+            );
+            return Ok(());
+        }
+
+        tokio::time::sleep(interval).await;
+    }
 }
 
+/// Expectations can't be shared between mocks, a clone starts without any.
+/// Tests that need a shared client should use
+/// [`in_memory::InMemorySealingJobManager`].
 impl Clone for MockSealingJobManagerClient {
     fn clone(&self) -> Self {
-        todo!()
+        MockSealingJobManagerClient::new()
     }
 }
 
@@ -323,27 +870,226 @@ pub struct SealingJobManagerHttpClient {
     http_client: reqwest::Client,
     add_jobs_uri: String,
     request_jobs_uri: String,
-    filter_jobs_uri: String,
+    request_any_jobs_uri: String,
+    query_jobs_uri: String,
     submit_output_uri: String,
     get_job_output_uri: String,
     get_job_input_uri: String,
     fail_job_uri: String,
+    release_job_uri: String,
+    retry_job_uri: String,
     get_job_state_uri: String,
+    report_progress_uri: String,
+    cancel_job_uri: String,
+    subscribe_jobs_uri: String,
+    watch_jobs_uri: String,
+    add_jobs_batch_uri: String,
+    submit_outputs_batch_uri: String,
+    get_outputs_batch_uri: String,
+    get_job_states_batch_uri: String,
+    register_worker_uri: String,
+    list_workers_uri: String,
+    get_job_history_uri: String,
+    get_job_history_by_id_uri: String,
+    generate_ticket_uri: String,
+    get_sector_paths_uri: String,
+    auth: Auth,
+    retrier: Arc<Retrier>,
+    metrics: Arc<dyn MetricsObserver>,
+    worker_id: Arc<RwLock<Option<WorkerId>>>,
 }
 
 impl SealingJobManagerHttpClient {
     pub fn new(uri: String) -> Self {
+        Self::from_parts(
+            uri,
+            reqwest::Client::new(),
+            Auth::None,
+            RetryConfig::default(),
+            Arc::new(NoopMetricsObserver),
+        )
+    }
+
+    pub fn builder(uri: String) -> SealingJobManagerHttpClientBuilder {
+        SealingJobManagerHttpClientBuilder::new(uri)
+    }
+
+    pub(crate) fn from_parts(
+        uri: String,
+        http_client: reqwest::Client,
+        auth: Auth,
+        retry: RetryConfig,
+        metrics: Arc<dyn MetricsObserver>,
+    ) -> Self {
         Self {
-            http_client: reqwest::Client::new(),
+            http_client,
             add_jobs_uri: uri.clone() + ADD_JOBS_URL,
             request_jobs_uri: uri.clone() + GET_JOBS_URL,
-            filter_jobs_uri: uri.clone() + FILTER_JOBS_URL,
+            request_any_jobs_uri: uri.clone() + REQUEST_ANY_JOBS_URL,
+            query_jobs_uri: uri.clone() + QUERY_JOBS_URL,
             submit_output_uri: uri.clone() + SUBMIT_OUTPUT_URL,
             get_job_output_uri: uri.clone() + GET_OUTPUT_URL,
             get_job_input_uri: uri.clone() + GET_JOB_INPUT_URI,
             fail_job_uri: uri.clone() + FAIL_JOB_URL,
-            get_job_state_uri: uri + GET_JOB_STATE_URL,
+            release_job_uri: uri.clone() + RELEASE_JOB_URL,
+            retry_job_uri: uri.clone() + RETRY_JOB_URL,
+            get_job_state_uri: uri.clone() + GET_JOB_STATE_URL,
+            report_progress_uri: uri.clone() + REPORT_PROGRESS_URL,
+            cancel_job_uri: uri.clone() + CANCEL_JOB_URL,
+            subscribe_jobs_uri: uri.clone() + SUBSCRIBE_JOBS_URL,
+            watch_jobs_uri: uri.clone() + WATCH_JOBS_URL,
+            add_jobs_batch_uri: uri.clone() + ADD_JOBS_BATCH_URL,
+            submit_outputs_batch_uri: uri.clone() + SUBMIT_OUTPUTS_BATCH_URL,
+            get_outputs_batch_uri: uri.clone() + GET_OUTPUTS_BATCH_URL,
+            get_job_states_batch_uri: uri.clone() + GET_JOB_STATES_BATCH_URL,
+            register_worker_uri: uri.clone() + REGISTER_WORKER_URL,
+            list_workers_uri: uri.clone() + LIST_WORKERS_URL,
+            get_job_history_uri: uri.clone() + GET_JOB_HISTORY_URL,
+            get_job_history_by_id_uri: uri.clone() + GET_JOB_HISTORY_BY_ID_URL,
+            generate_ticket_uri: uri.clone() + GENERATE_TICKET_URL,
+            get_sector_paths_uri: uri + GET_SECTOR_PATHS_URL,
+            auth,
+            retrier: Arc::new(retry.into()),
+            metrics,
+            worker_id: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn worker_id(&self) -> Option<WorkerId> {
+        *self.worker_id.read().unwrap()
+    }
+
+    async fn send(
+        &self,
+        operation: Operation,
+        idempotency_key: Option<String>,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        self.send_request(operation, idempotency_key, 0, request)
+            .await
+    }
+
+    /// Sends `body` as JSON with the request built by `request`.
+    async fn send_json(
+        &self,
+        operation: Operation,
+        idempotency_key: Option<String>,
+        body: &str,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        self.send_request(operation, idempotency_key, body.len(), || {
+            request()
+                .body(body.to_string())
+                .header(http::header::CONTENT_TYPE, "application/json")
+        })
+        .await
+    }
+
+    async fn send_request(
+        &self,
+        operation: Operation,
+        idempotency_key: Option<String>,
+        request_bytes: usize,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        let mut token = self.auth.bearer_token().await?;
+        let worker_id = self.worker_id();
+        let trace_headers = trace::current_headers();
+        let started = Instant::now();
+
+        let mut refreshed = false;
+        let result = loop {
+            let result = self
+                .retrier
+                .execute(operation, idempotency_key.is_some(), || {
+                    let mut request = auth::with_bearer(request(), &token);
+                    if let Some(key) = &idempotency_key {
+                        request = request.header(retry::IDEMPOTENCY_KEY_HEADER, key);
+                    }
+                    if let Some(worker_id) = worker_id {
+                        request = request.header(WORKER_ID_HEADER, worker_id.0.to_string());
+                    }
+                    for (name, value) in &trace_headers {
+                        request = request.header(name, value);
+                    }
+                    request.send()
+                })
+                .await;
+
+            // The token may have expired or been revoked, retry once with a
+            // fresh one.
+            if !refreshed
+                && matches!(&result, Ok(response) if response.status() == StatusCode::UNAUTHORIZED)
+            {
+                if let Some(fresh) = self.auth.refreshed_token().await? {
+                    tracing::debug!("Retrying {:?} with a refreshed token", operation);
+                    token = Some(fresh);
+                    refreshed = true;
+                    continue;
+                }
+            }
+            break result;
+        };
+
+        let status = result.as_ref().ok().map(|response| response.status());
+        self.metrics.on_request(
+            operation,
+            &RequestMetrics {
+                latency: started.elapsed(),
+                request_bytes: request_bytes as u64,
+                response_bytes: result
+                    .as_ref()
+                    .ok()
+                    .and_then(|response| response.content_length()),
+                status,
+            },
+        );
+        let category = match &result {
+            Ok(response) if operation.expects_status(response.status()) => None,
+            Ok(response) => ErrorCategory::from_status(response.status()),
+            Err(err) => Some(err.category()),
+        };
+        if let Some(category) = category {
+            self.metrics.on_error(operation, category);
+        }
+
+        result
+    }
+
+    async fn post_batch<Item: Serialize, Res: DeserializeOwned>(
+        &self,
+        operation: Operation,
+        uri: &str,
+        items: Vec<Item>,
+    ) -> Result<BatchResult<Res>, Error> {
+        let expected = items.len();
+        let body = serde_json::to_string(&BatchRequest { items })?;
+        let response = self
+            .send_json(operation, Some(retry::digest_key(&body)), &body, || {
+                self.http_client.post(uri)
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Batch request to {} failed: {}", uri, err);
+            return Err(err);
+        }
+
+        let response: BatchResponse<Res> = response.json().await?;
+        if response.results.len() != expected {
+            return Err(Error::BatchSizeMismatch {
+                expected,
+                got: response.results.len(),
+            });
+        }
+
+        let failed = response.results.iter().filter(|r| r.is_err()).count();
+        if failed > 0 {
+            tracing::warn!("{} of {} batch items failed at {}", failed, expected, uri);
         }
+
+        Ok(response.results)
     }
 }
 
@@ -353,19 +1099,23 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         &self,
         job: SealingJobT,
     ) -> Result<(), Error> {
-        let job: JobHttp = job.into();
-        let response = reqwest::Client::new()
-            .post(&self.add_jobs_uri)
-            .body(serde_json::to_string(&job)?)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .send()
+        let mut job = JobEnvelope::from(job.into());
+        trace::inject(&mut job);
+        let body = serde_json::to_string(&job)?;
+        // Keyed by the whole job, re-adding a sector's job with a new ticket
+        // or input must not replay the first response.
+        let key = retry::digest_key(&body);
+        let response = self
+            .send_json(Operation::AddJob, Some(key), &body, || {
+                self.http_client.post(&self.add_jobs_uri)
+            })
             .await?;
 
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
-            tracing::error!("Failed to submit jobs: {}", resp,);
+            let err = api_error::from_response(response).await;
+            tracing::error!("Failed to submit jobs: {}", err,);
 
-            return Err(Error::FetchJobs(resp));
+            return Err(err);
         } else {
             tracing::info!("Succesfully submited jobs");
         }
@@ -373,7 +1123,7 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         Ok(())
     }
 
-    async fn request_jobs<SealingJobT: SealingJob + From<JobHttp> + 'static>(
+    async fn request_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
         &self,
         count: usize,
     ) -> Result<Vec<SealingJobT>, Error> {
@@ -389,10 +1139,11 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
             job_type
         );
         let response = self
-            .http_client
-            .get(uri)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .send()
+            .send(Operation::RequestJobs, None, || {
+                self.http_client
+                    .get(&uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
             .await?;
 
         if response.status() == StatusCode::NO_CONTENT {
@@ -401,63 +1152,149 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         }
 
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
-            tracing::error!("Error while fetching jobs: {}", &resp);
-            return Err(Error::FetchJobs(resp));
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while fetching jobs: {}", err);
+            return Err(err);
         }
 
         let response: GetSealingJobsResponse = response.json().await?;
         tracing::trace!("request_jobs response {:?}", response);
-
-        let jobs: Vec<SealingJobT> = response.jobs.into_iter().map(|job| job.into()).collect();
-        Ok(jobs)
+        self.metrics
+            .on_jobs_fetched(SealingJobT::job_type(), response.jobs.len());
+
+        response
+            .jobs
+            .into_iter()
+            .map(|envelope| SealingJobT::try_from(envelope.job))
+            .collect()
     }
 
-    async fn filter_jobs<SealingJobT: SealingJob + From<JobHttp> + 'static>(
+    async fn request_any_jobs(
         &self,
-        filter: Filter,
-    ) -> Result<Vec<SealingJobT>, Error> {
-        let job_type = SealingJobT::job_type().to_string();
-        let uri = self.filter_jobs_uri.replace(":job_type", job_type.as_str());
-
-        tracing::debug!("Filtering {} jobs using filter {:?}", job_type, filter);
+        job_types: &[JobType],
+        count: usize,
+    ) -> Result<Vec<JobEnvelope>, Error> {
+        let uri = self
+            .request_any_jobs_uri
+            .replace(":count", count.to_string().as_str());
+
+        tracing::debug!(
+            "Requesting {} jobs of types {:?} from Job Management Server",
+            count,
+            job_types
+        );
 
         #[derive(Serialize)]
-        struct FilterJobsRequest {
-            filter: Filter,
+        struct RequestAnyJobs<'a> {
+            job_types: &'a [JobType],
         }
 
-        let request = FilterJobsRequest { filter };
+        let body = serde_json::to_string(&RequestAnyJobs { job_types })?;
         let response = self
-            .http_client
-            .get(uri)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .body(serde_json::to_string(&request)?)
-            .send()
+            .send_json(Operation::RequestJobs, None, &body, || {
+                self.http_client.post(&uri)
+            })
             .await?;
 
+        if response.status() == StatusCode::NO_CONTENT {
+            tracing::error!("{} jobs not available", count);
+            return Err(Error::NotEnoughJobs(count));
+        }
+
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
-            tracing::error!("Error while filtering jobs: {}", &resp);
-            return Err(Error::FetchJobs(resp));
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while fetching jobs: {}", err);
+            return Err(err);
         }
 
         let response: GetSealingJobsResponse = response.json().await?;
-        tracing::trace!("filter_jobs response {:?}", response);
+        tracing::trace!("request_any_jobs response {:?}", response);
+
+        let mut fetched = HashMap::new();
+        for envelope in &response.jobs {
+            *fetched.entry(envelope.job.job_type()).or_insert(0) += 1;
+        }
+        for (job_type, count) in fetched {
+            self.metrics.on_jobs_fetched(job_type, count);
+        }
 
-        let jobs: Vec<SealingJobT> = response.jobs.into_iter().map(|job| job.into()).collect();
-        Ok(jobs)
+        Ok(response.jobs)
     }
 
-    async fn get_job_input<SealingJobT: SealingJob + From<JobHttp> + 'static>(
+    async fn filter_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
+        &self,
+        filter: Filter,
+    ) -> Result<Vec<SealingJobT>, Error> {
+        tracing::debug!(
+            "Filtering {} jobs using filter {:?}",
+            SealingJobT::job_type(),
+            filter
+        );
+
+        // Without a limit every page is fetched.
+        let limit = filter.limit.map_or(usize::MAX, |limit| limit as usize);
+        query::query_jobs_stream(self, JobQuery::from(filter))
+            .take(limit)
+            .try_collect()
+            .await
+    }
+
+    async fn query_jobs<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
+        &self,
+        query: JobQuery,
+    ) -> Result<Page<SealingJobT>, Error> {
+        let job_type = SealingJobT::job_type().to_string();
+        let uri = self.query_jobs_uri.replace(":job_type", job_type.as_str());
+
+        tracing::debug!("Querying {} jobs using {:?}", job_type, query);
+
+        let body = serde_json::to_string(&query)?;
+        let response = self
+            .send_json(Operation::QueryJobs, None, &body, || {
+                self.http_client.post(&uri)
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while querying jobs: {}", err);
+            return Err(err);
+        }
+
+        let page: JobHttpPage = response.json().await?;
+        tracing::trace!("query_jobs response {:?}", page);
+
+        Ok(Page {
+            items: page
+                .items
+                .into_iter()
+                .map(SealingJobT::try_from)
+                .collect::<Result<_, _>>()?,
+            next_cursor: page.next_cursor,
+        })
+    }
+
+    async fn get_job_input<SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static>(
         &self,
         storage_provider_id: StorageProviderId,
         sector_id: SectorId,
     ) -> Result<Option<SealingJobT>, Error>
     where
-        SealingJobT::Output: From<JobOutputHttp>,
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>,
     {
-        let job_type = SealingJobT::job_type().to_string();
+        self.get_any_job_input(storage_provider_id, sector_id, SealingJobT::job_type())
+            .await?
+            .map(SealingJobT::try_from)
+            .transpose()
+    }
+
+    async fn get_any_job_input(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<Option<JobHttp>, Error> {
+        let job_type = job_type.to_string();
         let uri = self
             .get_job_input_uri
             .replace(":storage_provider_id", &storage_provider_id.0.to_string())
@@ -471,10 +1308,11 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
             sector_id.0
         );
         let response = self
-            .http_client
-            .get(uri)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .send()
+            .send(Operation::GetJobInput, None, || {
+                self.http_client
+                    .get(&uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
             .await?;
 
         if response.status() == StatusCode::NO_CONTENT {
@@ -482,15 +1320,14 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         }
 
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
-            tracing::error!("Error while fetching input: {}", &resp);
-            return Err(Error::FetchOutput(resp));
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while fetching input: {}", err);
+            return Err(err);
         }
 
         let input: JobHttp = response.json().await?;
         tracing::trace!("job_input response {:?}", input);
 
-        let input: SealingJobT = input.into();
         Ok(Some(input))
     }
 
@@ -503,34 +1340,49 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
     where
         JobOutputHttp: From<SealingJobT::Output>,
     {
+        self.submit_any_job_output(storage_provider_id, sector_id, output.into())
+            .await
+    }
+
+    async fn submit_any_job_output(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        output: JobOutputHttp,
+    ) -> Result<(), Error> {
+        let job_type = output.job_type();
         let request = SubmitSealingJobOutput {
             storage_provider_id,
             sector_id,
-            job: output.into(),
+            job: output,
         };
-        let response = reqwest::Client::new()
-            .post(&self.submit_output_uri)
-            .body(serde_json::to_string(&request)?)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .send()
+        let body = serde_json::to_string(&request)?;
+        let response = self
+            .send_json(
+                Operation::SubmitOutput,
+                Some(retry::digest_key(&body)),
+                &body,
+                || self.http_client.post(&self.submit_output_uri),
+            )
             .await?;
 
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
+            let err = api_error::from_response(response).await;
             tracing::error!(
                 "Failed to submit results for storage_provider_id: {}, sector_id: {}, response: {}",
                 storage_provider_id.0,
                 sector_id.0,
-                resp,
+                err,
             );
 
-            return Err(Error::FetchJobs(resp));
+            return Err(err);
         } else {
             tracing::info!(
                 "Succesfully submited result for storage_provider_id: {}, sector_id: {}",
                 storage_provider_id.0,
                 sector_id.0
             );
+            self.metrics.on_jobs_submitted(job_type, 1);
         }
 
         Ok(())
@@ -542,7 +1394,7 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         sector_id: SectorId,
     ) -> Result<Option<JobOutput<SealingJobT>>, Error>
     where
-        SealingJobT::Output: From<JobOutputHttp>,
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>,
     {
         let job_type = SealingJobT::job_type().to_string();
         let uri = self
@@ -558,10 +1410,11 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
             sector_id.0
         );
         let response = self
-            .http_client
-            .get(uri)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .send()
+            .send(Operation::GetJobOutput, None, || {
+                self.http_client
+                    .get(&uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
             .await?;
 
         if response.status() == StatusCode::NO_CONTENT {
@@ -571,7 +1424,8 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         if response.status() == StatusCode::FAILED_DEPENDENCY {
             #[derive(Deserialize)]
             struct ErrorResp {
-                pub err: String,
+                #[serde(deserialize_with = "failure::deserialize_compat")]
+                pub err: JobFailure,
             }
             let resp = response.json::<ErrorResp>().await?;
 
@@ -579,15 +1433,15 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         }
 
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
-            tracing::error!("Error while fetching output: {}", &resp);
-            return Err(Error::FetchOutput(resp));
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while fetching output: {}", err);
+            return Err(err);
         }
 
         let output: JobOutputHttp = response.json().await?;
         tracing::trace!("request_jobs response {:?}", output);
 
-        let output: SealingJobT::Output = output.into();
+        let output = SealingJobT::Output::try_from(output)?;
         Ok(Some(JobOutput(Ok(output))))
     }
 
@@ -595,31 +1449,50 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         &self,
         storage_provider_id: StorageProviderId,
         sector_id: SectorId,
-        error: &str,
+        error: JobFailure,
+    ) -> Result<(), Error> {
+        self.fail_any_job(
+            storage_provider_id,
+            sector_id,
+            SealingJobT::job_type(),
+            error,
+        )
+        .await
+    }
+
+    async fn fail_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+        error: JobFailure,
     ) -> Result<(), Error> {
         let request = FailJob {
             storage_provider_id,
             sector_id,
-            job_type: SealingJobT::job_type(),
-            error: error.to_string(),
+            job_type,
+            error,
         };
-        let response = reqwest::Client::new()
-            .post(&self.fail_job_uri)
-            .body(serde_json::to_string(&request)?)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .send()
+        let body = serde_json::to_string(&request)?;
+        // Unique per call, failing the next attempt the same way must not
+        // replay this one.
+        let key = uuid::Uuid::new_v4().to_string();
+        let response = self
+            .send_json(Operation::FailJob, Some(key), &body, || {
+                self.http_client.post(&self.fail_job_uri)
+            })
             .await?;
 
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
+            let err = api_error::from_response(response).await;
             tracing::error!(
                 "Failed to submit job failure for storage_provider_id: {}, sector_id: {}, response: {}",
                 storage_provider_id.0,
                 sector_id.0,
-                resp,
+                err,
             );
 
-            return Err(Error::FetchJobs(resp));
+            return Err(err);
         }
 
         tracing::info!(
@@ -631,11 +1504,98 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         Ok(())
     }
 
+    async fn release_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<(), Error> {
+        self.release_any_job(storage_provider_id, sector_id, SealingJobT::job_type())
+            .await
+    }
+
+    async fn release_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<(), Error> {
+        let request = ReleaseJob {
+            storage_provider_id,
+            sector_id,
+            job_type,
+        };
+        let body = serde_json::to_string(&request)?;
+        let response = self
+            .send_json(Operation::ReleaseJob, None, &body, || {
+                self.http_client.post(&self.release_job_uri)
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!(
+                "Failed to release {} job for storage_provider_id: {}, sector_id: {}: {}",
+                job_type,
+                storage_provider_id.0,
+                sector_id.0,
+                err,
+            );
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn retry_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<(), Error> {
+        self.retry_any_job(storage_provider_id, sector_id, SealingJobT::job_type())
+            .await
+    }
+
+    async fn retry_any_job(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        job_type: JobType,
+    ) -> Result<(), Error> {
+        let request = RetryJob {
+            storage_provider_id,
+            sector_id,
+            job_type,
+        };
+        let body = serde_json::to_string(&request)?;
+        // Unique per call like failures, the same job may be retried again
+        // after it fails again.
+        let key = uuid::Uuid::new_v4().to_string();
+        let response = self
+            .send_json(Operation::RetryJob, Some(key), &body, || {
+                self.http_client.post(&self.retry_job_uri)
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!(
+                "Failed to retry {} job for storage_provider_id: {}, sector_id: {}: {}",
+                job_type,
+                storage_provider_id.0,
+                sector_id.0,
+                err,
+            );
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     async fn get_job_state<SealingJobT: SealingJob + 'static>(
         &self,
         storage_provider_id: StorageProviderId,
         sector_id: SectorId,
-    ) -> Result<Option<JobState>, Error> {
+    ) -> Result<Option<JobStatus>, Error> {
         let job_type = SealingJobT::job_type().to_string();
         let uri = self
             .get_job_state_uri
@@ -650,10 +1610,11 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
             sector_id.0
         );
         let response = self
-            .http_client
-            .get(uri)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .send()
+            .send(Operation::GetJobState, None, || {
+                self.http_client
+                    .get(&uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
             .await?;
 
         if response.status() == StatusCode::NO_CONTENT {
@@ -661,19 +1622,501 @@ impl SealingJobManagerClient for SealingJobManagerHttpClient {
         }
 
         if response.status() != StatusCode::OK {
-            let resp = response.text().await?;
-            tracing::error!("Error while fetching state: {}", &resp);
-            return Err(Error::GetState(resp));
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while fetching state: {}", err);
+            return Err(err);
         }
 
-        #[derive(Deserialize, Debug)]
-        struct Response {
-            pub state: JobState,
+        let response: JobStatus = response.json().await?;
+        tracing::trace!("get_state response {:?}", response);
+
+        Ok(Some(response))
+    }
+
+    async fn report_progress<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        progress: JobProgress,
+    ) -> Result<(), Error> {
+        let request = ReportProgress {
+            storage_provider_id,
+            sector_id,
+            job_type: SealingJobT::job_type(),
+            progress,
+        };
+        let body = serde_json::to_string(&request)?;
+        let response = self
+            .send_json(Operation::ReportProgress, None, &body, || {
+                self.http_client.post(&self.report_progress_uri)
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!(
+                "Failed to report progress for storage_provider_id: {}, sector_id: {}, response: {}",
+                storage_provider_id.0,
+                sector_id.0,
+                err,
+            );
+
+            return Err(err);
         }
 
-        let response: Response = response.json().await?;
-        tracing::trace!("get_state response {:?}", response);
+        tracing::debug!(
+            "Reported {:?} progress {:.1}% for storage_provider_id: {}, sector_id: {}",
+            request.progress.stage,
+            request.progress.percent,
+            storage_provider_id.0,
+            sector_id.0
+        );
+
+        Ok(())
+    }
+
+    async fn cancel_job<SealingJobT: SealingJob + 'static>(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        reason: Option<String>,
+    ) -> Result<(), Error> {
+        let request = CancelJob {
+            storage_provider_id,
+            sector_id,
+            job_type: SealingJobT::job_type(),
+            reason,
+        };
+        let body = serde_json::to_string(&request)?;
+        let response = self
+            .send_json(Operation::CancelJob, None, &body, || {
+                self.http_client.post(&self.cancel_job_uri)
+            })
+            .await?;
 
-        Ok(Some(response.state))
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!(
+                "Failed to cancel job for storage_provider_id: {}, sector_id: {}, response: {}",
+                storage_provider_id.0,
+                sector_id.0,
+                err,
+            );
+
+            return Err(err);
+        }
+
+        tracing::info!(
+            "Succesfully cancelled {} job for storage_provider_id: {}, sector_id: {}",
+            request.job_type,
+            storage_provider_id.0,
+            sector_id.0
+        );
+
+        Ok(())
+    }
+
+    fn watch_jobs<SealingJobT: SealingJob + 'static>(
+        &self,
+        filter: Filter,
+        cursor: Option<EventCursor>,
+    ) -> BoxStream<'static, Result<JobStateEvent, Error>> {
+        let job_type = SealingJobT::job_type().to_string();
+        let uri = self.watch_jobs_uri.replace(":job_type", job_type.as_str());
+
+        tracing::debug!(
+            "Watching {} jobs using filter {:?} from cursor {:?}",
+            job_type,
+            filter,
+            cursor
+        );
+
+        let request = match serde_json::to_value(WatchJobs { filter, cursor }) {
+            Ok(request) => request,
+            Err(err) => return stream::once(async { Err(err.into()) }).boxed(),
+        };
+
+        subscription::event_stream(
+            self,
+            Operation::WatchJobs,
+            uri,
+            request,
+            subscription::STATE_EVENT,
+            ReconnectBackoff::default(),
+            cursor.map(|cursor| cursor.0.to_string()),
+        )
+        .boxed()
+    }
+
+    async fn get_job_history(&self, job: JobRef) -> Result<Option<JobHistory>, Error> {
+        let uri = match job {
+            JobRef::Sector {
+                storage_provider_id,
+                sector_id,
+                job_type,
+            } => self
+                .get_job_history_uri
+                .replace(":storage_provider_id", &storage_provider_id.0.to_string())
+                .replace(":sector_id", &sector_id.0.to_string())
+                .replace(":job_type", &job_type.to_string()),
+            JobRef::Id(job_id) => self
+                .get_job_history_by_id_uri
+                .replace(":job_id", &job_id.0.to_string()),
+        };
+
+        tracing::debug!("Requesting history of {:?}", job);
+        let response = self
+            .send(Operation::GetJobHistory, None, || {
+                self.http_client
+                    .get(&uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
+            .await?;
+
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while fetching job history: {}", err);
+            return Err(err);
+        }
+
+        let history: JobHistory = response.json().await?;
+        tracing::trace!("get_job_history response {:?}", history);
+
+        Ok(Some(history))
+    }
+
+    async fn register_worker(&self, info: WorkerInfo) -> Result<WorkerId, Error> {
+        let body = serde_json::to_string(&info)?;
+        let response = self
+            .send_json(
+                Operation::RegisterWorker,
+                Some(retry::digest_key(&body)),
+                &body,
+                || self.http_client.post(&self.register_worker_uri),
+            )
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Failed to register worker {}: {}", info.hostname, err);
+            return Err(err);
+        }
+
+        let response: RegisterWorkerResponse = response.json().await?;
+        *self.worker_id.write().unwrap() = Some(response.worker_id);
+
+        tracing::info!(
+            "Registered worker {} as {}",
+            info.hostname,
+            response.worker_id.0
+        );
+
+        Ok(response.worker_id)
+    }
+
+    async fn list_workers(&self) -> Result<Vec<RegisteredWorker>, Error> {
+        let response = self
+            .send(Operation::ListWorkers, None, || {
+                self.http_client
+                    .get(&self.list_workers_uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while listing workers: {}", err);
+            return Err(err);
+        }
+
+        let response: ListWorkersResponse = response.json().await?;
+        tracing::trace!("list_workers response {:?}", response);
+
+        Ok(response.workers)
+    }
+
+    async fn generate_ticket(
+        &self,
+        storage_provider_id: StorageProviderId,
+    ) -> Result<GeneratedTicket, Error> {
+        let request = GenerateTicket {
+            storage_provider_id,
+        };
+        let uri = self.generate_ticket_uri.replace(
+            ":storage_provider_id",
+            &request.storage_provider_id.0.to_string(),
+        );
+
+        tracing::debug!(
+            "Generating ticket for storage_provider_id: {}",
+            storage_provider_id.0
+        );
+        let response = self
+            .send(Operation::GenerateTicket, None, || {
+                self.http_client
+                    .get(&uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while generating ticket: {}", err);
+            return Err(err);
+        }
+
+        let response: GeneratedTicket = response.json().await?;
+        tracing::trace!("generate_ticket response {:?}", response);
+
+        Ok(response)
+    }
+
+    async fn get_sector_paths(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<SectorPaths, Error> {
+        let request = GetSectorPaths {
+            storage_provider_id,
+            sector_id,
+        };
+        let uri = self
+            .get_sector_paths_uri
+            .replace(
+                ":storage_provider_id",
+                &request.storage_provider_id.0.to_string(),
+            )
+            .replace(":sector_id", &request.sector_id.0.to_string());
+
+        let response = self
+            .send(Operation::GetSectorPaths, None, || {
+                self.http_client
+                    .get(&uri)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+            })
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Error while fetching sector paths: {}", err);
+            return Err(err);
+        }
+
+        let response: SectorPaths = response.json().await?;
+        tracing::trace!("get_sector_paths response {:?}", response);
+
+        Ok(response)
+    }
+
+    async fn add_jobs(&self, jobs: Vec<JobHttp>) -> Result<BatchResult<()>, Error> {
+        tracing::debug!("Adding batch of {} jobs", jobs.len());
+        let jobs: Vec<JobEnvelope> = jobs
+            .into_iter()
+            .map(|job| {
+                let mut job = JobEnvelope::from(job);
+                trace::inject(&mut job);
+                job
+            })
+            .collect();
+
+        self.post_batch(Operation::AddJobs, &self.add_jobs_batch_uri, jobs)
+            .await
+    }
+
+    async fn submit_job_outputs(
+        &self,
+        outputs: Vec<SubmitSealingJobOutput>,
+    ) -> Result<BatchResult<()>, Error> {
+        tracing::debug!("Submitting batch of {} job outputs", outputs.len());
+
+        let job_types: Vec<_> = outputs.iter().map(|output| output.job.job_type()).collect();
+        let results = self
+            .post_batch(
+                Operation::SubmitOutputs,
+                &self.submit_outputs_batch_uri,
+                outputs,
+            )
+            .await?;
+
+        let mut submitted = HashMap::new();
+        for (job_type, result) in job_types.into_iter().zip(&results) {
+            if result.is_ok() {
+                *submitted.entry(job_type).or_insert(0) += 1;
+            }
+        }
+        for (job_type, count) in submitted {
+            self.metrics.on_jobs_submitted(job_type, count);
+        }
+
+        Ok(results)
+    }
+
+    async fn get_job_states<SealingJobT: SealingJob + 'static>(
+        &self,
+        jobs: Vec<(StorageProviderId, SectorId)>,
+    ) -> Result<BatchResult<Option<JobStatus>>, Error> {
+        let job_type = SealingJobT::job_type().to_string();
+        let uri = self
+            .get_job_states_batch_uri
+            .replace(":job_type", job_type.as_str());
+
+        tracing::debug!("Requesting {} states for {} jobs", job_type, jobs.len());
+
+        let keys: Vec<JobKey> = jobs.into_iter().map(Into::into).collect();
+        self.post_batch(Operation::GetJobStates, &uri, keys).await
+    }
+
+    async fn get_job_outputs<SealingJobT: SealingJob + 'static>(
+        &self,
+        jobs: Vec<(StorageProviderId, SectorId)>,
+    ) -> Result<BatchResult<Option<JobOutput<SealingJobT>>>, Error>
+    where
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = Error>,
+    {
+        let job_type = SealingJobT::job_type().to_string();
+        let uri = self
+            .get_outputs_batch_uri
+            .replace(":job_type", job_type.as_str());
+
+        tracing::debug!("Requesting {} outputs for {} jobs", job_type, jobs.len());
+
+        let keys: Vec<JobKey> = jobs.into_iter().map(Into::into).collect();
+        let results: BatchResult<JobOutputEntry> = self
+            .post_batch(Operation::GetJobOutputs, &uri, keys)
+            .await?;
+
+        // An output that doesn't decode only fails its own item.
+        Ok(results
+            .into_iter()
+            .map(|result| match result {
+                Ok(JobOutputEntry::Missing) => Ok(None),
+                Ok(JobOutputEntry::Failed { err }) => Ok(Some(JobOutput(Err(err)))),
+                Ok(JobOutputEntry::Done { output }) => SealingJobT::Output::try_from(output)
+                    .map(|output| Some(JobOutput(Ok(output))))
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{
+        response::IntoResponse,
+        routing::{get, post},
+        Json, Router,
+    };
+    use filecoin_spec::{ChainEpoch, Ticket};
+    use job::sealing::{PC1Output, PC2Output, PC1};
+
+    use super::*;
+    use crate::retry::Idempotency;
+
+    #[tokio::test]
+    async fn test_get_job_outputs_unexpected_job_type() {
+        assert!(matches!(
+            PC1Output::try_from(JobOutputHttp::PC2(PC2Output::from(vec![2]))),
+            Err(Error::UnexpectedJobType {
+                expected: JobType::PC1,
+                got: JobType::PC2
+            })
+        ));
+
+        let router = Router::new().route(
+            GET_OUTPUTS_BATCH_URL,
+            post(|| async {
+                Json(BatchResponse {
+                    results: vec![
+                        Ok(JobOutputEntry::Done {
+                            output: JobOutputHttp::PC2(PC2Output::from(vec![2])),
+                        }),
+                        Ok(JobOutputEntry::Done {
+                            output: JobOutputHttp::PC1(PC1Output::from(vec![1])),
+                        }),
+                        Ok(JobOutputEntry::Missing),
+                    ],
+                })
+            }),
+        );
+        let server =
+            axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(router.into_make_service());
+        let client = SealingJobManagerHttpClient::new(format!("http://{}", server.local_addr()));
+        tokio::spawn(server);
+
+        let sp = StorageProviderId(1000);
+        let results = client
+            .get_job_outputs::<PC1>(vec![
+                (sp, SectorId(1)),
+                (sp, SectorId(2)),
+                (sp, SectorId(3)),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(
+            results[0].as_ref().err(),
+            Some(
+                &Error::UnexpectedJobType {
+                    expected: JobType::PC1,
+                    got: JobType::PC2
+                }
+                .to_string()
+            )
+        );
+        assert!(matches!(&results[1], Ok(Some(JobOutput(Ok(output)))) if output.0 == vec![1]));
+        assert!(matches!(results[2], Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_generate_ticket_is_not_retried() {
+        assert_eq!(Operation::GenerateTicket.idempotency(), Idempotency::Unsafe);
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let router = Router::new().route(
+            GENERATE_TICKET_URL,
+            get(move || async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                }
+                Json(GeneratedTicket {
+                    ticket: Ticket([7; 32]),
+                    ticket_epoch: ChainEpoch(42),
+                })
+                .into_response()
+            }),
+        );
+        let server =
+            axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(router.into_make_service());
+        let client = SealingJobManagerHttpClient::new(format!("http://{}", server.local_addr()));
+        tokio::spawn(server);
+
+        // A retry could draw a second ticket, so the failure is returned as is.
+        assert!(matches!(
+            client.generate_ticket(StorageProviderId(1000)).await,
+            Err(Error::Server(_))
+        ));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        assert_eq!(
+            client
+                .generate_ticket(StorageProviderId(1000))
+                .await
+                .unwrap(),
+            GeneratedTicket {
+                ticket: Ticket([7; 32]),
+                ticket_epoch: ChainEpoch(42),
+            }
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
     }
 }
@@ -0,0 +1,360 @@
+use std::{collections::HashMap, fmt::Write, sync::Mutex, time::Duration};
+
+use hyper::StatusCode;
+use job::JobType;
+
+use crate::{retry::Operation, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorCategory {
+    /// The request never got a response, e.g. the connection was refused.
+    Transport,
+    Timeout,
+    CircuitOpen,
+    NotFound,
+    Conflict,
+    Validation,
+    Unauthorized,
+    RateLimited,
+    Server,
+    /// The response couldn't be decoded.
+    Decode,
+    Other,
+}
+
+impl ErrorCategory {
+    /// Category of the error a response with `status` turns into, `None` for
+    /// successful responses.
+    pub fn from_status(status: StatusCode) -> Option<Self> {
+        if !status.is_client_error() && !status.is_server_error() {
+            return None;
+        }
+
+        Some(match status {
+            StatusCode::NOT_FOUND => ErrorCategory::NotFound,
+            StatusCode::CONFLICT => ErrorCategory::Conflict,
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => ErrorCategory::Validation,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorCategory::Unauthorized,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCategory::RateLimited,
+            _ => ErrorCategory::Server,
+        })
+    }
+}
+
+impl Error {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::Reqwest(err) if err.is_timeout() => ErrorCategory::Timeout,
+            Error::Reqwest(err) if err.is_decode() => ErrorCategory::Decode,
+            Error::Reqwest(_) | Error::Io(_) => ErrorCategory::Transport,
+            Error::Json(_) => ErrorCategory::Decode,
+            Error::CircuitOpen => ErrorCategory::CircuitOpen,
+            Error::NotFound(_) => ErrorCategory::NotFound,
+            Error::Conflict(_) | Error::AlreadySubmitted(_) => ErrorCategory::Conflict,
+            Error::Validation(_) => ErrorCategory::Validation,
+            Error::Unauthorized(_) => ErrorCategory::Unauthorized,
+            Error::RateLimited { .. } => ErrorCategory::RateLimited,
+            Error::Server(_) => ErrorCategory::Server,
+            _ => ErrorCategory::Other,
+        }
+    }
+}
+
+/// What a single request to the job manager took, retries included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestMetrics {
+    pub latency: Duration,
+    pub request_bytes: u64,
+    /// `None` when there was no response or it didn't say its length.
+    pub response_bytes: Option<u64>,
+    pub status: Option<StatusCode>,
+}
+
+/// Hooks for exporting client behaviour as metrics, see [`PrometheusObserver`].
+pub trait MetricsObserver: Send + Sync {
+    fn on_request(&self, _operation: Operation, _metrics: &RequestMetrics) {}
+    fn on_error(&self, _operation: Operation, _category: ErrorCategory) {}
+    fn on_jobs_fetched(&self, _job_type: JobType, _count: usize) {}
+    fn on_jobs_submitted(&self, _job_type: JobType, _count: usize) {}
+}
+
+pub struct NoopMetricsObserver;
+
+impl MetricsObserver for NoopMetricsObserver {}
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+struct RequestStats {
+    count: u64,
+    /// Non-cumulative, `render` adds them up.
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    request_bytes: u64,
+    response_bytes: u64,
+}
+
+#[derive(Default)]
+struct Metrics {
+    requests: HashMap<Operation, RequestStats>,
+    errors: HashMap<(Operation, ErrorCategory), u64>,
+    jobs_fetched: HashMap<JobType, u64>,
+    jobs_submitted: HashMap<JobType, u64>,
+}
+
+/// Keeps metrics in memory and renders them in the Prometheus text format,
+/// to be served from a worker's `/metrics` endpoint.
+#[derive(Default)]
+pub struct PrometheusObserver {
+    metrics: Mutex<Metrics>,
+}
+
+impl PrometheusObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let mut out = String::new();
+
+        let mut requests: Vec<_> = metrics
+            .requests
+            .iter()
+            .map(|(operation, stats)| (format!("{:?}", operation), stats))
+            .collect();
+        requests.sort_by(|a, b| a.0.cmp(&b.0));
+
+        header(
+            &mut out,
+            "job_client_requests_total",
+            "counter",
+            "Requests sent to the job manager.",
+        );
+        for (operation, stats) in &requests {
+            sample(
+                &mut out,
+                "job_client_requests_total",
+                &format!("operation=\"{}\"", operation),
+                stats.count,
+            );
+        }
+
+        header(
+            &mut out,
+            "job_client_request_duration_seconds",
+            "histogram",
+            "Latency of requests to the job manager, retries included.",
+        );
+        for (operation, stats) in &requests {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(stats.latency_buckets) {
+                cumulative += count;
+                sample(
+                    &mut out,
+                    "job_client_request_duration_seconds_bucket",
+                    &format!("operation=\"{}\",le=\"{}\"", operation, bound),
+                    cumulative,
+                );
+            }
+            sample(
+                &mut out,
+                "job_client_request_duration_seconds_bucket",
+                &format!("operation=\"{}\",le=\"+Inf\"", operation),
+                stats.count,
+            );
+            sample(
+                &mut out,
+                "job_client_request_duration_seconds_sum",
+                &format!("operation=\"{}\"", operation),
+                stats.latency_sum,
+            );
+            sample(
+                &mut out,
+                "job_client_request_duration_seconds_count",
+                &format!("operation=\"{}\"", operation),
+                stats.count,
+            );
+        }
+
+        header(
+            &mut out,
+            "job_client_request_bytes_total",
+            "counter",
+            "Request body bytes sent to the job manager.",
+        );
+        for (operation, stats) in &requests {
+            sample(
+                &mut out,
+                "job_client_request_bytes_total",
+                &format!("operation=\"{}\"", operation),
+                stats.request_bytes,
+            );
+        }
+
+        header(
+            &mut out,
+            "job_client_response_bytes_total",
+            "counter",
+            "Response body bytes received from the job manager.",
+        );
+        for (operation, stats) in &requests {
+            sample(
+                &mut out,
+                "job_client_response_bytes_total",
+                &format!("operation=\"{}\"", operation),
+                stats.response_bytes,
+            );
+        }
+
+        let mut errors: Vec<_> = metrics
+            .errors
+            .iter()
+            .map(|((operation, category), count)| {
+                (
+                    format!("operation=\"{:?}\",category=\"{:?}\"", operation, category),
+                    *count,
+                )
+            })
+            .collect();
+        errors.sort();
+        header(
+            &mut out,
+            "job_client_errors_total",
+            "counter",
+            "Failed requests to the job manager by error category.",
+        );
+        for (labels, count) in errors {
+            sample(&mut out, "job_client_errors_total", &labels, count);
+        }
+
+        for (name, help, jobs) in [
+            (
+                "job_client_jobs_fetched_total",
+                "Jobs claimed from the job manager.",
+                &metrics.jobs_fetched,
+            ),
+            (
+                "job_client_jobs_submitted_total",
+                "Job outputs submitted to the job manager.",
+                &metrics.jobs_submitted,
+            ),
+        ] {
+            let mut jobs: Vec<_> = jobs
+                .iter()
+                .map(|(job_type, count)| (format!("job_type=\"{}\"", job_type), *count))
+                .collect();
+            jobs.sort();
+            header(&mut out, name, "counter", help);
+            for (labels, count) in jobs {
+                sample(&mut out, name, &labels, count);
+            }
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, metric_type).unwrap();
+}
+
+fn sample(out: &mut String, name: &str, labels: &str, value: impl std::fmt::Display) {
+    writeln!(out, "{}{{{}}} {}", name, labels, value).unwrap();
+}
+
+impl MetricsObserver for PrometheusObserver {
+    fn on_request(&self, operation: Operation, request: &RequestMetrics) {
+        let mut metrics = self.metrics.lock().unwrap();
+        let stats = metrics.requests.entry(operation).or_default();
+        let latency = request.latency.as_secs_f64();
+
+        stats.count += 1;
+        stats.latency_sum += latency;
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| latency <= *bound) {
+            stats.latency_buckets[bucket] += 1;
+        }
+        stats.request_bytes += request.request_bytes;
+        stats.response_bytes += request.response_bytes.unwrap_or(0);
+    }
+
+    fn on_error(&self, operation: Operation, category: ErrorCategory) {
+        *self
+            .metrics
+            .lock()
+            .unwrap()
+            .errors
+            .entry((operation, category))
+            .or_default() += 1;
+    }
+
+    fn on_jobs_fetched(&self, job_type: JobType, count: usize) {
+        *self
+            .metrics
+            .lock()
+            .unwrap()
+            .jobs_fetched
+            .entry(job_type)
+            .or_default() += count as u64;
+    }
+
+    fn on_jobs_submitted(&self, job_type: JobType, count: usize) {
+        *self
+            .metrics
+            .lock()
+            .unwrap()
+            .jobs_submitted
+            .entry(job_type)
+            .or_default() += count as u64;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_render() {
+        let observer = PrometheusObserver::new();
+        for latency in [3, 30, 20_000] {
+            observer.on_request(
+                Operation::RequestJobs,
+                &RequestMetrics {
+                    latency: Duration::from_millis(latency),
+                    request_bytes: 10,
+                    response_bytes: Some(100),
+                    status: Some(StatusCode::OK),
+                },
+            );
+        }
+        observer.on_error(Operation::SubmitOutput, ErrorCategory::Conflict);
+        observer.on_jobs_fetched(JobType::PC1, 2);
+        observer.on_jobs_submitted(JobType::C2, 1);
+
+        let text = observer.render();
+        for line in [
+            "job_client_requests_total{operation=\"RequestJobs\"} 3",
+            "job_client_request_duration_seconds_bucket{operation=\"RequestJobs\",le=\"0.005\"} 1",
+            "job_client_request_duration_seconds_bucket{operation=\"RequestJobs\",le=\"10\"} 2",
+            "job_client_request_duration_seconds_bucket{operation=\"RequestJobs\",le=\"+Inf\"} 3",
+            "job_client_request_bytes_total{operation=\"RequestJobs\"} 30",
+            "job_client_response_bytes_total{operation=\"RequestJobs\"} 300",
+            "job_client_errors_total{operation=\"SubmitOutput\",category=\"Conflict\"} 1",
+            "job_client_jobs_fetched_total{job_type=\"PC1\"} 2",
+            "job_client_jobs_submitted_total{job_type=\"C2\"} 1",
+            "# TYPE job_client_request_duration_seconds histogram",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+
+        assert_eq!(
+            ErrorCategory::from_status(StatusCode::SERVICE_UNAVAILABLE),
+            Some(ErrorCategory::Server)
+        );
+        assert_eq!(ErrorCategory::from_status(StatusCode::NO_CONTENT), None);
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+
+use filecoin_spec::{RegisteredSealProof, SectorId, StorageProviderId};
+use futures::{stream, Stream};
+use job::sealing::SealingJob;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Filter, JobHttp, JobState, SealingJobManagerClient};
+
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Sector ids from `start` up to and including `end`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectorRange {
+    pub start: SectorId,
+    pub end: SectorId,
+}
+
+/// Milliseconds since the unix epoch, both bounds are optional and inclusive.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    pub from_ms: Option<u64>,
+    pub to_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    StorageProviderId,
+    SectorId,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub field: SortField,
+    pub order: SortOrder,
+}
+
+/// Opaque position returned by the server, only valid for the query that produced it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageCursor(pub String);
+
+impl PageCursor {
+    /// Position after the job with sort key `key` and sequence number `seq`,
+    /// so jobs added or removed meanwhile don't shift the next page.
+    pub fn after(key: u64, seq: u64) -> Self {
+        Self(format!("{key}:{seq}"))
+    }
+
+    /// Sort key and sequence number of the last job of the previous page.
+    pub fn position(&self) -> Option<(u64, u64)> {
+        let (key, seq) = self.0.split_once(':')?;
+        Some((key.parse().ok()?, seq.parse().ok()?))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JobQuery {
+    pub storage_provider_ids: Vec<StorageProviderId>,
+    pub sector_ids: Vec<SectorId>,
+    pub sector_range: Option<SectorRange>,
+    pub states: Vec<JobState>,
+    pub registered_proofs: Vec<RegisteredSealProof>,
+    pub created: Option<TimeRange>,
+    pub updated: Option<TimeRange>,
+    pub sort: Option<Sort>,
+    pub page_size: u32,
+    pub cursor: Option<PageCursor>,
+}
+
+impl Default for JobQuery {
+    fn default() -> Self {
+        Self {
+            storage_provider_ids: vec![],
+            sector_ids: vec![],
+            sector_range: None,
+            states: vec![],
+            registered_proofs: vec![],
+            created: None,
+            updated: None,
+            sort: None,
+            page_size: DEFAULT_PAGE_SIZE,
+            cursor: None,
+        }
+    }
+}
+
+impl JobQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn storage_provider(mut self, storage_provider_id: StorageProviderId) -> Self {
+        self.storage_provider_ids.push(storage_provider_id);
+        self
+    }
+
+    pub fn sector(mut self, sector_id: SectorId) -> Self {
+        self.sector_ids.push(sector_id);
+        self
+    }
+
+    pub fn sector_range(mut self, start: SectorId, end: SectorId) -> Self {
+        self.sector_range = Some(SectorRange { start, end });
+        self
+    }
+
+    pub fn state(mut self, state: JobState) -> Self {
+        self.states.push(state);
+        self
+    }
+
+    pub fn registered_proof(mut self, registered_proof: RegisteredSealProof) -> Self {
+        self.registered_proofs.push(registered_proof);
+        self
+    }
+
+    pub fn created(mut self, from_ms: Option<u64>, to_ms: Option<u64>) -> Self {
+        self.created = Some(TimeRange { from_ms, to_ms });
+        self
+    }
+
+    pub fn updated(mut self, from_ms: Option<u64>, to_ms: Option<u64>) -> Self {
+        self.updated = Some(TimeRange { from_ms, to_ms });
+        self
+    }
+
+    pub fn sort_by(mut self, field: SortField, order: SortOrder) -> Self {
+        self.sort = Some(Sort { field, order });
+        self
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    pub fn cursor(mut self, cursor: PageCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+}
+
+impl From<Filter> for JobQuery {
+    fn from(filter: Filter) -> Self {
+        Self {
+            storage_provider_ids: filter.storage_provider_id.into_iter().collect(),
+            sector_ids: filter.sector_id.into_iter().collect(),
+            states: filter.state.into_iter().collect(),
+            registered_proofs: filter.registered_proof.into_iter().collect(),
+            page_size: filter.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `None` on the last page.
+    pub next_cursor: Option<PageCursor>,
+}
+
+pub type JobHttpPage = Page<JobHttp>;
+
+/// Streams every job matching `query`, fetching the next page only once the
+/// previous one has been consumed.
+pub fn query_jobs_stream<SealingJobT, Client>(
+    client: &Client,
+    query: JobQuery,
+) -> impl Stream<Item = Result<SealingJobT, Error>> + '_
+where
+    SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static,
+    Client: SealingJobManagerClient,
+{
+    struct State<T> {
+        query: Option<JobQuery>,
+        items: VecDeque<T>,
+    }
+
+    let state = State {
+        query: Some(query),
+        items: VecDeque::new(),
+    };
+
+    stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                return Some((Ok(item), state));
+            }
+
+            let query = state.query.take()?;
+            let page = match client.query_jobs::<SealingJobT>(query.clone()).await {
+                Ok(page) => page,
+                Err(err) => return Some((Err(err), state)),
+            };
+
+            state.query = page.next_cursor.map(|cursor| query.cursor(cursor));
+            state.items = page.items.into();
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MockSealingJobManagerClient;
+    use futures::TryStreamExt;
+    use job::{sealing::PC1, test_utils::pc1};
+
+    #[tokio::test]
+    async fn test_query_jobs_stream_follows_cursor() {
+        let mut client = MockSealingJobManagerClient::new();
+        client
+            .expect_query_jobs::<PC1>()
+            .withf(|query| query.cursor.is_none())
+            .times(1)
+            .returning(|_| {
+                Ok(Page {
+                    items: vec![pc1(1), pc1(2)],
+                    next_cursor: Some(PageCursor::after(2, 2)),
+                })
+            });
+        client
+            .expect_query_jobs::<PC1>()
+            .withf(|query| query.cursor == Some(PageCursor::after(2, 2)))
+            .times(1)
+            .returning(|_| {
+                Ok(Page {
+                    items: vec![pc1(3)],
+                    next_cursor: None,
+                })
+            });
+
+        let jobs: Vec<PC1> = query_jobs_stream(&client, JobQuery::new().page_size(2))
+            .try_collect()
+            .await
+            .unwrap();
+
+        let sectors: Vec<u64> = jobs.iter().map(|job| job.input.sector_id.0).collect();
+        assert_eq!(sectors, vec![1, 2, 3]);
+    }
+}
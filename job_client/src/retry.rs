@@ -0,0 +1,449 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::StatusCode;
+use rand::Rng;
+
+use crate::{api_error, Error};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Idempotency key for requests without a natural job id, derived from the body.
+pub fn digest_key(body: &str) -> String {
+    blake3::hash(body.as_bytes()).to_hex().to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    AddJob,
+    AddJobs,
+    RequestJobs,
+    FilterJobs,
+    QueryJobs,
+    GetJobInput,
+    SubmitOutput,
+    SubmitOutputs,
+    GetJobOutput,
+    GetJobOutputs,
+    FailJob,
+    ReleaseJob,
+    RetryJob,
+    GetJobState,
+    GetJobStates,
+    ReportProgress,
+    CancelJob,
+    GetJobHistory,
+    RegisterWorker,
+    ListWorkers,
+    GenerateTicket,
+    GetSectorPaths,
+    SubscribeJobs,
+    WatchJobs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    /// Repeating the request has no additional effect.
+    Safe,
+    /// Only safe when the server can deduplicate it by an idempotency key.
+    WithKey,
+    /// Repeating the request changes server state, e.g. claims more jobs.
+    Unsafe,
+}
+
+impl Operation {
+    /// Whether `status` is an answer the operation handles rather than an
+    /// error, e.g. the 424 of a failed job's output.
+    pub fn expects_status(&self, status: StatusCode) -> bool {
+        match self {
+            Operation::GetJobOutput => status == StatusCode::FAILED_DEPENDENCY,
+            _ => false,
+        }
+    }
+
+    pub fn idempotency(&self) -> Idempotency {
+        match self {
+            Operation::FilterJobs
+            | Operation::QueryJobs
+            | Operation::GetJobInput
+            | Operation::GetJobOutput
+            | Operation::GetJobOutputs
+            | Operation::GetJobState
+            | Operation::GetJobStates
+            | Operation::ReportProgress
+            | Operation::ReleaseJob
+            | Operation::CancelJob
+            | Operation::GetJobHistory
+            | Operation::ListWorkers
+            | Operation::GetSectorPaths
+            // A connect that failed delivered no events, event streams resume
+            // from the last event id.
+            | Operation::SubscribeJobs
+            | Operation::WatchJobs => Idempotency::Safe,
+            Operation::AddJob
+            | Operation::AddJobs
+            | Operation::SubmitOutput
+            | Operation::SubmitOutputs
+            | Operation::FailJob
+            | Operation::RetryJob
+            | Operation::RegisterWorker => Idempotency::WithKey,
+            // Every ticket is fresh randomness, a repeat hands out another one.
+            Operation::RequestJobs | Operation::GenerateTicket => Idempotency::Unsafe,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, `1` disables retries.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Fraction of the backoff randomized, in the `0.0..=1.0` range.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .mul_f64(self.multiplier.powi(attempt.saturating_sub(1) as i32))
+            .min(self.max_backoff);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+
+        backoff.mul_f64(rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RetryPolicies {
+    pub default: RetryPolicy,
+    pub overrides: HashMap<Operation, RetryPolicy>,
+}
+
+impl RetryPolicies {
+    pub fn with(mut self, operation: Operation, policy: RetryPolicy) -> Self {
+        self.overrides.insert(operation, policy);
+        self
+    }
+
+    pub fn policy(&self, operation: Operation) -> &RetryPolicy {
+        self.overrides.get(&operation).unwrap_or(&self.default)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures after which the circuit opens.
+    pub failure_threshold: u32,
+    /// How long requests fail fast before a probe request is let through.
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Hooks for exporting retry behaviour as metrics.
+pub trait RetryObserver: Send + Sync {
+    fn on_retry(&self, _operation: Operation, _attempt: u32, _delay: Duration) {}
+    fn on_give_up(&self, _operation: Operation, _attempts: u32) {}
+    fn on_circuit_open(&self) {}
+    fn on_circuit_rejected(&self, _operation: Operation) {}
+}
+
+pub struct NoopRetryObserver;
+
+impl RetryObserver for NoopRetryObserver {}
+
+#[derive(Clone, Default)]
+pub struct RetryConfig {
+    pub policies: RetryPolicies,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub observer: Option<Arc<dyn RetryObserver>>,
+}
+
+#[derive(Debug)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<CircuitState>,
+}
+
+impl CircuitBreaker {
+    /// `None` while the circuit is open or a probe request is in flight.
+    fn allow(&self) -> Option<Permit<'_>> {
+        let mut state = self.state.lock().unwrap();
+        let probe = match *state {
+            CircuitState::Closed { .. } => false,
+            CircuitState::Open { until } if Instant::now() >= until => {
+                *state = CircuitState::HalfOpen;
+                true
+            }
+            CircuitState::Open { .. } => return None,
+            // A probe request is already in flight.
+            CircuitState::HalfOpen => return None,
+        };
+
+        Some(Permit {
+            breaker: self,
+            probe,
+            recorded: false,
+        })
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = CircuitState::Closed { failures: 0 };
+    }
+
+    /// Returns `true` when this failure opened the circuit.
+    fn record_failure(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            CircuitState::Closed { failures } => failures + 1,
+            CircuitState::HalfOpen => self.config.failure_threshold,
+            CircuitState::Open { .. } => return false,
+        };
+
+        if failures >= self.config.failure_threshold {
+            *state = CircuitState::Open {
+                until: Instant::now() + self.config.open_duration,
+            };
+            return true;
+        }
+
+        *state = CircuitState::Closed { failures };
+        false
+    }
+}
+
+/// Lets one request through the breaker. A probe dropped before its outcome
+/// is recorded, e.g. because the caller gave up on the request, counts as a
+/// failure so the circuit doesn't stay half open.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Permit<'_> {
+    fn success(mut self) {
+        self.recorded = true;
+        self.breaker.record_success();
+    }
+
+    /// Returns `true` when this failure opened the circuit.
+    fn failure(mut self) -> bool {
+        self.recorded = true;
+        self.breaker.record_failure()
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.breaker.record_failure();
+        }
+    }
+}
+
+pub(crate) struct Retrier {
+    policies: RetryPolicies,
+    circuit_breaker: Option<CircuitBreaker>,
+    observer: Arc<dyn RetryObserver>,
+}
+
+impl From<RetryConfig> for Retrier {
+    fn from(config: RetryConfig) -> Self {
+        Self {
+            policies: config.policies,
+            circuit_breaker: config.circuit_breaker.map(|config| CircuitBreaker {
+                config,
+                state: Mutex::new(CircuitState::Closed { failures: 0 }),
+            }),
+            observer: config
+                .observer
+                .unwrap_or_else(|| Arc::new(NoopRetryObserver)),
+        }
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+            | StatusCode::INTERNAL_SERVER_ERROR
+    )
+}
+
+impl Retrier {
+    /// Sends the request built by `send`, retrying transient failures as
+    /// allowed by the operation's policy and idempotency.
+    pub(crate) async fn execute<F, Fut>(
+        &self,
+        operation: Operation,
+        has_idempotency_key: bool,
+        send: F,
+    ) -> Result<reqwest::Response, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = reqwest::Result<reqwest::Response>>,
+    {
+        let policy = self.policies.policy(operation);
+        let can_repeat = match operation.idempotency() {
+            Idempotency::Safe => true,
+            Idempotency::WithKey => has_idempotency_key,
+            Idempotency::Unsafe => false,
+        };
+
+        let mut attempt = 1;
+        loop {
+            let permit = match &self.circuit_breaker {
+                Some(breaker) => match breaker.allow() {
+                    Some(permit) => Some(permit),
+                    None => {
+                        self.observer.on_circuit_rejected(operation);
+                        return Err(Error::CircuitOpen);
+                    }
+                },
+                None => None,
+            };
+
+            let result = send().await;
+            let retryable = match &result {
+                Ok(response) => is_retryable_status(response.status()) && can_repeat,
+                // The request never reached the server.
+                Err(err) if err.is_connect() => true,
+                Err(err) => err.is_timeout() && can_repeat,
+            };
+            let failed = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(_) => true,
+            };
+
+            if let Some(permit) = permit {
+                if !failed {
+                    permit.success();
+                } else if permit.failure() {
+                    tracing::warn!("Job manager circuit opened after {:?} failure", operation);
+                    self.observer.on_circuit_open();
+                }
+            }
+
+            if !retryable {
+                return Ok(result?);
+            }
+
+            if attempt >= policy.max_attempts {
+                if policy.max_attempts > 1 {
+                    self.observer.on_give_up(operation, attempt);
+                }
+                return Ok(result?);
+            }
+
+            let delay = match &result {
+                Ok(response) => api_error::retry_after(response)
+                    .map_or(policy.backoff(attempt), |retry_after| {
+                        retry_after.max(policy.backoff(attempt))
+                    }),
+                Err(_) => policy.backoff(attempt),
+            };
+            match &result {
+                Ok(response) => tracing::warn!(
+                    "{:?} attempt {} returned {}, retrying in {:?}",
+                    operation,
+                    attempt,
+                    response.status(),
+                    delay
+                ),
+                Err(err) => tracing::warn!(
+                    "{:?} attempt {} failed: {}, retrying in {:?}",
+                    operation,
+                    attempt,
+                    err,
+                    delay
+                ),
+            }
+            self.observer.on_retry(operation, attempt, delay);
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker_opens_and_probes() {
+        let breaker = CircuitBreaker {
+            config: CircuitBreakerConfig {
+                failure_threshold: 2,
+                open_duration: Duration::ZERO,
+            },
+            state: Mutex::new(CircuitState::Closed { failures: 0 }),
+        };
+
+        assert!(!breaker.record_failure());
+        assert!(breaker.record_failure());
+
+        // Open duration elapsed, a single probe goes through.
+        let probe = breaker.allow().unwrap();
+        assert!(breaker.allow().is_none());
+
+        // An abandoned probe reopens the circuit rather than blocking it.
+        drop(probe);
+        assert!(matches!(
+            *breaker.state.lock().unwrap(),
+            CircuitState::Open { .. }
+        ));
+
+        breaker.allow().unwrap().success();
+        assert!(breaker.allow().is_some());
+        assert!(breaker.allow().is_some());
+    }
+}
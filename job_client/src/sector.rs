@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+
+use filecoin_spec::{ChainEpoch, SectorId, StorageProviderId, Ticket};
+use serde::{Deserialize, Serialize};
+
+/// Mainnet genesis, in seconds since the unix epoch.
+const GENESIS_TIMESTAMP_SECS: u64 = 1598306400;
+const EPOCH_DURATION_SECS: u64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerateTicket {
+    pub storage_provider_id: StorageProviderId,
+}
+
+/// Sealing randomness for a new sector together with the epoch it was drawn at.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedTicket {
+    pub ticket: Ticket,
+    pub ticket_epoch: ChainEpoch,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetSectorPaths {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+}
+
+/// Where a sector's files live on the storage provider's storage.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SectorPaths {
+    pub unsealed: PathBuf,
+    pub sealed: PathBuf,
+    pub cache: PathBuf,
+}
+
+impl SectorPaths {
+    /// Lotus layout, e.g. `root/sealed/s-t01000-1`.
+    pub fn under(root: &Path, storage_provider_id: StorageProviderId, sector_id: SectorId) -> Self {
+        let name = format!("s-t0{}-{}", storage_provider_id.0, sector_id.0);
+
+        Self {
+            unsealed: root.join("unsealed").join(&name),
+            sealed: root.join("sealed").join(&name),
+            cache: root.join("cache").join(&name),
+        }
+    }
+}
+
+/// Mainnet epoch at `timestamp_ms` milliseconds since the unix epoch.
+pub fn mainnet_epoch(timestamp_ms: u64) -> ChainEpoch {
+    let secs = (timestamp_ms / 1000).saturating_sub(GENESIS_TIMESTAMP_SECS);
+    ChainEpoch((secs / EPOCH_DURATION_SECS) as i64)
+}
@@ -0,0 +1,294 @@
+use std::{
+    ops::Range,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use filecoin_spec::{SectorId, StorageProviderId};
+use futures::{Stream, StreamExt};
+use hyper::{http, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use super::{ByteStream, SectorFile, SectorFileInfo, SectorFileType, SectorStore};
+use crate::{api_error, auth, auth::Auth, Error};
+
+/// Offset a `PUT` appends at, the server rejects it when it isn't the current
+/// size of the file. Part of the upload protocol, see [`HttpSectorStore`].
+pub const UPLOAD_OFFSET_HEADER: &str = "Upload-Offset";
+
+const REMOTE_PATH: &str = "/remote/";
+
+/// Entry of a cache directory listing, returned for a `GET` on the cache
+/// directory with `Accept: application/json`. Part of the upload protocol,
+/// see [`HttpSectorStore`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Sector files behind an HTTP file server, where a sector file lives at
+/// `{base_url}/remote/{sealed|cache|unsealed}/s-t0{miner}-{sector}`.
+///
+/// Reads, `HEAD` and `DELETE` match lotus remote storage, so files can be read
+/// from the urls of `lotus::types::miner::SectorLocation`. Uploads, a `PUT`
+/// appending at [`UPLOAD_OFFSET_HEADER`], and JSON cache directory listings
+/// are a separate protocol lotus doesn't serve: `put` fails against lotus and
+/// `list` fails with [`Error::ListingNotSupported`] on the tar stream lotus
+/// returns for a cache directory.
+#[derive(Clone)]
+pub struct HttpSectorStore {
+    base_url: String,
+    http_client: reqwest::Client,
+    auth: Auth,
+}
+
+impl HttpSectorStore {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http_client: reqwest::Client::new(),
+            auth: Auth::None,
+        }
+    }
+
+    pub fn with_auth(mut self, auth: Auth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Splits a `SectorLocation` url into the store serving it and the file.
+    pub fn from_location_url(url: &str) -> Result<(Self, SectorFile), Error> {
+        let invalid = || Error::InvalidSectorFile(url.to_string());
+        let (base_url, path) = url.split_once(REMOTE_PATH).ok_or_else(invalid)?;
+        let mut parts = path.trim_end_matches('/').splitn(3, '/');
+
+        let file_type = match parts.next() {
+            Some("unsealed") => SectorFileType::Unsealed,
+            Some("sealed") => SectorFileType::Sealed,
+            Some("cache") => SectorFileType::Cache,
+            _ => return Err(invalid()),
+        };
+        let (storage_provider_id, sector_id) = parts
+            .next()
+            .and_then(|name| name.strip_prefix("s-t0"))
+            .and_then(|name| name.split_once('-'))
+            .and_then(|(miner, sector)| Some((miner.parse().ok()?, sector.parse().ok()?)))
+            .ok_or_else(invalid)?;
+
+        let file = SectorFile {
+            storage_provider_id: StorageProviderId(storage_provider_id),
+            sector_id: SectorId(sector_id),
+            file_type,
+            name: parts.next().map(str::to_string),
+        };
+        if file.name.is_some() {
+            file.validate()?;
+        }
+
+        Ok((Self::new(base_url), file))
+    }
+
+    pub fn file_url(&self, file: &SectorFile) -> String {
+        let mut url = format!(
+            "{}{}{}/{}",
+            self.base_url,
+            REMOTE_PATH,
+            file.file_type,
+            file.sector_name()
+        );
+        if let Some(name) = &file.name {
+            url.push('/');
+            url.push_str(name);
+        }
+
+        url
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        let token = self.auth.bearer_token().await?;
+        Ok(auth::with_bearer(request, &token).send().await?)
+    }
+
+    async fn size(&self, url: &str) -> Result<Option<u64>, Error> {
+        let response = self.send(self.http_client.head(url)).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            // `content_length` is the length of the body, which a HEAD
+            // response doesn't have.
+            StatusCode::OK => response
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Some)
+                .ok_or_else(|| Error::MissingContentLength(url.to_string())),
+            _ => Err(api_error::from_response(response).await),
+        }
+    }
+}
+
+fn body_stream(response: reqwest::Response) -> ByteStream {
+    response
+        .bytes_stream()
+        .map(|chunk| chunk.map_err(Error::from))
+        .boxed()
+}
+
+/// `reqwest` wants a `Sync` body, `ByteStream` is only `Send`.
+struct SyncStream(Mutex<ByteStream>);
+
+impl Stream for SyncStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().0.get_mut().unwrap().poll_next_unpin(cx)
+    }
+}
+
+#[async_trait]
+impl SectorStore for HttpSectorStore {
+    async fn put(&self, file: &SectorFile, offset: u64, body: ByteStream) -> Result<u64, Error> {
+        file.validate()?;
+        let sent = Arc::new(AtomicU64::new(0));
+        let counter = sent.clone();
+        let body = body.inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                counter.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            }
+        });
+
+        // The body can't be replayed, so unlike job manager calls this isn't retried.
+        let response = self
+            .send(
+                self.http_client
+                    .put(self.file_url(file))
+                    .header(UPLOAD_OFFSET_HEADER, offset)
+                    .header(http::header::CONTENT_TYPE, "application/octet-stream")
+                    .body(reqwest::Body::wrap_stream(SyncStream(Mutex::new(
+                        body.boxed(),
+                    )))),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            let err = api_error::from_response(response).await;
+            tracing::error!("Failed to upload {:?} at offset {}: {}", file, offset, err);
+            return Err(err);
+        }
+
+        Ok(offset + sent.load(Ordering::Relaxed))
+    }
+
+    async fn get(&self, file: &SectorFile) -> Result<ByteStream, Error> {
+        file.validate()?;
+        let response = self.send(self.http_client.get(self.file_url(file))).await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(api_error::from_response(response).await);
+        }
+
+        Ok(body_stream(response))
+    }
+
+    async fn read_range(&self, file: &SectorFile, range: Range<u64>) -> Result<ByteStream, Error> {
+        file.validate()?;
+        if range.is_empty() {
+            return Ok(futures::stream::empty().boxed());
+        }
+
+        let url = self.file_url(file);
+        let response = self
+            .send(self.http_client.get(&url).header(
+                http::header::RANGE,
+                format!("bytes={}-{}", range.start, range.end - 1),
+            ))
+            .await?;
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => Ok(body_stream(response)),
+            StatusCode::OK => Err(Error::RangeNotSupported(url)),
+            _ => Err(api_error::from_response(response).await),
+        }
+    }
+
+    async fn stat(&self, file: &SectorFile) -> Result<Option<SectorFileInfo>, Error> {
+        file.validate()?;
+
+        Ok(self
+            .size(&self.file_url(file))
+            .await?
+            .map(|size| SectorFileInfo {
+                file: file.clone(),
+                size,
+            }))
+    }
+
+    async fn list(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Vec<SectorFileInfo>, Error> {
+        let mut files = vec![];
+        for file in [
+            SectorFile::unsealed(storage_provider_id, sector_id),
+            SectorFile::sealed(storage_provider_id, sector_id),
+        ] {
+            files.extend(self.stat(&file).await?);
+        }
+
+        let cache = SectorFile {
+            storage_provider_id,
+            sector_id,
+            file_type: SectorFileType::Cache,
+            name: None,
+        };
+        let response = self
+            .send(
+                self.http_client
+                    .get(self.file_url(&cache))
+                    .header(http::header::ACCEPT, "application/json"),
+            )
+            .await?;
+
+        let is_json = response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        match response.status() {
+            StatusCode::NOT_FOUND => {}
+            StatusCode::OK if !is_json => {
+                return Err(Error::ListingNotSupported(self.file_url(&cache)))
+            }
+            StatusCode::OK => {
+                let entries: Vec<CacheEntry> = response.json().await?;
+                files.extend(entries.into_iter().map(|entry| SectorFileInfo {
+                    file: SectorFile::cache(storage_provider_id, sector_id, entry.name),
+                    size: entry.size,
+                }));
+            }
+            _ => return Err(api_error::from_response(response).await),
+        }
+
+        Ok(files)
+    }
+
+    async fn delete(&self, file: &SectorFile) -> Result<(), Error> {
+        file.validate()?;
+        let response = self
+            .send(self.http_client.delete(self.file_url(file)))
+            .await?;
+
+        match response.status() {
+            status if status.is_success() || status == StatusCode::NOT_FOUND => Ok(()),
+            _ => Err(api_error::from_response(response).await),
+        }
+    }
+}
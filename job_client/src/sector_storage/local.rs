@@ -0,0 +1,158 @@
+use std::{
+    io::{ErrorKind, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use filecoin_spec::{SectorId, StorageProviderId};
+use futures::StreamExt;
+use tokio::{
+    fs::{self, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+
+use super::{ByteStream, SectorFile, SectorFileInfo, SectorFileType, SectorStore};
+use crate::{sector::SectorPaths, Error};
+
+/// Sector files in the lotus layout under `root`, see [`SectorPaths::under`].
+pub struct LocalSectorStore {
+    root: PathBuf,
+}
+
+impl LocalSectorStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn path(&self, file: &SectorFile) -> PathBuf {
+        let paths = SectorPaths::under(&self.root, file.storage_provider_id, file.sector_id);
+        match (file.file_type, &file.name) {
+            (SectorFileType::Unsealed, _) => paths.unsealed,
+            (SectorFileType::Sealed, _) => paths.sealed,
+            (SectorFileType::Cache, Some(name)) => paths.cache.join(name),
+            (SectorFileType::Cache, None) => paths.cache,
+        }
+    }
+}
+
+async fn size(path: &Path) -> Result<Option<u64>, Error> {
+    match fs::metadata(path).await {
+        Ok(metadata) => Ok(Some(metadata.len())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[async_trait]
+impl SectorStore for LocalSectorStore {
+    async fn put(
+        &self,
+        file: &SectorFile,
+        offset: u64,
+        mut body: ByteStream,
+    ) -> Result<u64, Error> {
+        file.validate()?;
+        let path = self.path(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut out = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let mut size = out.metadata().await?.len();
+        if size != offset {
+            return Err(Error::TransferOffset {
+                expected: size,
+                got: offset,
+            });
+        }
+
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            out.write_all(&chunk).await?;
+            size += chunk.len() as u64;
+        }
+        out.sync_data().await?;
+
+        Ok(size)
+    }
+
+    async fn get(&self, file: &SectorFile) -> Result<ByteStream, Error> {
+        file.validate()?;
+        let reader = fs::File::open(self.path(file)).await?;
+
+        Ok(ReaderStream::new(reader)
+            .map(|chunk| chunk.map_err(Error::from))
+            .boxed())
+    }
+
+    async fn read_range(&self, file: &SectorFile, range: Range<u64>) -> Result<ByteStream, Error> {
+        file.validate()?;
+        let mut reader = fs::File::open(self.path(file)).await?;
+        reader.seek(SeekFrom::Start(range.start)).await?;
+        let length = range.end.saturating_sub(range.start);
+
+        Ok(ReaderStream::new(reader.take(length))
+            .map(|chunk| chunk.map_err(Error::from))
+            .boxed())
+    }
+
+    async fn stat(&self, file: &SectorFile) -> Result<Option<SectorFileInfo>, Error> {
+        file.validate()?;
+
+        Ok(size(&self.path(file)).await?.map(|size| SectorFileInfo {
+            file: file.clone(),
+            size,
+        }))
+    }
+
+    async fn list(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Vec<SectorFileInfo>, Error> {
+        let mut files = vec![];
+        for file in [
+            SectorFile::unsealed(storage_provider_id, sector_id),
+            SectorFile::sealed(storage_provider_id, sector_id),
+        ] {
+            files.extend(self.stat(&file).await?);
+        }
+
+        let cache = SectorPaths::under(&self.root, storage_provider_id, sector_id).cache;
+        let mut entries = match fs::read_dir(&cache).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(files),
+            Err(err) => return Err(err.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            files.push(SectorFileInfo {
+                file: SectorFile::cache(
+                    storage_provider_id,
+                    sector_id,
+                    entry.file_name().to_string_lossy(),
+                ),
+                size: metadata.len(),
+            });
+        }
+
+        Ok(files)
+    }
+
+    async fn delete(&self, file: &SectorFile) -> Result<(), Error> {
+        file.validate()?;
+        match fs::remove_file(self.path(file)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
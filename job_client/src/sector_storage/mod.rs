@@ -0,0 +1,396 @@
+pub mod http;
+pub mod local;
+
+use std::{
+    fmt,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use filecoin_spec::{SectorId, StorageProviderId};
+use futures::{stream::BoxStream, StreamExt};
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+pub use self::http::HttpSectorStore;
+pub use local::LocalSectorStore;
+
+pub type ByteStream = BoxStream<'static, Result<Bytes, Error>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SectorFileType {
+    Unsealed,
+    Sealed,
+    Cache,
+}
+
+impl SectorFileType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SectorFileType::Unsealed => "unsealed",
+            SectorFileType::Sealed => "sealed",
+            SectorFileType::Cache => "cache",
+        }
+    }
+}
+
+impl fmt::Display for SectorFileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A sector's unsealed or sealed file, or a file inside its cache directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SectorFile {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub file_type: SectorFileType,
+    /// Name inside the cache directory, e.g. `p_aux`. Only set for cache files.
+    pub name: Option<String>,
+}
+
+impl SectorFile {
+    pub fn unsealed(storage_provider_id: StorageProviderId, sector_id: SectorId) -> Self {
+        Self::new(
+            storage_provider_id,
+            sector_id,
+            SectorFileType::Unsealed,
+            None,
+        )
+    }
+
+    pub fn sealed(storage_provider_id: StorageProviderId, sector_id: SectorId) -> Self {
+        Self::new(storage_provider_id, sector_id, SectorFileType::Sealed, None)
+    }
+
+    pub fn cache(
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        name: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            storage_provider_id,
+            sector_id,
+            SectorFileType::Cache,
+            Some(name.into()),
+        )
+    }
+
+    /// The whole cache directory.
+    pub fn cache_dir(storage_provider_id: StorageProviderId, sector_id: SectorId) -> Self {
+        Self::new(storage_provider_id, sector_id, SectorFileType::Cache, None)
+    }
+
+    fn new(
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+        file_type: SectorFileType,
+        name: Option<String>,
+    ) -> Self {
+        Self {
+            storage_provider_id,
+            sector_id,
+            file_type,
+            name,
+        }
+    }
+
+    /// Lotus sector name, e.g. `s-t01000-1`.
+    pub fn sector_name(&self) -> String {
+        format!("s-t0{}-{}", self.storage_provider_id.0, self.sector_id.0)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let valid = match (&self.file_type, &self.name) {
+            (SectorFileType::Cache, Some(name)) => {
+                !name.is_empty() && !name.contains(['/', '\\']) && name != "." && name != ".."
+            }
+            (SectorFileType::Cache, None) => false,
+            (_, name) => name.is_none(),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidSectorFile(format!("{:?}", self)))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SectorFileInfo {
+    pub file: SectorFile,
+    pub size: u64,
+}
+
+/// Hex encoded blake3 hash of a file's content.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checksum(pub String);
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Storage for sector files. Writes are appends at a known offset so an
+/// interrupted transfer can pick up where it stopped, see [`transfer`].
+#[automock]
+#[async_trait]
+pub trait SectorStore: Send + Sync {
+    /// Appends `body` to `file` starting at `offset`, which has to be the
+    /// current size of the file. Returns the size after the write.
+    async fn put(&self, file: &SectorFile, offset: u64, body: ByteStream) -> Result<u64, Error>;
+
+    async fn get(&self, file: &SectorFile) -> Result<ByteStream, Error>;
+
+    async fn read_range(&self, file: &SectorFile, range: Range<u64>) -> Result<ByteStream, Error>;
+
+    /// `None` when the file doesn't exist.
+    async fn stat(&self, file: &SectorFile) -> Result<Option<SectorFileInfo>, Error>;
+
+    /// Every file stored for the sector, cache files included.
+    async fn list(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Vec<SectorFileInfo>, Error>;
+
+    /// Deleting a missing file is a no-op.
+    async fn delete(&self, file: &SectorFile) -> Result<(), Error>;
+
+    async fn checksum(&self, file: &SectorFile) -> Result<Checksum, Error> {
+        checksum_stream(self.get(file).await?).await
+    }
+}
+
+pub async fn checksum_stream(mut stream: ByteStream) -> Result<Checksum, Error> {
+    let mut hasher = blake3::Hasher::new();
+    while let Some(chunk) = stream.next().await {
+        hasher.update(&chunk?);
+    }
+
+    Ok(Checksum(hasher.finalize().to_hex().to_string()))
+}
+
+/// Copies `file` from `source` to `destination`, resuming from whatever part
+/// of it `destination` already holds, and verifies the checksums match. When
+/// they don't, e.g. because the part already there was corrupt, the file is
+/// transferred again from the start once.
+pub async fn transfer(
+    source: &dyn SectorStore,
+    destination: &dyn SectorStore,
+    file: &SectorFile,
+) -> Result<Checksum, Error> {
+    match copy(source, destination, file).await {
+        Err(Error::ChecksumMismatch { expected, got }) => {
+            tracing::warn!(
+                "Transferred {:?} has checksum {} instead of {}, transferring it again",
+                file,
+                got,
+                expected
+            );
+            destination.delete(file).await?;
+            copy(source, destination, file).await
+        }
+        result => result,
+    }
+}
+
+async fn copy(
+    source: &dyn SectorStore,
+    destination: &dyn SectorStore,
+    file: &SectorFile,
+) -> Result<Checksum, Error> {
+    let size = source
+        .stat(file)
+        .await?
+        .ok_or_else(|| Error::InvalidSectorFile(format!("{:?} doesn't exist", file)))?
+        .size;
+    let mut offset = destination.stat(file).await?.map_or(0, |info| info.size);
+
+    if offset > size {
+        tracing::warn!(
+            "Destination holds {} bytes of {:?}, more than the {} byte source, restarting",
+            offset,
+            file,
+            size
+        );
+        destination.delete(file).await?;
+        offset = 0;
+    }
+
+    // The source is hashed as it's read, the part the destination already
+    // holds first, so it's read once.
+    let hasher = Arc::new(Mutex::new(blake3::Hasher::new()));
+    let mut prefix = source.read_range(file, 0..offset).await?;
+    while let Some(chunk) = prefix.next().await {
+        hasher.lock().unwrap().update(&chunk?);
+    }
+
+    if offset < size {
+        tracing::debug!("Transferring {:?} from byte {} of {}", file, offset, size);
+        let hashing = hasher.clone();
+        let body = source
+            .read_range(file, offset..size)
+            .await?
+            .inspect(move |chunk| {
+                if let Ok(chunk) = chunk {
+                    hashing.lock().unwrap().update(chunk);
+                }
+            })
+            .boxed();
+        destination.put(file, offset, body).await?;
+    }
+
+    let expected = Checksum(hasher.lock().unwrap().finalize().to_hex().to_string());
+    let got = destination.checksum(file).await?;
+    if expected != got {
+        return Err(Error::ChecksumMismatch { expected, got });
+    }
+
+    Ok(got)
+}
+
+/// Transfers every file of the sector, see [`transfer`].
+pub async fn transfer_sector(
+    source: &dyn SectorStore,
+    destination: &dyn SectorStore,
+    storage_provider_id: StorageProviderId,
+    sector_id: SectorId,
+) -> Result<Vec<(SectorFile, Checksum)>, Error> {
+    let mut checksums = vec![];
+    for info in source.list(storage_provider_id, sector_id).await? {
+        let checksum = transfer(source, destination, &info.file).await?;
+        checksums.push((info.file, checksum));
+    }
+
+    Ok(checksums)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::stream;
+
+    fn body(chunks: &[&'static [u8]]) -> ByteStream {
+        stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok(Bytes::from_static(chunk)))
+                .collect::<Vec<_>>(),
+        )
+        .boxed()
+    }
+
+    async fn read(stream: ByteStream) -> Vec<u8> {
+        stream.map(|chunk| chunk.unwrap().to_vec()).concat().await
+    }
+
+    fn temp_root() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("sector_store_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_local_transfer_resumes_and_verifies() {
+        let (source_root, destination_root) = (temp_root(), temp_root());
+        let source = LocalSectorStore::new(&source_root);
+        let destination = LocalSectorStore::new(&destination_root);
+        let sealed = SectorFile::sealed(StorageProviderId(1000), SectorId(1));
+        let p_aux = SectorFile::cache(StorageProviderId(1000), SectorId(1), "p_aux");
+
+        source
+            .put(&sealed, 0, body(&[b"hello ", b"sealed"]))
+            .await
+            .unwrap();
+        source.put(&p_aux, 0, body(&[b"aux"])).await.unwrap();
+        assert_eq!(
+            read(source.read_range(&sealed, 6..12).await.unwrap()).await,
+            b"sealed"
+        );
+        assert!(source.put(&sealed, 3, body(&[b"x"])).await.is_err());
+
+        // Interrupted transfers left the first bytes behind, the corrupt
+        // sealed prefix makes it transfer the file again.
+        destination.put(&sealed, 0, body(&[b"heL"])).await.unwrap();
+        destination.put(&p_aux, 0, body(&[b"a"])).await.unwrap();
+        let checksums =
+            transfer_sector(&source, &destination, StorageProviderId(1000), SectorId(1))
+                .await
+                .unwrap();
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(
+            read(destination.get(&sealed).await.unwrap()).await,
+            b"hello sealed"
+        );
+
+        let mut files = destination
+            .list(StorageProviderId(1000), SectorId(1))
+            .await
+            .unwrap();
+        files.sort_by_key(|info| info.size);
+        assert_eq!(files[0].file, p_aux);
+        assert_eq!(files[1].size, 12);
+
+        destination.delete(&sealed).await.unwrap();
+        assert_eq!(destination.stat(&sealed).await.unwrap(), None);
+
+        std::fs::remove_dir_all(source_root).ok();
+        std::fs::remove_dir_all(destination_root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_http_stat_and_lotus_listing() {
+        use axum::{http::header::CONTENT_TYPE, routing::get, Router};
+
+        let router = Router::new()
+            .route(
+                "/remote/sealed/s-t01000-1",
+                get(|| async { "hello sealed" }),
+            )
+            // Lotus serves cache directories as tar streams.
+            .route(
+                "/remote/cache/s-t01000-1",
+                get(|| async { ([(CONTENT_TYPE, "application/x-tar")], "tar") }),
+            );
+        let server =
+            axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(router.into_make_service());
+        let store = HttpSectorStore::new(format!("http://{}", server.local_addr()));
+        tokio::spawn(server);
+
+        let sealed = SectorFile::sealed(StorageProviderId(1000), SectorId(1));
+        assert_eq!(store.stat(&sealed).await.unwrap().unwrap().size, 12);
+        let missing = SectorFile::sealed(StorageProviderId(1000), SectorId(2));
+        assert_eq!(store.stat(&missing).await.unwrap(), None);
+        assert!(matches!(
+            store.list(StorageProviderId(1000), SectorId(1)).await,
+            Err(Error::ListingNotSupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_lotus_remote_url() {
+        let (store, file) =
+            HttpSectorStore::from_location_url("http://10.0.0.1:2345/remote/sealed/s-t01000-7")
+                .unwrap();
+        assert_eq!(
+            file,
+            SectorFile::sealed(StorageProviderId(1000), SectorId(7))
+        );
+        assert_eq!(
+            store.file_url(&SectorFile::cache(
+                StorageProviderId(1000),
+                SectorId(7),
+                "t_aux"
+            )),
+            "http://10.0.0.1:2345/remote/cache/s-t01000-7/t_aux"
+        );
+        assert!(HttpSectorStore::from_location_url("http://host/remote/sealed/bad").is_err());
+    }
+}
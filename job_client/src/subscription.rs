@@ -0,0 +1,447 @@
+use std::{collections::VecDeque, marker::PhantomData, pin::Pin, time::Duration};
+
+use bytes::Bytes;
+use filecoin_spec::RegisteredSealProof;
+use futures::{stream, Stream, StreamExt};
+use hyper::{http, StatusCode};
+use job::JobType;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{api_error, retry::Operation, Error, JobEnvelope, SealingJobManagerHttpClient};
+
+pub const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+pub const JOB_EVENT: &str = "job";
+pub const STATE_EVENT: &str = "state";
+const DEFAULT_EVENT: &str = "message";
+
+/// Capabilities a worker declares when subscribing, the server only pushes
+/// jobs matching all of them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JobSubscription {
+    pub job_types: Vec<JobType>,
+    pub registered_proofs: Vec<RegisteredSealProof>,
+    /// Maximum number of jobs the worker can hold at once.
+    pub max_in_flight: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectBackoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl ReconnectBackoff {
+    fn next(&self, current: Duration) -> Duration {
+        current.mul_f64(self.multiplier).min(self.max)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental `text/event-stream` parser, chunks may split lines at any byte.
+#[derive(Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    current: SseEvent,
+    events: VecDeque<SseEvent>,
+}
+
+impl SseParser {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.process_line(line.trim_end_matches(['\n', '\r']));
+        }
+    }
+
+    pub fn next_event(&mut self) -> Option<SseEvent> {
+        self.events.pop_front()
+    }
+
+    fn process_line(&mut self, line: &str) {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.current);
+            if !event.data.is_empty() {
+                self.events.push_back(event);
+            }
+            return;
+        }
+
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "data" => {
+                if !self.current.data.is_empty() {
+                    self.current.data.push('\n');
+                }
+                self.current.data.push_str(value);
+            }
+            "id" => self.current.id = Some(value.to_string()),
+            "event" => self.current.event = Some(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+type BodyStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+
+struct EventStreamState<T> {
+    client: SealingJobManagerHttpClient,
+    operation: Operation,
+    uri: String,
+    request: String,
+    event_name: &'static str,
+    backoff: ReconnectBackoff,
+    delay: Option<Duration>,
+    last_event_id: Option<String>,
+    body: Option<BodyStream>,
+    parser: SseParser,
+    _event: PhantomData<T>,
+}
+
+enum Connect {
+    Connected(BodyStream),
+    Retry,
+    Fatal(Error),
+}
+
+impl<T: DeserializeOwned> EventStreamState<T> {
+    async fn connect(&mut self) -> Connect {
+        if let Some(delay) = self.delay {
+            tracing::debug!("Reconnecting to {} in {:?}", self.uri, delay);
+            tokio::time::sleep(delay).await;
+        }
+
+        // Sent like any other request, so an expired token is refreshed once
+        // and only a second 401 ends the stream.
+        let response = self
+            .client
+            .send_json(self.operation, None, &self.request, || {
+                let request = self
+                    .client
+                    .http_client
+                    .post(&self.uri)
+                    .header(http::header::ACCEPT, "text/event-stream");
+                match &self.last_event_id {
+                    Some(id) => request.header(LAST_EVENT_ID_HEADER, id),
+                    None => request,
+                }
+            })
+            .await;
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::warn!("Connection to {} failed: {}", self.uri, err);
+                self.increase_delay();
+                return Connect::Retry;
+            }
+        };
+
+        let status = response.status();
+        if status == StatusCode::OK {
+            tracing::info!("Subscribed to {}", self.uri);
+            self.delay = None;
+            return Connect::Connected(Box::pin(response.bytes_stream()));
+        }
+
+        let retry_after = api_error::retry_after(&response);
+        let err = api_error::from_response(response).await;
+        let retryable = matches!(
+            status,
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS
+        );
+        if status.is_client_error() && !retryable {
+            tracing::error!("Subscription to {} rejected: {}", self.uri, err);
+            return Connect::Fatal(err);
+        }
+
+        tracing::warn!("Subscription to {} failed: {}", self.uri, err);
+        self.increase_delay();
+        if retry_after.is_some() {
+            self.delay = self.delay.max(retry_after);
+        }
+        Connect::Retry
+    }
+
+    fn reset(&mut self) {
+        self.body = None;
+        self.parser = SseParser::default();
+        self.increase_delay();
+    }
+
+    fn increase_delay(&mut self) {
+        self.delay = Some(match self.delay {
+            Some(delay) => self.backoff.next(delay),
+            None => self.backoff.initial,
+        });
+    }
+
+    fn decode(&mut self, event: SseEvent) -> Option<Result<T, Error>> {
+        if event.id.is_some() {
+            self.last_event_id = event.id;
+        }
+
+        match event.event.as_deref().unwrap_or(DEFAULT_EVENT) {
+            name if name == self.event_name || name == DEFAULT_EVENT => {
+                Some(serde_json::from_str(&event.data).map_err(Into::into))
+            }
+            other => {
+                tracing::trace!("Ignoring {} event from {}", other, self.uri);
+                None
+            }
+        }
+    }
+}
+
+/// Opens a Server-Sent-Events stream posting `request` to `uri` and decoding
+/// every `event_name` event as `T`. Dropped connections are re-established
+/// with `backoff`, or after the server's `Retry-After`, resuming from the last
+/// received event id. Only a `4xx` response other than `408` and `429` ends
+/// the stream, a `401` only once a refreshed token was rejected too.
+pub(crate) fn event_stream<T>(
+    client: &SealingJobManagerHttpClient,
+    operation: Operation,
+    uri: String,
+    request: serde_json::Value,
+    event_name: &'static str,
+    backoff: ReconnectBackoff,
+    last_event_id: Option<String>,
+) -> impl Stream<Item = Result<T, Error>> + Send
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let state = EventStreamState {
+        client: client.clone(),
+        operation,
+        uri,
+        request: request.to_string(),
+        event_name,
+        backoff,
+        delay: None,
+        last_event_id,
+        body: None,
+        parser: SseParser::default(),
+        _event: PhantomData,
+    };
+
+    stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        loop {
+            while let Some(event) = state.parser.next_event() {
+                if let Some(item) = state.decode(event) {
+                    return Some((item, Some(state)));
+                }
+            }
+
+            let Some(body) = state.body.as_mut() else {
+                match state.connect().await {
+                    Connect::Connected(body) => state.body = Some(body),
+                    Connect::Retry => {}
+                    Connect::Fatal(err) => return Some((Err(err), None)),
+                }
+                continue;
+            };
+
+            match body.next().await {
+                Some(Ok(chunk)) => state.parser.feed(&chunk),
+                Some(Err(err)) => {
+                    tracing::warn!("Event stream {} error: {}", state.uri, err);
+                    state.reset();
+                }
+                None => {
+                    tracing::debug!("Event stream {} closed by server", state.uri);
+                    state.reset();
+                }
+            }
+        }
+    })
+}
+
+impl SealingJobManagerHttpClient {
+    /// Opens a Server-Sent-Events stream of jobs matching `subscription`,
+    /// reconnecting with `backoff` whenever the connection drops.
+    pub fn subscribe_jobs(
+        &self,
+        subscription: JobSubscription,
+        backoff: ReconnectBackoff,
+    ) -> Result<impl Stream<Item = Result<JobEnvelope, Error>> + Send, Error> {
+        Ok(event_stream(
+            self,
+            Operation::SubscribeJobs,
+            self.subscribe_jobs_uri.clone(),
+            serde_json::to_value(&subscription)?,
+            JOB_EVENT,
+            backoff,
+            None,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use async_trait::async_trait;
+    use axum::{http::HeaderMap, response::IntoResponse, routing::post, Router};
+    use job::test_utils::pc1;
+
+    use super::*;
+    use crate::{auth::TokenProvider, builder::SealingJobManagerHttpClientBuilder};
+
+    struct RotatingToken;
+
+    #[async_trait]
+    impl TokenProvider for RotatingToken {
+        async fn token(&self) -> Result<String, Error> {
+            Ok("expired".to_string())
+        }
+
+        async fn refresh(&self) -> Result<String, Error> {
+            Ok("fresh".to_string())
+        }
+    }
+
+    fn subscription() -> JobSubscription {
+        JobSubscription {
+            job_types: vec![JobType::PC1],
+            registered_proofs: vec![],
+            max_in_flight: None,
+        }
+    }
+
+    #[test]
+    fn test_sse_parser_split_chunks() {
+        let mut parser = SseParser::default();
+        parser.feed(b": keep-alive\n\nid: 7\r\nevent: job\nda");
+        assert_eq!(parser.next_event(), None);
+
+        parser.feed(b"ta: {\"a\":1}\n\n");
+        assert_eq!(
+            parser.next_event(),
+            Some(SseEvent {
+                id: Some("7".to_string()),
+                event: Some("job".to_string()),
+                data: "{\"a\":1}".to_string(),
+            })
+        );
+        assert_eq!(parser.next_event(), None);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_retries_rate_limited_connects() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let router = Router::new().route(
+            crate::SUBSCRIBE_JOBS_URL,
+            post(move |headers: HeaderMap| async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return (
+                        StatusCode::TOO_MANY_REQUESTS,
+                        [(http::header::RETRY_AFTER, "0")],
+                        String::new(),
+                    )
+                        .into_response();
+                }
+                assert!(headers.get(LAST_EVENT_ID_HEADER).is_none());
+                let job = serde_json::to_string(&crate::JobHttp::PC1(pc1(1))).unwrap();
+                format!("event: {}\nid: 1\ndata: {}\n\n", JOB_EVENT, job).into_response()
+            }),
+        );
+        let server =
+            axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(router.into_make_service());
+        let uri = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let client = SealingJobManagerHttpClient::new(uri);
+        let backoff = ReconnectBackoff {
+            initial: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let mut jobs = Box::pin(client.subscribe_jobs(subscription(), backoff).unwrap());
+
+        let job = jobs.next().await.unwrap().unwrap();
+        assert_eq!(job.job.sector_id().0, 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_refreshes_expired_token() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let router = Router::new().route(
+            crate::SUBSCRIBE_JOBS_URL,
+            post(move |headers: HeaderMap| async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                match headers.get(http::header::AUTHORIZATION) {
+                    Some(token) if token == "Bearer fresh" => {
+                        let job = serde_json::to_string(&crate::JobHttp::PC1(pc1(1))).unwrap();
+                        format!("event: {}\nid: 1\ndata: {}\n\n", JOB_EVENT, job).into_response()
+                    }
+                    _ => StatusCode::UNAUTHORIZED.into_response(),
+                }
+            }),
+        );
+        let server =
+            axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(router.into_make_service());
+        let uri = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let client = SealingJobManagerHttpClientBuilder::new(uri.clone())
+            .token_provider(Arc::new(RotatingToken))
+            .build()
+            .unwrap();
+        let mut jobs = Box::pin(
+            client
+                .subscribe_jobs(subscription(), ReconnectBackoff::default())
+                .unwrap(),
+        );
+        let job = jobs.next().await.unwrap().unwrap();
+        assert_eq!(job.job.sector_id().0, 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // A token that can't be refreshed ends the stream.
+        let client = SealingJobManagerHttpClientBuilder::new(uri)
+            .bearer_token("expired".to_string())
+            .build()
+            .unwrap();
+        let mut jobs = Box::pin(
+            client
+                .subscribe_jobs(subscription(), ReconnectBackoff::default())
+                .unwrap(),
+        );
+        assert!(matches!(
+            jobs.next().await,
+            Some(Err(Error::Unauthorized(_)))
+        ));
+        assert!(jobs.next().await.is_none());
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use opentelemetry::{
+    propagation::TextMapPropagator, sdk::propagation::TraceContextPropagator, Context,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::JobEnvelope;
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// W3C trace context headers of the current span. Empty unless spans are
+/// recorded by a `tracing_opentelemetry` layer.
+pub fn current_headers() -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+    TraceContextPropagator::new().inject_context(&tracing::Span::current().context(), &mut headers);
+    headers
+}
+
+pub fn current_trace_parent() -> Option<String> {
+    current_headers().remove(TRACEPARENT_HEADER)
+}
+
+pub fn context_from_trace_parent(trace_parent: &str) -> Context {
+    let headers = HashMap::from([(TRACEPARENT_HEADER.to_string(), trace_parent.to_string())]);
+    TraceContextPropagator::new().extract(&headers)
+}
+
+/// Stores the current trace parent in a job that doesn't carry one yet.
+pub fn inject(job: &mut JobEnvelope) {
+    if job.trace_parent.is_none() {
+        job.trace_parent = current_trace_parent();
+    }
+}
+
+/// Makes `span` part of the trace the job was added in, e.g. when a worker
+/// claims it.
+pub fn continue_trace(span: &tracing::Span, job: &JobEnvelope) {
+    if let Some(trace_parent) = &job.trace_parent {
+        span.set_parent(context_from_trace_parent(trace_parent));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use job::test_utils::pc1;
+    use opentelemetry::{
+        sdk::trace::TracerProvider,
+        trace::{TraceContextExt, TracerProvider as _},
+    };
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::JobHttp;
+
+    #[test]
+    fn test_trace_continues_across_job() {
+        // The tracer only samples spans while its provider is alive.
+        let provider = TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut job = JobEnvelope::from(JobHttp::PC1(pc1(1)));
+            inject(&mut job);
+            assert!(job.trace_parent.is_none());
+
+            let add_span = tracing::info_span!("add_job");
+            let trace_id = add_span.context().span().span_context().trace_id();
+            add_span.in_scope(|| {
+                assert!(current_headers()
+                    .get(TRACEPARENT_HEADER)
+                    .is_some_and(|header| header.starts_with("00-")));
+                inject(&mut job);
+            });
+            assert!(job.trace_parent.is_some());
+
+            // Peers that only know the job ignore the extra field.
+            let json = serde_json::to_string(&job).unwrap();
+            assert!(matches!(
+                serde_json::from_str(&json).unwrap(),
+                JobHttp::PC1(_)
+            ));
+
+            let run_span = tracing::info_span!("run_job");
+            continue_trace(&run_span, &job);
+            assert_eq!(
+                run_span.context().span().span_context().trace_id(),
+                trace_id
+            );
+        });
+    }
+}
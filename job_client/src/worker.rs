@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use filecoin_spec::RegisteredSealProof;
+use job::JobType;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const WORKER_ID_HEADER: &str = "X-Worker-Id";
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct WorkerId(pub Uuid);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GpuInfo {
+    pub name: String,
+    pub memory_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkerResources {
+    pub cpu_cores: u32,
+    pub memory_bytes: u64,
+    pub disk_bytes: u64,
+    pub gpus: Vec<GpuInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WorkerInfo {
+    pub hostname: String,
+    pub job_types: Vec<JobType>,
+    pub registered_proofs: Vec<RegisteredSealProof>,
+    pub resources: WorkerResources,
+    /// Software component name to version, e.g. `filecoin-proofs` -> `16.0.0`.
+    pub versions: HashMap<String, String>,
+}
+
+impl WorkerInfo {
+    pub fn can_run(&self, job_type: JobType, registered_proof: RegisteredSealProof) -> bool {
+        self.job_types.contains(&job_type) && self.registered_proofs.contains(&registered_proof)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RegisterWorkerResponse {
+    pub worker_id: WorkerId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredWorker {
+    pub worker_id: WorkerId,
+    pub info: WorkerInfo,
+    /// Milliseconds since the unix epoch.
+    pub registered_at_ms: u64,
+    /// Milliseconds since the unix epoch of the last request carrying the worker id.
+    pub last_seen_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListWorkersResponse {
+    pub workers: Vec<RegisteredWorker>,
+}
@@ -0,0 +1,50 @@
+CREATE TABLE jobs (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    id TEXT NOT NULL UNIQUE,
+    storage_provider_id INTEGER NOT NULL,
+    sector_id INTEGER NOT NULL,
+    job_type TEXT NOT NULL,
+    registered_proof TEXT NOT NULL,
+    -- JobHttp as JSON
+    input TEXT NOT NULL,
+    state TEXT NOT NULL,
+    claimed INTEGER NOT NULL DEFAULT 0,
+    -- Position in the queue, bumped when a job is requeued
+    queued_at INTEGER NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    progress TEXT,
+    -- JobOutputHttp as JSON
+    output TEXT,
+    error TEXT,
+    created_ms INTEGER NOT NULL,
+    updated_ms INTEGER NOT NULL,
+    UNIQUE (storage_provider_id, sector_id, job_type)
+);
+
+CREATE INDEX jobs_queue ON jobs (state, claimed, job_type, queued_at);
+
+CREATE TABLE job_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_seq INTEGER NOT NULL REFERENCES jobs (seq),
+    timestamp_ms INTEGER NOT NULL,
+    -- JobHistoryEventKind as JSON
+    kind TEXT NOT NULL
+);
+
+CREATE INDEX job_history_job ON job_history (job_seq, id);
+
+CREATE TABLE job_events (
+    cursor INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_seq INTEGER NOT NULL REFERENCES jobs (seq),
+    old_state TEXT,
+    new_state TEXT NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    error TEXT
+);
+
+CREATE TABLE workers (
+    worker_id TEXT PRIMARY KEY,
+    info TEXT NOT NULL,
+    registered_at_ms INTEGER NOT NULL,
+    last_seen_ms INTEGER NOT NULL
+);
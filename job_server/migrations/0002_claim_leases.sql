@@ -0,0 +1,12 @@
+ALTER TABLE jobs ADD COLUMN claimed_by TEXT;
+-- Milliseconds since the unix epoch, renewed by progress reports and requests
+-- from the worker holding the claim
+ALTER TABLE jobs ADD COLUMN lease_expires_ms INTEGER;
+
+-- Jobs claimed before leases existed get the default 5 minute lease, so they
+-- go back to the queue unless their worker is still around to renew it
+UPDATE jobs
+SET lease_expires_ms = CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER) + 300000
+WHERE claimed = 1;
+
+CREATE INDEX jobs_leases ON jobs (claimed_by, lease_expires_ms);
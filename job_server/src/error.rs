@@ -0,0 +1,64 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use job_client::api_error::{ErrorResponse, ALREADY_SUBMITTED_CODE};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    AlreadySubmitted(String),
+
+    #[error("{0}")]
+    Validation(String),
+
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("{0}")]
+    Storage(String),
+}
+
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Conflict(_) | Error::AlreadySubmitted(_) => StatusCode::CONFLICT,
+            Error::Validation(_) => StatusCode::BAD_REQUEST,
+            Error::Json(_) | Error::Sqlite(_) | Error::Storage(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("Request failed: {}", self);
+        }
+
+        let code = match self {
+            Error::AlreadySubmitted(_) => Some(ALREADY_SUBMITTED_CODE.to_string()),
+            _ => None,
+        };
+        let body = ErrorResponse {
+            code,
+            message: self.to_string(),
+            request_id: None,
+        };
+
+        (status, Json(body)).into_response()
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Bytes, Full},
+    extract::State,
+    http::{HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use job_client::retry::IDEMPOTENCY_KEY_HEADER;
+
+/// Keys older than this are forgotten, retries of a request are long done.
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Upper bound on remembered keys, the oldest are evicted first.
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 10_000;
+
+struct StoredResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+type Slot = Arc<tokio::sync::Mutex<Option<StoredResponse>>>;
+
+#[derive(Default)]
+struct Inner {
+    slots: HashMap<String, Slot>,
+    order: VecDeque<(Instant, String)>,
+}
+
+/// Responses by `Idempotency-Key`, so a retried request gets the response of
+/// the first one instead of being applied again.
+pub struct IdempotencyCache {
+    inner: Mutex<Inner>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_TTL, DEFAULT_IDEMPOTENCY_CAPACITY)
+    }
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            ttl,
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn slot(&self, key: String) -> Slot {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        while let Some((created, _)) = inner.order.front() {
+            if inner.order.len() < self.capacity && now.duration_since(*created) < self.ttl {
+                break;
+            }
+            if let Some((_, key)) = inner.order.pop_front() {
+                inner.slots.remove(&key);
+            }
+        }
+
+        if let Some(slot) = inner.slots.get(&key) {
+            return slot.clone();
+        }
+        let slot = Slot::default();
+        inner.slots.insert(key.clone(), slot.clone());
+        inner.order.push_back((now, key));
+        slot
+    }
+}
+
+/// Replays the stored response for a known key. Concurrent requests with the
+/// same key wait for the first one, server errors aren't stored so the
+/// request can be retried.
+pub async fn replay<B>(
+    State(cache): State<Arc<IdempotencyCache>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(key) = request
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return next.run(request).await;
+    };
+
+    // Keys are only unique per route.
+    let key = format!("{} {} {}", request.method(), request.uri().path(), key);
+    let slot = cache.slot(key);
+    let mut stored = slot.lock().await;
+    if let Some(response) = stored.as_ref() {
+        tracing::debug!("Replaying response for {}", request.uri().path());
+        return (
+            response.status,
+            response.headers.clone(),
+            Full::new(response.body.clone()),
+        )
+            .into_response();
+    }
+
+    let response = next.run(request).await;
+    if response.status().is_server_error() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!("Failed to buffer response: {}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    *stored = Some(StoredResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    });
+
+    Response::from_parts(parts, axum::body::boxed(Full::new(body)))
+}
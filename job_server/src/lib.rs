@@ -0,0 +1,114 @@
+pub mod error;
+pub mod idempotency;
+pub mod routes;
+pub mod sector;
+pub mod storage;
+
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub use error::Error;
+use idempotency::IdempotencyCache;
+use sector::SectorProvider;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub listen_addr: SocketAddr,
+    /// Root of the lotus style sector layout handed out by the sector paths route.
+    pub sector_root: PathBuf,
+    /// How often streaming routes look for new jobs and events.
+    pub poll_interval: Duration,
+    /// SQLite file backing the job store, jobs are kept in memory when unset.
+    pub database: Option<PathBuf>,
+    /// How long a claim lasts unless the worker renews it.
+    pub claim_lease: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 8080)),
+            sector_root: PathBuf::from("/var/lib/sectors"),
+            poll_interval: Duration::from_millis(500),
+            database: None,
+            claim_lease: storage::DEFAULT_CLAIM_LEASE,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn from_env() -> ServerConfig {
+        let default = ServerConfig::default();
+        ServerConfig {
+            listen_addr: std::env::var("JOB_SERVER_LISTEN_ADDR")
+                .map(|addr| {
+                    addr.parse()
+                        .expect("JOB_SERVER_LISTEN_ADDR is not an address")
+                })
+                .unwrap_or(default.listen_addr),
+            sector_root: std::env::var("JOB_SERVER_SECTOR_ROOT")
+                .map(PathBuf::from)
+                .unwrap_or(default.sector_root),
+            poll_interval: std::env::var("JOB_SERVER_POLL_INTERVAL_MS")
+                .map(|ms| {
+                    Duration::from_millis(
+                        ms.parse()
+                            .expect("JOB_SERVER_POLL_INTERVAL_MS is not a number"),
+                    )
+                })
+                .unwrap_or(default.poll_interval),
+            database: std::env::var("JOB_SERVER_DATABASE")
+                .map(PathBuf::from)
+                .ok()
+                .or(default.database),
+            claim_lease: std::env::var("JOB_SERVER_CLAIM_LEASE_SECS")
+                .map(|secs| {
+                    Duration::from_secs(
+                        secs.parse()
+                            .expect("JOB_SERVER_CLAIM_LEASE_SECS is not a number"),
+                    )
+                })
+                .unwrap_or(default.claim_lease),
+        }
+    }
+}
+
+pub struct AppState<Store> {
+    pub store: Arc<Store>,
+    pub sectors: Arc<dyn SectorProvider>,
+    pub idempotency: Arc<IdempotencyCache>,
+    pub config: ServerConfig,
+}
+
+impl<Store> Clone for AppState<Store> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            sectors: self.sectors.clone(),
+            idempotency: self.idempotency.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<Store> AppState<Store> {
+    pub fn new(store: Store, sectors: impl SectorProvider, config: ServerConfig) -> Self {
+        Self {
+            store: Arc::new(store),
+            sectors: Arc::new(sectors),
+            idempotency: Arc::new(IdempotencyCache::default()),
+            config,
+        }
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
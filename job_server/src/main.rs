@@ -0,0 +1,41 @@
+use job_server::{
+    routes,
+    sector::LocalSectorProvider,
+    storage::{JobStore, MemoryJobStore, SqliteJobStore},
+    AppState, ServerConfig,
+};
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let config = ServerConfig::from_env();
+    match config.database.clone() {
+        Some(path) => {
+            tracing::info!("Storing jobs in {}", path.display());
+            let store = SqliteJobStore::open(&path)
+                .expect("failed to open job database")
+                .with_lease(config.claim_lease);
+            serve(store, config).await
+        }
+        None => {
+            let store = MemoryJobStore::new().with_lease(config.claim_lease);
+            serve(store, config).await
+        }
+    }
+}
+
+async fn serve<Store: JobStore>(store: Store, config: ServerConfig) {
+    let sectors = LocalSectorProvider::new(config.sector_root.clone());
+    let listen_addr = config.listen_addr;
+    let state = AppState::new(store, sectors, config);
+
+    tracing::info!("Job server listening on {}", listen_addr);
+    axum::Server::bind(&listen_addr)
+        .serve(routes::router(state).into_make_service())
+        .await
+        .expect("job server failed");
+}
@@ -0,0 +1,857 @@
+use std::{collections::VecDeque, convert::Infallible};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use filecoin_spec::{RegisteredSealProof, SectorId, StorageProviderId};
+use futures::{stream, Stream};
+use job::{JobId, JobType};
+use job_client::{
+    failure::JobFailure,
+    history::JobRef,
+    query::JobQuery,
+    sector::{GenerateTicket, GetSectorPaths},
+    subscription::{JobSubscription, JOB_EVENT, LAST_EVENT_ID_HEADER, STATE_EVENT},
+    worker::{ListWorkersResponse, RegisterWorkerResponse, WorkerId, WorkerInfo, WORKER_ID_HEADER},
+    BatchRequest, BatchResponse, CancelJob, EventCursor, FailJob, Filter, GetSealingJobsResponse,
+    JobEnvelope, JobKey, JobOutputEntry, JobState, JobStateEvent, JobStatus, ReleaseJob,
+    ReportProgress, RetryJob, SubmitSealingJobOutput, WatchJobs, ADD_JOBS_BATCH_URL, ADD_JOBS_URL,
+    CANCEL_JOB_URL, FAIL_JOB_URL, GENERATE_TICKET_URL, GET_ALL_JOBS_URL, GET_JOBS_URL,
+    GET_JOB_HISTORY_BY_ID_URL, GET_JOB_HISTORY_URL, GET_JOB_INPUT_URI, GET_JOB_STATES_BATCH_URL,
+    GET_JOB_STATE_URL, GET_OUTPUTS_BATCH_URL, GET_OUTPUT_URL, GET_SECTOR_PATHS_URL,
+    LIST_WORKERS_URL, QUERY_JOBS_URL, REGISTER_WORKER_URL, RELEASE_JOB_URL, REPORT_PROGRESS_URL,
+    REQUEST_ANY_JOBS_URL, RETRY_JOB_URL, SUBMIT_OUTPUTS_BATCH_URL, SUBMIT_OUTPUT_URL,
+    SUBSCRIBE_JOBS_URL, WATCH_JOBS_URL,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    idempotency,
+    storage::{ClaimJobs, JobStore},
+    AppState, Error,
+};
+
+type SectorPath = Path<(StorageProviderId, SectorId, JobType)>;
+
+pub fn router<Store: JobStore>(state: AppState<Store>) -> Router {
+    let idempotency = state.idempotency.clone();
+    Router::new()
+        .route(ADD_JOBS_URL, post(add_job::<Store>))
+        .route(GET_JOBS_URL, get(request_jobs::<Store>))
+        .route(REQUEST_ANY_JOBS_URL, post(request_any_jobs::<Store>))
+        .route(GET_ALL_JOBS_URL, get(get_all_jobs::<Store>))
+        .route(GET_JOB_INPUT_URI, get(get_job_input::<Store>))
+        .route(GET_JOB_STATE_URL, get(get_job_state::<Store>))
+        .route(QUERY_JOBS_URL, post(query_jobs::<Store>))
+        .route(SUBMIT_OUTPUT_URL, post(submit_output::<Store>))
+        .route(GET_OUTPUT_URL, get(get_output::<Store>))
+        .route(FAIL_JOB_URL, post(fail_job::<Store>))
+        .route(RELEASE_JOB_URL, post(release_job::<Store>))
+        .route(RETRY_JOB_URL, post(retry_job::<Store>))
+        .route(REPORT_PROGRESS_URL, post(report_progress::<Store>))
+        .route(CANCEL_JOB_URL, post(cancel_job::<Store>))
+        .route(GET_JOB_HISTORY_URL, get(get_job_history::<Store>))
+        .route(
+            GET_JOB_HISTORY_BY_ID_URL,
+            get(get_job_history_by_id::<Store>),
+        )
+        .route(SUBSCRIBE_JOBS_URL, post(subscribe_jobs::<Store>))
+        .route(WATCH_JOBS_URL, post(watch_jobs::<Store>))
+        .route(REGISTER_WORKER_URL, post(register_worker::<Store>))
+        .route(LIST_WORKERS_URL, get(list_workers::<Store>))
+        .route(ADD_JOBS_BATCH_URL, post(add_jobs_batch::<Store>))
+        .route(
+            SUBMIT_OUTPUTS_BATCH_URL,
+            post(submit_outputs_batch::<Store>),
+        )
+        .route(GET_OUTPUTS_BATCH_URL, post(get_outputs_batch::<Store>))
+        .route(
+            GET_JOB_STATES_BATCH_URL,
+            post(get_job_states_batch::<Store>),
+        )
+        .route(GENERATE_TICKET_URL, get(generate_ticket::<Store>))
+        .route(GET_SECTOR_PATHS_URL, get(get_sector_paths::<Store>))
+        .layer(middleware::from_fn_with_state(
+            idempotency,
+            idempotency::replay,
+        ))
+        .with_state(state)
+}
+
+fn json_or_no_content<T: Serialize>(value: Option<T>) -> Response {
+    match value {
+        Some(value) => Json(value).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Reads the worker id header and records that the worker is alive.
+async fn worker_id<Store: JobStore>(
+    state: &AppState<Store>,
+    headers: &HeaderMap,
+) -> Result<Option<WorkerId>, Error> {
+    let Some(worker_id) = headers
+        .get(WORKER_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| Uuid::parse_str(value).ok())
+        .map(WorkerId)
+    else {
+        return Ok(None);
+    };
+
+    state.store.worker_seen(worker_id).await?;
+    Ok(Some(worker_id))
+}
+
+/// Proofs the worker registered with, any proof for unknown workers.
+async fn registered_proofs<Store: JobStore>(
+    state: &AppState<Store>,
+    worker_id: Option<WorkerId>,
+) -> Result<Vec<RegisteredSealProof>, Error> {
+    let Some(worker_id) = worker_id else {
+        return Ok(vec![]);
+    };
+
+    Ok(state
+        .store
+        .get_worker(worker_id)
+        .await?
+        .map(|worker| worker.info.registered_proofs)
+        .unwrap_or_default())
+}
+
+async fn add_job<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(job): Json<JobEnvelope>,
+) -> Result<StatusCode, Error> {
+    state.store.add_job(job).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn claim<Store: JobStore>(
+    state: &AppState<Store>,
+    headers: &HeaderMap,
+    job_types: Vec<JobType>,
+    count: usize,
+) -> Result<Response, Error> {
+    let worker_id = worker_id(state, headers).await?;
+    let registered_proofs = registered_proofs(state, worker_id).await?;
+    let jobs = state
+        .store
+        .claim_jobs(ClaimJobs {
+            job_types,
+            registered_proofs,
+            count,
+            worker_id,
+        })
+        .await?;
+
+    if jobs.is_empty() {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    Ok(Json(GetSealingJobsResponse { jobs }).into_response())
+}
+
+async fn request_jobs<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path((count, job_type)): Path<(usize, JobType)>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    claim(&state, &headers, vec![job_type], count).await
+}
+
+#[derive(Deserialize)]
+struct RequestAnyJobs {
+    job_types: Vec<JobType>,
+}
+
+async fn request_any_jobs<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(count): Path<usize>,
+    headers: HeaderMap,
+    Json(request): Json<RequestAnyJobs>,
+) -> Result<Response, Error> {
+    claim(&state, &headers, request.job_types, count).await
+}
+
+async fn get_all_jobs<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path((storage_provider_id, job_type)): Path<(StorageProviderId, JobType)>,
+) -> Result<Json<GetSealingJobsResponse>, Error> {
+    let query = JobQuery::new()
+        .storage_provider(storage_provider_id)
+        .page_size(u32::MAX);
+    let page = state.store.query_jobs(job_type, query).await?;
+
+    Ok(Json(GetSealingJobsResponse {
+        jobs: page.items.into_iter().map(Into::into).collect(),
+    }))
+}
+
+async fn get_job_input<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path((storage_provider_id, sector_id, job_type)): SectorPath,
+) -> Result<Response, Error> {
+    let key = (storage_provider_id, sector_id).into();
+    Ok(json_or_no_content(
+        state.store.get_job(key, job_type).await?,
+    ))
+}
+
+/// Workers poll this while running a job, so it renews their claims too.
+async fn get_job_state<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path((storage_provider_id, sector_id, job_type)): SectorPath,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    worker_id(&state, &headers).await?;
+    let key = (storage_provider_id, sector_id).into();
+    Ok(json_or_no_content(
+        state.store.get_job_state(key, job_type).await?,
+    ))
+}
+
+async fn query_jobs<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(job_type): Path<JobType>,
+    Json(query): Json<JobQuery>,
+) -> Result<Response, Error> {
+    Ok(Json(state.store.query_jobs(job_type, query).await?).into_response())
+}
+
+async fn submit_output<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(output): Json<SubmitSealingJobOutput>,
+) -> Result<StatusCode, Error> {
+    state.store.submit_output(output).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+struct OutputFailure {
+    err: JobFailure,
+}
+
+async fn get_output<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path((storage_provider_id, sector_id, job_type)): SectorPath,
+) -> Result<Response, Error> {
+    let key = (storage_provider_id, sector_id).into();
+    Ok(match state.store.get_output(key, job_type).await? {
+        Some(Ok(output)) => Json(output).into_response(),
+        Some(Err(err)) => {
+            (StatusCode::FAILED_DEPENDENCY, Json(OutputFailure { err })).into_response()
+        }
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+async fn fail_job<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    headers: HeaderMap,
+    Json(request): Json<FailJob>,
+) -> Result<StatusCode, Error> {
+    let worker_id = worker_id(&state, &headers).await?;
+    state.store.fail_job(request, worker_id).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn release_job<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    headers: HeaderMap,
+    Json(request): Json<ReleaseJob>,
+) -> Result<StatusCode, Error> {
+    let worker_id = worker_id(&state, &headers).await?;
+    state.store.release_job(request, worker_id).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn retry_job<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(request): Json<RetryJob>,
+) -> Result<StatusCode, Error> {
+    state.store.retry_job(request).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn report_progress<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(request): Json<ReportProgress>,
+) -> Result<StatusCode, Error> {
+    state.store.report_progress(request).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn cancel_job<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(request): Json<CancelJob>,
+) -> Result<StatusCode, Error> {
+    state.store.cancel_job(request).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn get_job_history<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path((storage_provider_id, sector_id, job_type)): SectorPath,
+) -> Result<Response, Error> {
+    let job = JobRef::Sector {
+        storage_provider_id,
+        sector_id,
+        job_type,
+    };
+    Ok(json_or_no_content(state.store.get_job_history(job).await?))
+}
+
+async fn get_job_history_by_id<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Response, Error> {
+    let job = JobRef::Id(JobId(job_id));
+    Ok(json_or_no_content(state.store.get_job_history(job).await?))
+}
+
+/// Pushes jobs to the worker as they become available, never letting it hold
+/// more than `max_in_flight` unfinished jobs. Event ids are job event cursors,
+/// a worker reconnecting with `Last-Event-ID` keeps the jobs it still holds
+/// in flight.
+async fn subscribe_jobs<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    headers: HeaderMap,
+    Json(subscription): Json<JobSubscription>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+    let worker_id = worker_id(&state, &headers).await?;
+    let mut subscription = subscription;
+    if subscription.registered_proofs.is_empty() {
+        subscription.registered_proofs = registered_proofs(&state, worker_id).await?;
+    }
+    let max_in_flight = subscription.max_in_flight.unwrap_or(1).max(1);
+
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(EventCursor);
+    let cursor = match last_event_id {
+        Some(cursor) => cursor,
+        None => state.store.latest_cursor().await?,
+    };
+
+    let mut in_flight = vec![];
+    if let (Some(worker_id), Some(_)) = (worker_id, last_event_id) {
+        for envelope in state.store.claimed_jobs(worker_id).await? {
+            let job = &envelope.job;
+            if subscription.job_types.contains(&job.job_type()) {
+                let key = (job.storage_provider_id(), job.sector_id()).into();
+                in_flight.push((key, job.job_type()));
+            }
+        }
+    }
+
+    struct Subscriber<Store> {
+        state: AppState<Store>,
+        subscription: JobSubscription,
+        in_flight: Vec<(JobKey, JobType)>,
+        buffered: VecDeque<JobEnvelope>,
+        /// Job events up to here have been applied to `in_flight`.
+        cursor: EventCursor,
+    }
+
+    let subscriber = Subscriber {
+        state,
+        subscription,
+        in_flight,
+        buffered: VecDeque::new(),
+        cursor,
+    };
+
+    let stream = stream::unfold(subscriber, move |mut subscriber| async move {
+        loop {
+            if let Some(job) = subscriber.buffered.pop_front() {
+                match Event::default()
+                    .event(JOB_EVENT)
+                    .id(subscriber.cursor.0.to_string())
+                    .json_data(&job)
+                {
+                    Ok(event) => return Some((Ok(event), subscriber)),
+                    Err(err) => {
+                        tracing::error!("Failed to encode job event: {}", err);
+                        continue;
+                    }
+                }
+            }
+
+            if let Err(err) = refill(&mut subscriber, worker_id, max_in_flight).await {
+                tracing::error!("Failed to claim jobs for subscriber: {}", err);
+            }
+
+            if subscriber.buffered.is_empty() {
+                tokio::time::sleep(subscriber.state.config.poll_interval).await;
+            }
+        }
+    });
+
+    async fn refill<Store: JobStore>(
+        subscriber: &mut Subscriber<Store>,
+        worker_id: Option<WorkerId>,
+        max_in_flight: usize,
+    ) -> Result<(), Error> {
+        let store = &subscriber.state.store;
+        let mut cursor = subscriber.cursor;
+        for job_type in &subscriber.subscription.job_types {
+            for event in store
+                .events_after(*job_type, &Filter::default(), subscriber.cursor)
+                .await?
+            {
+                cursor = cursor.max(event.cursor);
+                if event.new_state != JobState::Pending {
+                    let key = (event.storage_provider_id, event.sector_id).into();
+                    subscriber
+                        .in_flight
+                        .retain(|in_flight| *in_flight != (key, event.job_type));
+                }
+            }
+        }
+        subscriber.cursor = cursor;
+
+        // Requeued, released or expired jobs stay pending but free the slot.
+        if let Some(worker_id) = worker_id {
+            let held: Vec<(JobKey, JobType)> = store
+                .claimed_jobs(worker_id)
+                .await?
+                .iter()
+                .map(|envelope| {
+                    let job = &envelope.job;
+                    (
+                        (job.storage_provider_id(), job.sector_id()).into(),
+                        job.job_type(),
+                    )
+                })
+                .collect();
+            subscriber
+                .in_flight
+                .retain(|in_flight| held.contains(in_flight));
+        }
+
+        let free = max_in_flight.saturating_sub(subscriber.in_flight.len());
+        if free == 0 {
+            return Ok(());
+        }
+
+        let jobs = store
+            .claim_jobs(ClaimJobs {
+                job_types: subscriber.subscription.job_types.clone(),
+                registered_proofs: subscriber.subscription.registered_proofs.clone(),
+                count: free,
+                worker_id,
+            })
+            .await?;
+        for envelope in jobs {
+            let job = &envelope.job;
+            let key = (job.storage_provider_id(), job.sector_id()).into();
+            subscriber.in_flight.push((key, job.job_type()));
+            subscriber.buffered.push_back(envelope);
+        }
+
+        Ok(())
+    }
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Streams state changes after the `Last-Event-ID` header, the requested
+/// cursor, or from now on.
+async fn watch_jobs<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(job_type): Path<JobType>,
+    headers: HeaderMap,
+    Json(request): Json<WatchJobs>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(EventCursor);
+    let cursor = match last_event_id.or(request.cursor) {
+        Some(cursor) => cursor,
+        None => state.store.latest_cursor().await?,
+    };
+
+    let filter = request.filter;
+    let stream = stream::unfold(
+        (state, cursor, VecDeque::<JobStateEvent>::new()),
+        move |(state, mut cursor, mut buffered)| {
+            let filter = filter.clone();
+            async move {
+                loop {
+                    if let Some(event) = buffered.pop_front() {
+                        cursor = event.cursor;
+                        match Event::default()
+                            .event(STATE_EVENT)
+                            .id(event.cursor.0.to_string())
+                            .json_data(&event)
+                        {
+                            Ok(event) => return Some((Ok(event), (state, cursor, buffered))),
+                            Err(err) => {
+                                tracing::error!("Failed to encode state event: {}", err);
+                                continue;
+                            }
+                        }
+                    }
+
+                    match state.store.events_after(job_type, &filter, cursor).await {
+                        Ok(events) if !events.is_empty() => buffered.extend(events),
+                        Ok(_) => tokio::time::sleep(state.config.poll_interval).await,
+                        Err(err) => {
+                            tracing::error!("Failed to read job events: {}", err);
+                            tokio::time::sleep(state.config.poll_interval).await;
+                        }
+                    }
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn register_worker<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(info): Json<WorkerInfo>,
+) -> Result<Json<RegisterWorkerResponse>, Error> {
+    tracing::info!("Registering worker {}", info.hostname);
+    let worker_id = state.store.register_worker(info).await?;
+
+    Ok(Json(RegisterWorkerResponse { worker_id }))
+}
+
+async fn list_workers<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+) -> Result<Json<ListWorkersResponse>, Error> {
+    Ok(Json(ListWorkersResponse {
+        workers: state.store.list_workers().await?,
+    }))
+}
+
+async fn add_jobs_batch<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(request): Json<BatchRequest<JobEnvelope>>,
+) -> Json<BatchResponse<()>> {
+    let mut results = vec![];
+    for job in request.items {
+        results.push(
+            state
+                .store
+                .add_job(job)
+                .await
+                .map_err(|err| err.to_string()),
+        );
+    }
+
+    Json(BatchResponse { results })
+}
+
+async fn submit_outputs_batch<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Json(request): Json<BatchRequest<SubmitSealingJobOutput>>,
+) -> Json<BatchResponse<()>> {
+    let mut results = vec![];
+    for output in request.items {
+        results.push(
+            state
+                .store
+                .submit_output(output)
+                .await
+                .map_err(|err| err.to_string()),
+        );
+    }
+
+    Json(BatchResponse { results })
+}
+
+async fn get_outputs_batch<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(job_type): Path<JobType>,
+    Json(request): Json<BatchRequest<JobKey>>,
+) -> Json<BatchResponse<JobOutputEntry>> {
+    let mut results = vec![];
+    for key in request.items {
+        let entry = match state.store.get_output(key, job_type).await {
+            Ok(Some(Ok(output))) => Ok(JobOutputEntry::Done { output }),
+            Ok(Some(Err(err))) => Ok(JobOutputEntry::Failed { err }),
+            Ok(None) => Ok(JobOutputEntry::Missing),
+            Err(err) => Err(err.to_string()),
+        };
+        results.push(entry);
+    }
+
+    Json(BatchResponse { results })
+}
+
+async fn get_job_states_batch<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(job_type): Path<JobType>,
+    Json(request): Json<BatchRequest<JobKey>>,
+) -> Json<BatchResponse<Option<JobStatus>>> {
+    let mut results = vec![];
+    for key in request.items {
+        results.push(
+            state
+                .store
+                .get_job_state(key, job_type)
+                .await
+                .map_err(|err| err.to_string()),
+        );
+    }
+
+    Json(BatchResponse { results })
+}
+
+async fn generate_ticket<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(request): Path<GenerateTicket>,
+) -> Result<Response, Error> {
+    Ok(Json(
+        state
+            .sectors
+            .generate_ticket(request.storage_provider_id)
+            .await?,
+    )
+    .into_response())
+}
+
+async fn get_sector_paths<Store: JobStore>(
+    State(state): State<AppState<Store>>,
+    Path(request): Path<GetSectorPaths>,
+) -> Result<Response, Error> {
+    Ok(Json(
+        state
+            .sectors
+            .sector_paths(request.storage_provider_id, request.sector_id)
+            .await?,
+    )
+    .into_response())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{sector::LocalSectorProvider, storage::MemoryJobStore, ServerConfig};
+    use futures::StreamExt;
+    use job::{
+        sealing::{PC1Output, PC1},
+        test_utils::pc1,
+    };
+    use job_client::{
+        metrics::PrometheusObserver, JobHttp, JobOutput, JobOutputHttp, SealingJobManagerClient,
+        SealingJobManagerHttpClient,
+    };
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_http_client_round_trip() {
+        let state = AppState::new(
+            MemoryJobStore::new(),
+            LocalSectorProvider::new("/sectors"),
+            ServerConfig::default(),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router(state).into_make_service());
+        tokio::spawn(server);
+
+        let metrics = Arc::new(PrometheusObserver::new());
+        let client = SealingJobManagerHttpClient::builder(uri)
+            .metrics(metrics.clone())
+            .build()
+            .unwrap();
+        client.add_job(pc1(1)).await.unwrap();
+        client.add_job(pc1(2)).await.unwrap();
+
+        let jobs: Vec<PC1> = client.request_jobs(2).await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert!(matches!(
+            client.request_jobs::<PC1>(1).await,
+            Err(job_client::Error::NotEnoughJobs(1))
+        ));
+
+        client
+            .submit_job_output::<PC1>(StorageProviderId(1000), SectorId(1), PC1Output(vec![7]))
+            .await
+            .unwrap();
+        // A retry carries the same idempotency key and gets the first response,
+        // a different output for the same job is rejected.
+        client
+            .submit_job_output::<PC1>(StorageProviderId(1000), SectorId(1), PC1Output(vec![7]))
+            .await
+            .unwrap();
+        assert!(matches!(
+            client
+                .submit_job_output::<PC1>(StorageProviderId(1000), SectorId(1), PC1Output(vec![8]))
+                .await,
+            Err(job_client::Error::AlreadySubmitted(_))
+        ));
+
+        let failure = JobFailure::invalid_input("bad ticket");
+        client
+            .fail_job::<PC1>(StorageProviderId(1000), SectorId(2), failure.clone())
+            .await
+            .unwrap();
+
+        let outputs = client
+            .get_job_outputs::<PC1>(vec![
+                (StorageProviderId(1000), SectorId(1)),
+                (StorageProviderId(1000), SectorId(2)),
+                (StorageProviderId(1000), SectorId(3)),
+            ])
+            .await
+            .unwrap();
+        assert!(matches!(&outputs[0], Ok(Some(JobOutput(Ok(PC1Output(out))))) if out == &[7]));
+        assert!(matches!(&outputs[1], Ok(Some(JobOutput(Err(err)))) if err == &failure));
+        assert!(matches!(&outputs[2], Ok(None)));
+        // A failed job's output comes back as a 424.
+        assert!(matches!(
+            client
+                .get_job_output::<PC1>(StorageProviderId(1000), SectorId(2))
+                .await,
+            Ok(Some(JobOutput(Err(err)))) if err == failure
+        ));
+
+        let states: Vec<JobState> = client
+            .watch_jobs::<PC1>(Filter::default(), Some(EventCursor(0)))
+            .take(2)
+            .map(|event| event.unwrap().new_state)
+            .collect()
+            .await;
+        assert_eq!(states, vec![JobState::Done, JobState::Failed]);
+
+        let paths = client
+            .get_sector_paths(StorageProviderId(1000), SectorId(7))
+            .await
+            .unwrap();
+        assert!(paths.sealed.ends_with("sealed/s-t01000-7"));
+        client
+            .generate_ticket(StorageProviderId(1000))
+            .await
+            .unwrap();
+
+        let fail_job_bytes = serde_json::to_string(&FailJob {
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(2),
+            job_type: JobType::PC1,
+            error: failure,
+        })
+        .unwrap()
+        .len();
+        let fail_job_bytes =
+            format!("job_client_request_bytes_total{{operation=\"FailJob\"}} {fail_job_bytes}");
+
+        let metrics = metrics.render();
+        assert!(!metrics.contains("job_client_errors_total{operation=\"GetJobOutput\""));
+        for line in [
+            fail_job_bytes.as_str(),
+            "job_client_jobs_fetched_total{job_type=\"PC1\"} 2",
+            "job_client_jobs_submitted_total{job_type=\"PC1\"} 2",
+            "job_client_errors_total{operation=\"SubmitOutput\",category=\"Conflict\"} 1",
+            "job_client_requests_total{operation=\"AddJob\"} 2",
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscription_resumes_in_flight_jobs() {
+        use axum::body::HttpBody;
+        use job_client::worker::{WorkerInfo, WorkerResources};
+
+        let config = ServerConfig {
+            poll_interval: std::time::Duration::from_millis(10),
+            ..ServerConfig::default()
+        };
+        let state = AppState::new(
+            MemoryJobStore::new(),
+            LocalSectorProvider::new("/sectors"),
+            config,
+        );
+        let worker_id = state
+            .store
+            .register_worker(WorkerInfo {
+                hostname: "worker".to_string(),
+                job_types: vec![JobType::PC1],
+                registered_proofs: vec![],
+                resources: WorkerResources::default(),
+                versions: Default::default(),
+            })
+            .await
+            .unwrap();
+        state
+            .store
+            .add_job(JobHttp::PC1(pc1(1)).into())
+            .await
+            .unwrap();
+        state
+            .store
+            .add_job(JobHttp::PC1(pc1(2)).into())
+            .await
+            .unwrap();
+
+        let subscribe = |last_event_id: Option<String>| {
+            let state = state.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert(WORKER_ID_HEADER, worker_id.0.to_string().parse().unwrap());
+                if let Some(id) = last_event_id {
+                    headers.insert(LAST_EVENT_ID_HEADER, id.parse().unwrap());
+                }
+                let subscription = JobSubscription {
+                    job_types: vec![JobType::PC1],
+                    registered_proofs: vec![],
+                    max_in_flight: Some(1),
+                };
+                subscribe_jobs(State(state), headers, Json(subscription))
+                    .await
+                    .unwrap()
+                    .into_response()
+                    .into_body()
+            }
+        };
+
+        let mut body = subscribe(None).await;
+        let event = String::from_utf8(body.data().await.unwrap().unwrap().to_vec()).unwrap();
+        assert!(event.contains("\"sector_id\":1"), "{}", event);
+        let id = event
+            .lines()
+            .find_map(|line| line.strip_prefix("id:"))
+            .unwrap()
+            .to_string();
+        drop(body);
+
+        // The resumed subscription still counts sector 1 as in flight.
+        let mut body = subscribe(Some(id)).await;
+        let wait = std::time::Duration::from_millis(200);
+        assert!(tokio::time::timeout(wait, body.data()).await.is_err());
+
+        state
+            .store
+            .submit_output(SubmitSealingJobOutput {
+                storage_provider_id: StorageProviderId(1000),
+                sector_id: SectorId(1),
+                job: JobOutputHttp::PC1(PC1Output(vec![1])),
+            })
+            .await
+            .unwrap();
+        let event = String::from_utf8(body.data().await.unwrap().unwrap().to_vec()).unwrap();
+        assert!(event.contains("\"sector_id\":2"), "{}", event);
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use filecoin_spec::{SectorId, StorageProviderId, Ticket};
+use job_client::sector::{mainnet_epoch, GeneratedTicket, SectorPaths};
+use rand::RngCore;
+
+use crate::{now_ms, Error};
+
+/// Chain and storage facts the job manager hands out but doesn't own.
+#[async_trait]
+pub trait SectorProvider: Send + Sync + 'static {
+    async fn generate_ticket(
+        &self,
+        storage_provider_id: StorageProviderId,
+    ) -> Result<GeneratedTicket, Error>;
+
+    async fn sector_paths(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<SectorPaths, Error>;
+}
+
+/// Draws tickets from local randomness and lays sectors out like lotus under
+/// `root`. Tickets are not tied to the chain, so only for testing and devnets.
+pub struct LocalSectorProvider {
+    root: PathBuf,
+}
+
+impl LocalSectorProvider {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl SectorProvider for LocalSectorProvider {
+    async fn generate_ticket(
+        &self,
+        _storage_provider_id: StorageProviderId,
+    ) -> Result<GeneratedTicket, Error> {
+        let mut ticket = [0; 32];
+        rand::thread_rng().fill_bytes(&mut ticket);
+
+        Ok(GeneratedTicket {
+            ticket: Ticket(ticket),
+            ticket_epoch: mainnet_epoch(now_ms()),
+        })
+    }
+
+    async fn sector_paths(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<SectorPaths, Error> {
+        Ok(SectorPaths::under(
+            &self.root,
+            storage_provider_id,
+            sector_id,
+        ))
+    }
+}
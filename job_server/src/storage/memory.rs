@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use job::JobType;
+use job_client::{
+    failure::JobFailure,
+    history::{JobHistory, JobRef},
+    in_memory::InMemorySealingJobManager,
+    query::{JobHttpPage, JobQuery},
+    worker::{RegisteredWorker, WorkerId, WorkerInfo},
+    CancelJob, EventCursor, FailJob, Filter, JobEnvelope, JobHttp, JobKey, JobOutputHttp,
+    JobStateEvent, JobStatus, ReleaseJob, ReportProgress, RetryJob, SubmitSealingJobOutput,
+};
+
+use super::{ClaimJobs, JobStore, DEFAULT_CLAIM_LEASE, DEFAULT_MAX_ATTEMPTS};
+use crate::Error;
+
+/// Keeps everything in process memory, jobs are lost on restart. Backed by
+/// the job client's [`InMemorySealingJobManager`].
+pub struct MemoryJobStore {
+    jobs: InMemorySealingJobManager,
+}
+
+impl Default for MemoryJobStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryJobStore {
+    pub fn new() -> Self {
+        Self {
+            jobs: InMemorySealingJobManager::new()
+                .with_max_attempts(DEFAULT_MAX_ATTEMPTS)
+                .with_lease(DEFAULT_CLAIM_LEASE),
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.jobs = self.jobs.with_max_attempts(max_attempts);
+        self
+    }
+
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.jobs = self.jobs.with_lease(lease);
+        self
+    }
+}
+
+impl From<job_client::Error> for Error {
+    fn from(err: job_client::Error) -> Self {
+        match err {
+            job_client::Error::NotFound(err) => Error::NotFound(err.message),
+            job_client::Error::Conflict(err) => Error::Conflict(err.message),
+            job_client::Error::AlreadySubmitted(err) => Error::AlreadySubmitted(err.message),
+            job_client::Error::Validation(err) => Error::Validation(err.message),
+            job_client::Error::Json(err) => Error::Json(err),
+            err => Error::Storage(err.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl JobStore for MemoryJobStore {
+    async fn add_job(&self, job: JobEnvelope) -> Result<(), Error> {
+        Ok(self.jobs.insert(job)?)
+    }
+
+    async fn claim_jobs(&self, claim: ClaimJobs) -> Result<Vec<JobEnvelope>, Error> {
+        Ok(self.jobs.claim_jobs(
+            &claim.job_types,
+            &claim.registered_proofs,
+            claim.count,
+            claim.worker_id,
+        )?)
+    }
+
+    async fn claimed_jobs(&self, worker_id: WorkerId) -> Result<Vec<JobEnvelope>, Error> {
+        Ok(self.jobs.claimed_jobs(worker_id)?)
+    }
+
+    async fn query_jobs(&self, job_type: JobType, query: JobQuery) -> Result<JobHttpPage, Error> {
+        Ok(self.jobs.query(job_type, &query)?)
+    }
+
+    async fn get_job(&self, key: JobKey, job_type: JobType) -> Result<Option<JobHttp>, Error> {
+        Ok(self.jobs.job(key, job_type)?)
+    }
+
+    async fn get_job_state(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+    ) -> Result<Option<JobStatus>, Error> {
+        Ok(self.jobs.status(key, job_type))
+    }
+
+    async fn submit_output(&self, output: SubmitSealingJobOutput) -> Result<(), Error> {
+        Ok(self.jobs.submit(output)?)
+    }
+
+    async fn get_output(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+    ) -> Result<Option<Result<JobOutputHttp, JobFailure>>, Error> {
+        Ok(self.jobs.output(key, job_type)?)
+    }
+
+    async fn fail_job(&self, request: FailJob, worker_id: Option<WorkerId>) -> Result<(), Error> {
+        let key = (request.storage_provider_id, request.sector_id).into();
+        Ok(self
+            .jobs
+            .fail(key, request.job_type, request.error, worker_id)?)
+    }
+
+    async fn release_job(
+        &self,
+        request: ReleaseJob,
+        worker_id: Option<WorkerId>,
+    ) -> Result<(), Error> {
+        let key = (request.storage_provider_id, request.sector_id).into();
+        Ok(self.jobs.release(key, request.job_type, worker_id)?)
+    }
+
+    async fn retry_job(&self, request: RetryJob) -> Result<(), Error> {
+        let key = (request.storage_provider_id, request.sector_id).into();
+        Ok(self.jobs.retry(key, request.job_type)?)
+    }
+
+    async fn report_progress(&self, request: ReportProgress) -> Result<(), Error> {
+        let key = (request.storage_provider_id, request.sector_id).into();
+        Ok(self
+            .jobs
+            .progress(key, request.job_type, request.progress)?)
+    }
+
+    async fn cancel_job(&self, request: CancelJob) -> Result<(), Error> {
+        let key = (request.storage_provider_id, request.sector_id).into();
+        Ok(self.jobs.cancel(key, request.job_type, request.reason)?)
+    }
+
+    async fn get_job_history(&self, job: JobRef) -> Result<Option<JobHistory>, Error> {
+        Ok(self.jobs.history(job))
+    }
+
+    async fn events_after(
+        &self,
+        job_type: JobType,
+        filter: &Filter,
+        cursor: EventCursor,
+    ) -> Result<Vec<JobStateEvent>, Error> {
+        Ok(self.jobs.events_after(job_type, filter, cursor))
+    }
+
+    async fn latest_cursor(&self) -> Result<EventCursor, Error> {
+        Ok(self.jobs.latest_cursor())
+    }
+
+    async fn register_worker(&self, info: WorkerInfo) -> Result<WorkerId, Error> {
+        Ok(self.jobs.add_worker(info))
+    }
+
+    async fn worker_seen(&self, worker_id: WorkerId) -> Result<(), Error> {
+        self.jobs.worker_seen(worker_id);
+        Ok(())
+    }
+
+    async fn get_worker(&self, worker_id: WorkerId) -> Result<Option<RegisteredWorker>, Error> {
+        Ok(self.jobs.worker(worker_id))
+    }
+
+    async fn list_workers(&self) -> Result<Vec<RegisteredWorker>, Error> {
+        Ok(self.jobs.workers())
+    }
+}
@@ -0,0 +1,115 @@
+pub mod memory;
+pub mod sqlite;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use filecoin_spec::RegisteredSealProof;
+use job::JobType;
+use job_client::{
+    failure::JobFailure,
+    history::{JobHistory, JobRef},
+    query::{JobHttpPage, JobQuery},
+    worker::{RegisteredWorker, WorkerId, WorkerInfo},
+    CancelJob, EventCursor, FailJob, Filter, JobEnvelope, JobHttp, JobKey, JobOutputHttp,
+    JobStateEvent, JobStatus, ReleaseJob, ReportProgress, RetryJob, SubmitSealingJobOutput,
+};
+
+use crate::Error;
+
+pub use memory::MemoryJobStore;
+pub use sqlite::SqliteJobStore;
+
+/// Claims after which a retryable failure fails the job for good.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// How long a claim lasts unless the worker renews it, by reporting progress
+/// or by any request carrying its worker id.
+pub const DEFAULT_CLAIM_LEASE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimJobs {
+    pub job_types: Vec<JobType>,
+    /// Any proof when empty.
+    pub registered_proofs: Vec<RegisteredSealProof>,
+    pub count: usize,
+    pub worker_id: Option<WorkerId>,
+}
+
+/// Persistence behind the job manager routes. Jobs are identified by their
+/// storage provider, sector and type.
+#[async_trait]
+pub trait JobStore: Send + Sync + 'static {
+    /// Adding a job that already exists is a no-op.
+    async fn add_job(&self, job: JobEnvelope) -> Result<(), Error>;
+
+    /// Claims up to `count` pending jobs, oldest first. Jobs whose claim
+    /// lease ran out are requeued, or failed once out of attempts, first.
+    async fn claim_jobs(&self, claim: ClaimJobs) -> Result<Vec<JobEnvelope>, Error>;
+
+    /// Pending jobs currently claimed by `worker_id`.
+    async fn claimed_jobs(&self, worker_id: WorkerId) -> Result<Vec<JobEnvelope>, Error>;
+
+    async fn query_jobs(&self, job_type: JobType, query: JobQuery) -> Result<JobHttpPage, Error>;
+
+    async fn get_job(&self, key: JobKey, job_type: JobType) -> Result<Option<JobHttp>, Error>;
+
+    async fn get_job_state(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+    ) -> Result<Option<JobStatus>, Error>;
+
+    async fn submit_output(&self, output: SubmitSealingJobOutput) -> Result<(), Error>;
+
+    /// `None` until the job is done or has failed for good.
+    async fn get_output(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+    ) -> Result<Option<Result<JobOutputHttp, JobFailure>>, Error>;
+
+    /// Requeues the job when the failure is retryable and attempts are left.
+    /// With a `worker_id`, only that worker's claim can be failed.
+    async fn fail_job(&self, request: FailJob, worker_id: Option<WorkerId>) -> Result<(), Error>;
+
+    /// Requeues a claimed job without counting the attempt, a no-op when the
+    /// job isn't claimed. With a `worker_id`, another worker's claim is a
+    /// conflict.
+    async fn release_job(
+        &self,
+        request: ReleaseJob,
+        worker_id: Option<WorkerId>,
+    ) -> Result<(), Error>;
+
+    /// Requeues a job that failed for good with its attempts reset, a no-op
+    /// for pending jobs.
+    async fn retry_job(&self, request: RetryJob) -> Result<(), Error>;
+
+    /// Also renews the job's claim lease.
+    async fn report_progress(&self, request: ReportProgress) -> Result<(), Error>;
+
+    async fn cancel_job(&self, request: CancelJob) -> Result<(), Error>;
+
+    async fn get_job_history(&self, job: JobRef) -> Result<Option<JobHistory>, Error>;
+
+    /// State changes of `job_type` jobs matching `filter`, after `cursor`.
+    async fn events_after(
+        &self,
+        job_type: JobType,
+        filter: &Filter,
+        cursor: EventCursor,
+    ) -> Result<Vec<JobStateEvent>, Error>;
+
+    /// Cursor of the most recent event.
+    async fn latest_cursor(&self) -> Result<EventCursor, Error>;
+
+    async fn register_worker(&self, info: WorkerInfo) -> Result<WorkerId, Error>;
+
+    /// Also renews the leases of the worker's claims.
+    async fn worker_seen(&self, worker_id: WorkerId) -> Result<(), Error>;
+
+    async fn get_worker(&self, worker_id: WorkerId) -> Result<Option<RegisteredWorker>, Error>;
+
+    async fn list_workers(&self) -> Result<Vec<RegisteredWorker>, Error>;
+}
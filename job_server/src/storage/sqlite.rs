@@ -0,0 +1,1338 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use filecoin_spec::{SectorId, StorageProviderId};
+use job::{JobId, JobType};
+use job_client::{
+    failure::JobFailure,
+    history::{JobHistory, JobHistoryEvent, JobHistoryEventKind, JobRef},
+    in_memory::LEASE_EXPIRED_CODE,
+    query::{JobHttpPage, JobQuery, Page, PageCursor, SortField, SortOrder, TimeRange},
+    retry,
+    worker::{RegisteredWorker, WorkerId, WorkerInfo},
+    CancelJob, EventCursor, FailJob, Filter, JobEnvelope, JobHttp, JobKey, JobOutputHttp,
+    JobProgress, JobState, JobStateEvent, JobStatus, ReleaseJob, ReportProgress, RetryJob,
+    SubmitSealingJobOutput,
+};
+use rusqlite::{
+    params, params_from_iter, types::Value, Connection, OptionalExtension, TransactionBehavior,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use super::{ClaimJobs, JobStore, DEFAULT_CLAIM_LEASE, DEFAULT_MAX_ATTEMPTS};
+use crate::{now_ms, Error};
+
+/// Applied in order, `PRAGMA user_version` holds the number already applied.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../migrations/0001_create_jobs.sql"),
+    include_str!("../../migrations/0002_claim_leases.sql"),
+];
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Upper bound on events returned by a single `events_after` call.
+const EVENTS_BATCH: i64 = 1000;
+
+const JOB_COLUMNS: &str = "seq, id, storage_provider_id, sector_id, job_type, state, attempts, \
+                           input, output, error";
+
+/// Unit enums are stored by their serde name rather than as quoted JSON.
+fn to_text<T: Serialize>(value: &T) -> Result<String, Error> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(text) => Ok(text),
+        value => Ok(value.to_string()),
+    }
+}
+
+fn from_text<T: DeserializeOwned>(text: String) -> Result<T, Error> {
+    Ok(serde_json::from_value(serde_json::Value::String(text))?)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, Error> {
+    Ok(serde_json::to_string(value)?)
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, Error> {
+    Ok(serde_json::from_str(json)?)
+}
+
+struct JobRow {
+    seq: i64,
+    id: String,
+    storage_provider_id: i64,
+    sector_id: i64,
+    job_type: String,
+    state: String,
+    attempts: u32,
+    input: String,
+    output: Option<String>,
+    error: Option<String>,
+}
+
+impl JobRow {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            seq: row.get(0)?,
+            id: row.get(1)?,
+            storage_provider_id: row.get(2)?,
+            sector_id: row.get(3)?,
+            job_type: row.get(4)?,
+            state: row.get(5)?,
+            attempts: row.get(6)?,
+            input: row.get(7)?,
+            output: row.get(8)?,
+            error: row.get(9)?,
+        })
+    }
+
+    fn state(&self) -> Result<JobState, Error> {
+        from_text(self.state.clone())
+    }
+
+    fn job(&self) -> Result<JobHttp, Error> {
+        from_json(&self.input)
+    }
+
+    fn envelope(&self) -> Result<JobEnvelope, Error> {
+        from_json(&self.input)
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} job for storage_provider_id: {}, sector_id: {}",
+            self.job_type, self.storage_provider_id, self.sector_id
+        )
+    }
+}
+
+fn find(
+    conn: &Connection,
+    storage_provider_id: StorageProviderId,
+    sector_id: SectorId,
+    job_type: JobType,
+) -> Result<Option<JobRow>, Error> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {JOB_COLUMNS} FROM jobs \
+                 WHERE storage_provider_id = ?1 AND sector_id = ?2 AND job_type = ?3"
+            ),
+            params![
+                storage_provider_id.0 as i64,
+                sector_id.0 as i64,
+                job_type.to_string()
+            ],
+            JobRow::from_row,
+        )
+        .optional()?)
+}
+
+fn get(
+    conn: &Connection,
+    storage_provider_id: StorageProviderId,
+    sector_id: SectorId,
+    job_type: JobType,
+) -> Result<JobRow, Error> {
+    find(conn, storage_provider_id, sector_id, job_type)?.ok_or_else(|| {
+        Error::NotFound(format!(
+            "{} job not found for storage_provider_id: {}, sector_id: {}",
+            job_type, storage_provider_id.0, sector_id.0
+        ))
+    })
+}
+
+fn conflict(row: &JobRow) -> Error {
+    Error::Conflict(format!("{} is {}", row.describe(), row.state))
+}
+
+fn not_claimed(row: &JobRow, worker_id: WorkerId) -> Error {
+    Error::Conflict(format!(
+        "{} isn't claimed by worker {}",
+        row.describe(),
+        worker_id.0
+    ))
+}
+
+fn record(conn: &Connection, seq: i64, kind: &JobHistoryEventKind) -> Result<(), Error> {
+    let now = now_ms() as i64;
+    conn.execute(
+        "INSERT INTO job_history (job_seq, timestamp_ms, kind) VALUES (?1, ?2, ?3)",
+        params![seq, now, to_json(kind)?],
+    )?;
+    conn.execute(
+        "UPDATE jobs SET updated_ms = ?2 WHERE seq = ?1",
+        params![seq, now],
+    )?;
+
+    Ok(())
+}
+
+fn transition(conn: &Connection, row: &JobRow, new_state: JobState) -> Result<(), Error> {
+    let new_state = to_text(&new_state)?;
+    conn.execute(
+        "UPDATE jobs SET state = ?2, claimed = 0, claimed_by = NULL, lease_expires_ms = NULL \
+         WHERE seq = ?1",
+        params![row.seq, new_state],
+    )?;
+    conn.execute(
+        "INSERT INTO job_events (job_seq, old_state, new_state, timestamp_ms, error) \
+         SELECT seq, ?2, state, ?3, error FROM jobs WHERE seq = ?1",
+        params![row.seq, row.state, now_ms() as i64],
+    )?;
+
+    Ok(())
+}
+
+fn requeue(conn: &Connection, seq: i64) -> Result<(), Error> {
+    conn.execute(
+        "UPDATE jobs SET claimed = 0, claimed_by = NULL, lease_expires_ms = NULL, \
+         queued_at = (SELECT COALESCE(MAX(queued_at), 0) + 1 FROM jobs) WHERE seq = ?1",
+        params![seq],
+    )?;
+    record(conn, seq, &JobHistoryEventKind::Requeued)
+}
+
+/// Requeues jobs whose claim lease ran out, or fails them for good once they
+/// are out of attempts.
+fn expire_leases(conn: &Connection, max_attempts: u32) -> Result<(), Error> {
+    let rows = conn
+        .prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM jobs \
+             WHERE state = ?1 AND claimed = 1 AND lease_expires_ms <= ?2 ORDER BY queued_at"
+        ))?
+        .query_map(
+            params![to_text(&JobState::Pending)?, now_ms() as i64],
+            JobRow::from_row,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for row in rows {
+        tracing::debug!("Claim of {} expired", row.describe());
+        record(conn, row.seq, &JobHistoryEventKind::LeaseExpired)?;
+        if row.attempts < max_attempts {
+            requeue(conn, row.seq)?;
+        } else {
+            let error = JobFailure::transient("claim lease expired").with_code(LEASE_EXPIRED_CODE);
+            conn.execute(
+                "UPDATE jobs SET error = ?2 WHERE seq = ?1",
+                params![row.seq, to_json(&error)?],
+            )?;
+            transition(conn, &row, JobState::Failed)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn insert_job(conn: &Connection, envelope: &JobEnvelope) -> Result<(), Error> {
+    let job = &envelope.job;
+    let now = now_ms() as i64;
+    let inserted = conn.execute(
+        "INSERT INTO jobs (id, storage_provider_id, sector_id, job_type, registered_proof, \
+         input, state, queued_at, created_ms, updated_ms) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, \
+         (SELECT COALESCE(MAX(queued_at), 0) + 1 FROM jobs), ?8, ?8) \
+         ON CONFLICT (storage_provider_id, sector_id, job_type) DO NOTHING",
+        params![
+            JobId::new().0.to_string(),
+            job.storage_provider_id().0 as i64,
+            job.sector_id().0 as i64,
+            job.job_type().to_string(),
+            to_text(&job.registered_proof())?,
+            to_json(envelope)?,
+            to_text(&JobState::Pending)?,
+            now,
+        ],
+    )?;
+
+    if inserted > 0 {
+        return record(conn, conn.last_insert_rowid(), &JobHistoryEventKind::Added);
+    }
+
+    // Adding a job again is a no-op, unless it ended without an output. Then
+    // it has to be retried instead.
+    let row = get(
+        conn,
+        job.storage_provider_id(),
+        job.sector_id(),
+        job.job_type(),
+    )?;
+    match row.state()? {
+        JobState::Pending | JobState::Done => Ok(()),
+        JobState::Failed | JobState::Cancelled => Err(conflict(&row)),
+    }
+}
+
+fn submit(conn: &Connection, output: &SubmitSealingJobOutput) -> Result<(), Error> {
+    let row = get(
+        conn,
+        output.storage_provider_id,
+        output.sector_id,
+        output.job.job_type(),
+    )?;
+    match row.state()? {
+        JobState::Pending => {}
+        JobState::Done => {
+            return Err(Error::AlreadySubmitted(format!(
+                "output already submitted for {}",
+                row.describe()
+            )))
+        }
+        JobState::Failed | JobState::Cancelled => return Err(conflict(&row)),
+    }
+
+    let json = to_json(&output.job)?;
+    let digest = retry::digest_key(&json);
+    conn.execute(
+        "UPDATE jobs SET output = ?2 WHERE seq = ?1",
+        params![row.seq, json],
+    )?;
+    record(
+        conn,
+        row.seq,
+        &JobHistoryEventKind::OutputSubmitted { digest },
+    )?;
+    transition(conn, &row, JobState::Done)
+}
+
+fn output(
+    conn: &Connection,
+    key: JobKey,
+    job_type: JobType,
+) -> Result<Option<Result<JobOutputHttp, JobFailure>>, Error> {
+    let Some(row) = find(conn, key.storage_provider_id, key.sector_id, job_type)? else {
+        return Ok(None);
+    };
+
+    match (row.state()?, &row.output, &row.error) {
+        (JobState::Done, Some(output), _) => Ok(Some(Ok(from_json(output)?))),
+        (JobState::Failed, _, Some(error)) => Ok(Some(Err(from_json(error)?))),
+        _ => Ok(None),
+    }
+}
+
+fn push_in<T>(
+    clauses: &mut Vec<String>,
+    params: &mut Vec<Value>,
+    column: &str,
+    values: &[T],
+    to_value: impl Fn(&T) -> Result<Value, Error>,
+) -> Result<(), Error> {
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    let placeholders = vec!["?"; values.len()].join(", ");
+    clauses.push(format!("{column} IN ({placeholders})"));
+    for value in values {
+        params.push(to_value(value)?);
+    }
+
+    Ok(())
+}
+
+fn push_range(
+    clauses: &mut Vec<String>,
+    params: &mut Vec<Value>,
+    column: &str,
+    range: &Option<TimeRange>,
+) {
+    let Some(range) = range else {
+        return;
+    };
+    if let Some(from_ms) = range.from_ms {
+        clauses.push(format!("{column} >= ?"));
+        params.push(Value::Integer(from_ms as i64));
+    }
+    if let Some(to_ms) = range.to_ms {
+        clauses.push(format!("{column} <= ?"));
+        params.push(Value::Integer(to_ms as i64));
+    }
+}
+
+fn query(conn: &Connection, job_type: JobType, query: &JobQuery) -> Result<JobHttpPage, Error> {
+    let mut clauses = vec!["job_type = ?".to_string()];
+    let mut params = vec![Value::Text(job_type.to_string())];
+
+    push_in(
+        &mut clauses,
+        &mut params,
+        "storage_provider_id",
+        &query.storage_provider_ids,
+        |id| Ok(Value::Integer(id.0 as i64)),
+    )?;
+    push_in(
+        &mut clauses,
+        &mut params,
+        "sector_id",
+        &query.sector_ids,
+        |id| Ok(Value::Integer(id.0 as i64)),
+    )?;
+    if let Some(range) = query.sector_range {
+        clauses.push("sector_id BETWEEN ? AND ?".to_string());
+        params.push(Value::Integer(range.start.0 as i64));
+        params.push(Value::Integer(range.end.0 as i64));
+    }
+    push_in(&mut clauses, &mut params, "state", &query.states, |state| {
+        Ok(Value::Text(to_text(state)?))
+    })?;
+    push_in(
+        &mut clauses,
+        &mut params,
+        "registered_proof",
+        &query.registered_proofs,
+        |proof| Ok(Value::Text(to_text(proof)?)),
+    )?;
+    push_range(&mut clauses, &mut params, "created_ms", &query.created);
+    push_range(&mut clauses, &mut params, "updated_ms", &query.updated);
+
+    // Unsorted queries are in seq order, which doubles as the sort key.
+    let (column, direction) = match query.sort {
+        Some(sort) => {
+            let column = match sort.field {
+                SortField::StorageProviderId => "storage_provider_id",
+                SortField::SectorId => "sector_id",
+                SortField::CreatedAt => "created_ms",
+                SortField::UpdatedAt => "updated_ms",
+            };
+            (column, sort.order)
+        }
+        None => ("seq", SortOrder::Asc),
+    };
+    let (direction, after) = match direction {
+        SortOrder::Asc => ("ASC", ">"),
+        SortOrder::Desc => ("DESC", "<"),
+    };
+
+    if let Some(cursor) = &query.cursor {
+        let (last_key, last_seq) = cursor
+            .position()
+            .ok_or_else(|| Error::Validation(format!("invalid cursor {}", cursor.0)))?;
+        clauses.push(format!(
+            "({column} {after} ? OR ({column} = ? AND seq > ?))"
+        ));
+        params.push(Value::Integer(last_key as i64));
+        params.push(Value::Integer(last_key as i64));
+        params.push(Value::Integer(last_seq as i64));
+    }
+    let page_size = query.page_size.max(1) as i64;
+    // One extra row tells whether there is a next page.
+    params.push(Value::Integer(page_size + 1));
+
+    let sql = format!(
+        "SELECT {JOB_COLUMNS}, {column} FROM jobs WHERE {} \
+         ORDER BY {column} {direction}, seq LIMIT ?",
+        clauses.join(" AND ")
+    );
+    let mut statement = conn.prepare(&sql)?;
+    let rows = statement
+        .query_map(params_from_iter(params), |row| {
+            Ok((JobRow::from_row(row)?, row.get::<_, i64>(10)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let next_cursor = rows
+        .get(page_size as usize)
+        .and(rows.get(page_size as usize - 1))
+        .map(|(row, sort_key)| PageCursor::after(*sort_key as u64, row.seq as u64));
+    Ok(Page {
+        items: rows
+            .iter()
+            .take(page_size as usize)
+            .map(|(row, _)| row.job())
+            .collect::<Result<_, _>>()?,
+        next_cursor,
+    })
+}
+
+/// Job store in a single SQLite file, safe to share between processes.
+/// Claims run in `IMMEDIATE` transactions so a job is never handed out twice.
+pub struct SqliteJobStore {
+    conn: Arc<Mutex<Connection>>,
+    max_attempts: u32,
+    lease: Duration,
+}
+
+impl SqliteJobStore {
+    /// Opens or creates the database at `path` and applies pending migrations.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self, Error> {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        migrate(&mut conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            lease: DEFAULT_CLAIM_LEASE,
+        })
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    fn lease_expiry(&self) -> i64 {
+        now_ms().saturating_add(self.lease.as_millis() as u64) as i64
+    }
+
+    /// Runs `f` on the blocking pool, SQLite calls block on disk and locks.
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || f(&mut conn.lock().unwrap()))
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?
+    }
+
+    /// Runs `f` in a write transaction, committed only when `f` succeeds.
+    async fn write<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        self.run(|conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let result = f(&tx)?;
+            tx.commit()?;
+            Ok(result)
+        })
+        .await
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), Error> {
+    // The version is read under the same lock the migrations run under, so
+    // processes opening the file at the same time apply each one once.
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Exclusive)?;
+    let version: usize = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tracing::info!("Applying job store migration {}", index + 1);
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+#[async_trait]
+impl JobStore for SqliteJobStore {
+    async fn add_job(&self, job: JobEnvelope) -> Result<(), Error> {
+        self.write(move |conn| insert_job(conn, &job)).await
+    }
+
+    async fn claim_jobs(&self, claim: ClaimJobs) -> Result<Vec<JobEnvelope>, Error> {
+        if claim.job_types.is_empty() || claim.count == 0 {
+            return Ok(vec![]);
+        }
+
+        let max_attempts = self.max_attempts;
+        let lease_expires_ms = self.lease_expiry();
+        self.write(move |conn| {
+            expire_leases(conn, max_attempts)?;
+
+            let mut clauses = vec!["state = ?".to_string(), "claimed = 0".to_string()];
+            let mut params = vec![Value::Text(to_text(&JobState::Pending)?)];
+            push_in(
+                &mut clauses,
+                &mut params,
+                "job_type",
+                &claim.job_types,
+                |job_type| Ok(Value::Text(job_type.to_string())),
+            )?;
+            push_in(
+                &mut clauses,
+                &mut params,
+                "registered_proof",
+                &claim.registered_proofs,
+                |proof| Ok(Value::Text(to_text(proof)?)),
+            )?;
+            params.push(Value::Integer(claim.count as i64));
+
+            let sql = format!(
+                "SELECT {JOB_COLUMNS} FROM jobs WHERE {} ORDER BY queued_at LIMIT ?",
+                clauses.join(" AND ")
+            );
+            let rows = conn
+                .prepare(&sql)?
+                .query_map(params_from_iter(params), JobRow::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let kind = JobHistoryEventKind::Claimed {
+                worker_id: claim.worker_id,
+            };
+            let claimed_by = claim.worker_id.map(|worker_id| worker_id.0.to_string());
+            let mut jobs = vec![];
+            for row in rows {
+                conn.execute(
+                    "UPDATE jobs SET claimed = 1, claimed_by = ?2, lease_expires_ms = ?3, \
+                     attempts = attempts + 1 WHERE seq = ?1",
+                    params![row.seq, claimed_by, lease_expires_ms],
+                )?;
+                record(conn, row.seq, &kind)?;
+                jobs.push(row.envelope()?);
+            }
+
+            Ok(jobs)
+        })
+        .await
+    }
+
+    async fn claimed_jobs(&self, worker_id: WorkerId) -> Result<Vec<JobEnvelope>, Error> {
+        self.run(move |conn| {
+            conn.prepare(&format!(
+                "SELECT {JOB_COLUMNS} FROM jobs \
+                 WHERE claimed_by = ?1 AND claimed = 1 AND state = ?2 ORDER BY queued_at"
+            ))?
+            .query_map(
+                params![worker_id.0.to_string(), to_text(&JobState::Pending)?],
+                JobRow::from_row,
+            )?
+            .map(|row| row?.envelope())
+            .collect()
+        })
+        .await
+    }
+
+    async fn query_jobs(
+        &self,
+        job_type: JobType,
+        job_query: JobQuery,
+    ) -> Result<JobHttpPage, Error> {
+        self.run(move |conn| query(conn, job_type, &job_query))
+            .await
+    }
+
+    async fn get_job(&self, key: JobKey, job_type: JobType) -> Result<Option<JobHttp>, Error> {
+        self.run(move |conn| {
+            find(conn, key.storage_provider_id, key.sector_id, job_type)?
+                .map(|row| row.job())
+                .transpose()
+        })
+        .await
+    }
+
+    async fn get_job_state(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+    ) -> Result<Option<JobStatus>, Error> {
+        self.run(move |conn| {
+            let status = conn
+                .query_row(
+                    "SELECT state, progress FROM jobs \
+                     WHERE storage_provider_id = ?1 AND sector_id = ?2 AND job_type = ?3",
+                    params![
+                        key.storage_provider_id.0 as i64,
+                        key.sector_id.0 as i64,
+                        job_type.to_string()
+                    ],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+                )
+                .optional()?;
+
+            status
+                .map(|(state, progress)| {
+                    Ok(JobStatus {
+                        state: from_text(state)?,
+                        progress: progress
+                            .map(|progress| from_json::<JobProgress>(&progress))
+                            .transpose()?,
+                    })
+                })
+                .transpose()
+        })
+        .await
+    }
+
+    async fn submit_output(&self, output: SubmitSealingJobOutput) -> Result<(), Error> {
+        self.write(move |conn| submit(conn, &output)).await
+    }
+
+    async fn get_output(
+        &self,
+        key: JobKey,
+        job_type: JobType,
+    ) -> Result<Option<Result<JobOutputHttp, JobFailure>>, Error> {
+        self.run(move |conn| output(conn, key, job_type)).await
+    }
+
+    async fn fail_job(&self, request: FailJob, worker_id: Option<WorkerId>) -> Result<(), Error> {
+        let max_attempts = self.max_attempts;
+        self.write(move |conn| {
+            let row = get(
+                conn,
+                request.storage_provider_id,
+                request.sector_id,
+                request.job_type,
+            )?;
+            if row.state()? != JobState::Pending {
+                return Err(conflict(&row));
+            }
+
+            let error = request.error;
+            let claimed_by = worker_id.map(|worker_id| worker_id.0.to_string());
+            let failed = conn.execute(
+                "UPDATE jobs SET error = ?2, claimed = 0, claimed_by = NULL, \
+                 lease_expires_ms = NULL \
+                 WHERE seq = ?1 AND (?3 IS NULL OR (claimed = 1 AND claimed_by = ?3))",
+                params![row.seq, to_json(&error)?, claimed_by],
+            )?;
+            if let (0, Some(worker_id)) = (failed, worker_id) {
+                return Err(not_claimed(&row, worker_id));
+            }
+            let requeue_job = error.retryable && row.attempts < max_attempts;
+            record(conn, row.seq, &JobHistoryEventKind::Failed { error })?;
+
+            if requeue_job {
+                requeue(conn, row.seq)
+            } else {
+                transition(conn, &row, JobState::Failed)
+            }
+        })
+        .await
+    }
+
+    async fn release_job(
+        &self,
+        request: ReleaseJob,
+        worker_id: Option<WorkerId>,
+    ) -> Result<(), Error> {
+        self.write(move |conn| {
+            let row = get(
+                conn,
+                request.storage_provider_id,
+                request.sector_id,
+                request.job_type,
+            )?;
+            let claimed_by = worker_id.map(|worker_id| worker_id.0.to_string());
+            // Keeps its place in the queue.
+            let released = conn.execute(
+                "UPDATE jobs SET claimed = 0, claimed_by = NULL, lease_expires_ms = NULL, \
+                 attempts = MAX(attempts - 1, 0) \
+                 WHERE seq = ?1 AND state = ?2 AND claimed = 1 AND (?3 IS NULL OR claimed_by = ?3)",
+                params![row.seq, to_text(&JobState::Pending)?, claimed_by],
+            )?;
+            if released > 0 {
+                record(conn, row.seq, &JobHistoryEventKind::Released)?;
+                return Ok(());
+            }
+
+            // Nothing to release unless another worker holds the claim.
+            if let Some(worker_id) = worker_id {
+                let claimed: bool = conn.query_row(
+                    "SELECT claimed = 1 AND state = ?2 FROM jobs WHERE seq = ?1",
+                    params![row.seq, to_text(&JobState::Pending)?],
+                    |row| row.get(0),
+                )?;
+                if claimed {
+                    return Err(not_claimed(&row, worker_id));
+                }
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn retry_job(&self, request: RetryJob) -> Result<(), Error> {
+        self.write(move |conn| {
+            let row = get(
+                conn,
+                request.storage_provider_id,
+                request.sector_id,
+                request.job_type,
+            )?;
+            match row.state()? {
+                JobState::Failed => {}
+                JobState::Pending => return Ok(()),
+                JobState::Done | JobState::Cancelled => return Err(conflict(&row)),
+            }
+
+            conn.execute(
+                "UPDATE jobs SET attempts = 0, error = NULL, progress = NULL WHERE seq = ?1",
+                params![row.seq],
+            )?;
+            requeue(conn, row.seq)?;
+            transition(conn, &row, JobState::Pending)
+        })
+        .await
+    }
+
+    async fn report_progress(&self, request: ReportProgress) -> Result<(), Error> {
+        let lease_expires_ms = self.lease_expiry();
+        self.write(move |conn| {
+            let row = get(
+                conn,
+                request.storage_provider_id,
+                request.sector_id,
+                request.job_type,
+            )?;
+            conn.execute(
+                "UPDATE jobs SET progress = ?2, \
+                 lease_expires_ms = CASE WHEN claimed = 1 THEN ?3 END WHERE seq = ?1",
+                params![row.seq, to_json(&request.progress)?, lease_expires_ms],
+            )?;
+            record(
+                conn,
+                row.seq,
+                &JobHistoryEventKind::Progress {
+                    progress: request.progress,
+                },
+            )
+        })
+        .await
+    }
+
+    async fn cancel_job(&self, request: CancelJob) -> Result<(), Error> {
+        self.write(move |conn| {
+            let row = get(
+                conn,
+                request.storage_provider_id,
+                request.sector_id,
+                request.job_type,
+            )?;
+            match row.state()? {
+                JobState::Pending => {}
+                JobState::Cancelled => return Ok(()),
+                JobState::Done | JobState::Failed => return Err(conflict(&row)),
+            }
+
+            record(
+                conn,
+                row.seq,
+                &JobHistoryEventKind::Cancelled {
+                    reason: request.reason,
+                },
+            )?;
+            transition(conn, &row, JobState::Cancelled)
+        })
+        .await
+    }
+
+    async fn get_job_history(&self, job: JobRef) -> Result<Option<JobHistory>, Error> {
+        self.run(move |conn| {
+            let row = match job {
+                JobRef::Sector {
+                    storage_provider_id,
+                    sector_id,
+                    job_type,
+                } => find(conn, storage_provider_id, sector_id, job_type)?,
+                JobRef::Id(id) => conn
+                    .query_row(
+                        &format!("SELECT {JOB_COLUMNS} FROM jobs WHERE id = ?1"),
+                        params![id.0.to_string()],
+                        JobRow::from_row,
+                    )
+                    .optional()?,
+            };
+            let Some(row) = row else {
+                return Ok(None);
+            };
+
+            let job_id =
+                JobId(Uuid::parse_str(&row.id).map_err(|err| Error::Storage(err.to_string()))?);
+            let job_type: JobType = from_text(row.job_type.clone())?;
+            let events = conn
+                .prepare(
+                    "SELECT timestamp_ms, kind FROM job_history WHERE job_seq = ?1 ORDER BY id",
+                )?
+                .query_map(params![row.seq], |history| {
+                    Ok((history.get::<_, i64>(0)?, history.get::<_, String>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .map(|(timestamp_ms, kind)| {
+                    Ok(JobHistoryEvent {
+                        job_id,
+                        storage_provider_id: StorageProviderId(row.storage_provider_id as u64),
+                        sector_id: SectorId(row.sector_id as u64),
+                        job_type,
+                        timestamp_ms: timestamp_ms as u64,
+                        kind: from_json(&kind)?,
+                    })
+                })
+                .collect::<Result<_, Error>>()?;
+
+            Ok(Some(JobHistory { events }))
+        })
+        .await
+    }
+
+    async fn events_after(
+        &self,
+        job_type: JobType,
+        filter: &Filter,
+        cursor: EventCursor,
+    ) -> Result<Vec<JobStateEvent>, Error> {
+        let filter = filter.clone();
+        self.run(move |conn| {
+            let mut clauses = vec!["e.cursor > ?".to_string(), "j.job_type = ?".to_string()];
+            let mut params = vec![
+                Value::Integer(cursor.0 as i64),
+                Value::Text(job_type.to_string()),
+            ];
+            if let Some(id) = filter.storage_provider_id {
+                clauses.push("j.storage_provider_id = ?".to_string());
+                params.push(Value::Integer(id.0 as i64));
+            }
+            if let Some(id) = filter.sector_id {
+                clauses.push("j.sector_id = ?".to_string());
+                params.push(Value::Integer(id.0 as i64));
+            }
+            if let Some(state) = &filter.state {
+                clauses.push("e.new_state = ?".to_string());
+                params.push(Value::Text(to_text(state)?));
+            }
+            if let Some(proof) = &filter.registered_proof {
+                clauses.push("j.registered_proof = ?".to_string());
+                params.push(Value::Text(to_text(proof)?));
+            }
+            params.push(Value::Integer(EVENTS_BATCH));
+
+            let sql = format!(
+                "SELECT e.cursor, j.storage_provider_id, j.sector_id, e.old_state, e.new_state, \
+                 e.timestamp_ms, e.error FROM job_events e JOIN jobs j ON j.seq = e.job_seq \
+                 WHERE {} ORDER BY e.cursor LIMIT ?",
+                clauses.join(" AND ")
+            );
+            let rows = conn
+                .prepare(&sql)?
+                .query_map(params_from_iter(params), |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, i64>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            rows.into_iter()
+                .map(
+                    |(
+                        cursor,
+                        storage_provider_id,
+                        sector_id,
+                        old_state,
+                        new_state,
+                        timestamp_ms,
+                        error,
+                    )| {
+                        Ok(JobStateEvent {
+                            cursor: EventCursor(cursor as u64),
+                            storage_provider_id: StorageProviderId(storage_provider_id as u64),
+                            sector_id: SectorId(sector_id as u64),
+                            job_type,
+                            old_state: old_state.map(from_text).transpose()?,
+                            new_state: from_text(new_state)?,
+                            timestamp_ms: timestamp_ms as u64,
+                            error: error.map(|error| from_json(&error)).transpose()?,
+                        })
+                    },
+                )
+                .collect()
+        })
+        .await
+    }
+
+    async fn latest_cursor(&self) -> Result<EventCursor, Error> {
+        self.run(|conn| {
+            let cursor: i64 = conn.query_row(
+                "SELECT COALESCE(MAX(cursor), 0) FROM job_events",
+                [],
+                |row| row.get(0),
+            )?;
+            Ok(EventCursor(cursor as u64))
+        })
+        .await
+    }
+
+    async fn register_worker(&self, info: WorkerInfo) -> Result<WorkerId, Error> {
+        self.write(move |conn| {
+            let worker_id = WorkerId(Uuid::new_v4());
+            let now = now_ms() as i64;
+            conn.execute(
+                "INSERT INTO workers (worker_id, info, registered_at_ms, last_seen_ms) \
+                 VALUES (?1, ?2, ?3, ?3)",
+                params![worker_id.0.to_string(), to_json(&info)?, now],
+            )?;
+
+            Ok(worker_id)
+        })
+        .await
+    }
+
+    async fn worker_seen(&self, worker_id: WorkerId) -> Result<(), Error> {
+        let lease_expires_ms = self.lease_expiry();
+        self.write(move |conn| {
+            let worker_id = worker_id.0.to_string();
+            conn.execute(
+                "UPDATE workers SET last_seen_ms = ?2 WHERE worker_id = ?1",
+                params![worker_id, now_ms() as i64],
+            )?;
+            conn.execute(
+                "UPDATE jobs SET lease_expires_ms = ?2 WHERE claimed_by = ?1 AND claimed = 1",
+                params![worker_id, lease_expires_ms],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_worker(&self, worker_id: WorkerId) -> Result<Option<RegisteredWorker>, Error> {
+        self.run(move |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT info, registered_at_ms, last_seen_ms FROM workers \
+                     WHERE worker_id = ?1",
+                    params![worker_id.0.to_string()],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, i64>(1)?,
+                            row.get::<_, i64>(2)?,
+                        ))
+                    },
+                )
+                .optional()?;
+
+            row.map(|(info, registered_at_ms, last_seen_ms)| {
+                Ok(RegisteredWorker {
+                    worker_id,
+                    info: from_json(&info)?,
+                    registered_at_ms: registered_at_ms as u64,
+                    last_seen_ms: last_seen_ms as u64,
+                })
+            })
+            .transpose()
+        })
+        .await
+    }
+
+    async fn list_workers(&self) -> Result<Vec<RegisteredWorker>, Error> {
+        self.run(|conn| {
+            let rows = conn
+                .prepare(
+                    "SELECT worker_id, info, registered_at_ms, last_seen_ms FROM workers \
+                     ORDER BY registered_at_ms",
+                )?
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            rows.into_iter()
+                .map(|(worker_id, info, registered_at_ms, last_seen_ms)| {
+                    Ok(RegisteredWorker {
+                        worker_id: WorkerId(
+                            Uuid::parse_str(&worker_id)
+                                .map_err(|err| Error::Storage(err.to_string()))?,
+                        ),
+                        info: from_json(&info)?,
+                        registered_at_ms: registered_at_ms as u64,
+                        last_seen_ms: last_seen_ms as u64,
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use job::{sealing::PC1Output, test_utils::pc1};
+
+    fn claim(count: usize) -> ClaimJobs {
+        ClaimJobs {
+            job_types: vec![JobType::PC1],
+            registered_proofs: vec![],
+            count,
+            worker_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_claims_are_exclusive_and_persisted() {
+        let path = std::env::temp_dir().join(format!("job_store_{}.sqlite", Uuid::new_v4()));
+
+        {
+            let store = SqliteJobStore::open(&path).unwrap();
+            for sector_id in 0..20 {
+                store
+                    .add_job(JobHttp::PC1(pc1(sector_id)).into())
+                    .await
+                    .unwrap();
+            }
+            // Re-adding is a no-op.
+            store.add_job(JobHttp::PC1(pc1(0)).into()).await.unwrap();
+        }
+
+        // Two stores on the same file behave like two server processes.
+        let first = SqliteJobStore::open(&path).unwrap();
+        let second = SqliteJobStore::open(&path).unwrap();
+        let (a, b) = tokio::join!(first.claim_jobs(claim(15)), second.claim_jobs(claim(15)));
+        let mut sectors: Vec<u64> = a
+            .unwrap()
+            .into_iter()
+            .chain(b.unwrap())
+            .map(|envelope| envelope.job.sector_id().0)
+            .collect();
+        sectors.sort();
+        assert_eq!(sectors, (0..20).collect::<Vec<_>>());
+
+        first
+            .fail_job(
+                FailJob {
+                    storage_provider_id: StorageProviderId(1000),
+                    sector_id: SectorId(3),
+                    job_type: JobType::PC1,
+                    error: JobFailure::invalid_input("bad ticket"),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        let key = (StorageProviderId(1000), SectorId(3)).into();
+        assert!(matches!(
+            second.get_output(key, JobType::PC1).await.unwrap(),
+            Some(Err(_))
+        ));
+
+        let events = second
+            .events_after(JobType::PC1, &Filter::default(), EventCursor(0))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].new_state, JobState::Failed);
+
+        drop((first, second));
+        remove_database(&path);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_requeues_retryable_failures() {
+        let store = SqliteJobStore::open_in_memory()
+            .unwrap()
+            .with_max_attempts(2);
+        store.add_job(JobHttp::PC1(pc1(1)).into()).await.unwrap();
+        let key: JobKey = (StorageProviderId(1000), SectorId(1)).into();
+        let fail = || FailJob {
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(1),
+            job_type: JobType::PC1,
+            error: JobFailure::transient("worker lost its GPU"),
+        };
+
+        assert_eq!(store.claim_jobs(claim(1)).await.unwrap().len(), 1);
+        store.fail_job(fail(), None).await.unwrap();
+        assert!(store.get_output(key, JobType::PC1).await.unwrap().is_none());
+
+        // Requeued with its attempt counted, the second failure is final.
+        assert_eq!(store.claim_jobs(claim(1)).await.unwrap().len(), 1);
+        store.fail_job(fail(), None).await.unwrap();
+        assert!(store.claim_jobs(claim(1)).await.unwrap().is_empty());
+        assert!(matches!(
+            store.get_output(key, JobType::PC1).await.unwrap(),
+            Some(Err(failure)) if failure.details == "worker lost its GPU"
+        ));
+
+        // A failed job isn't added again, it has to be retried.
+        assert!(matches!(
+            store.add_job(JobHttp::PC1(pc1(1)).into()).await,
+            Err(Error::Conflict(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_claim_leases() {
+        let store = SqliteJobStore::open_in_memory()
+            .unwrap()
+            .with_max_attempts(2)
+            .with_lease(Duration::ZERO);
+        store.add_job(JobHttp::PC1(pc1(1)).into()).await.unwrap();
+        let key: JobKey = (StorageProviderId(1000), SectorId(1)).into();
+
+        // A release keeps the attempt count, an expired lease uses it up.
+        assert_eq!(store.claim_jobs(claim(1)).await.unwrap().len(), 1);
+        let release = || ReleaseJob {
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(1),
+            job_type: JobType::PC1,
+        };
+        store.release_job(release(), None).await.unwrap();
+        assert_eq!(store.claim_jobs(claim(1)).await.unwrap().len(), 1);
+        assert_eq!(store.claim_jobs(claim(1)).await.unwrap().len(), 1);
+        assert!(store.claim_jobs(claim(1)).await.unwrap().is_empty());
+        assert!(matches!(
+            store.get_output(key, JobType::PC1).await.unwrap(),
+            Some(Err(failure)) if failure.code.as_deref() == Some(LEASE_EXPIRED_CODE)
+        ));
+
+        // A late release or failure from a worker whose lease ran out leaves
+        // the claim of the worker that took the job over alone.
+        let store = SqliteJobStore::open_in_memory()
+            .unwrap()
+            .with_lease(Duration::ZERO);
+        store.add_job(JobHttp::PC1(pc1(1)).into()).await.unwrap();
+        let (a, b) = (WorkerId(Uuid::new_v4()), WorkerId(Uuid::new_v4()));
+        let claim_by = |worker_id| ClaimJobs {
+            worker_id: Some(worker_id),
+            ..claim(1)
+        };
+        assert_eq!(store.claim_jobs(claim_by(a)).await.unwrap().len(), 1);
+        assert_eq!(store.claim_jobs(claim_by(b)).await.unwrap().len(), 1);
+        assert!(matches!(
+            store.release_job(release(), Some(a)).await,
+            Err(Error::Conflict(_))
+        ));
+        let failure = FailJob {
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(1),
+            job_type: JobType::PC1,
+            error: JobFailure::invalid_input("bad ticket"),
+        };
+        assert!(matches!(
+            store.fail_job(failure, Some(a)).await,
+            Err(Error::Conflict(_))
+        ));
+        assert_eq!(store.claimed_jobs(b).await.unwrap().len(), 1);
+        store.release_job(release(), Some(b)).await.unwrap();
+        assert!(store.claimed_jobs(b).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sqlite_migration_leases_old_claims() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO jobs (id, storage_provider_id, sector_id, job_type, registered_proof, \
+             input, state, claimed, queued_at, created_ms, updated_ms) \
+             VALUES ('job', 1000, 1, 'PC1', 'StackedDrg2KiBV1', '{}', 'Pending', 1, 1, 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        let lease_expires_ms: i64 = conn
+            .query_row("SELECT lease_expires_ms FROM jobs", [], |row| row.get(0))
+            .unwrap();
+        let now = now_ms() as i64;
+        assert!(lease_expires_ms > now);
+        assert!(lease_expires_ms <= now + DEFAULT_CLAIM_LEASE.as_millis() as i64);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_output_round_trip() {
+        let store = SqliteJobStore::open_in_memory().unwrap();
+        store.add_job(JobHttp::PC1(pc1(1)).into()).await.unwrap();
+        store.claim_jobs(claim(1)).await.unwrap();
+
+        let output = || SubmitSealingJobOutput {
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(1),
+            job: JobOutputHttp::PC1(PC1Output(vec![1, 2, 3])),
+        };
+        store.submit_output(output()).await.unwrap();
+        assert!(matches!(
+            store.submit_output(output()).await,
+            Err(Error::AlreadySubmitted(_))
+        ));
+
+        let key = (StorageProviderId(1000), SectorId(1)).into();
+        assert!(matches!(
+            store.get_output(key, JobType::PC1).await.unwrap(),
+            Some(Ok(JobOutputHttp::PC1(PC1Output(bytes)))) if bytes == [1, 2, 3]
+        ));
+        assert_eq!(
+            store
+                .get_job_state(key, JobType::PC1)
+                .await
+                .unwrap()
+                .unwrap()
+                .state,
+            JobState::Done
+        );
+    }
+
+    fn remove_database(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.as_os_str().to_owned();
+            file.push(suffix);
+            std::fs::remove_file(file).ok();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_query_pages_by_keyset() {
+        let store = SqliteJobStore::open_in_memory().unwrap();
+        for sector_id in 1..=3 {
+            store
+                .add_job(JobHttp::PC1(pc1(sector_id)).into())
+                .await
+                .unwrap();
+        }
+        let query = JobQuery::new()
+            .sort_by(SortField::SectorId, SortOrder::Desc)
+            .page_size(2);
+        let sectors = |page: &JobHttpPage| -> Vec<u64> {
+            page.items.iter().map(|job| job.sector_id().0).collect()
+        };
+
+        let page = store.query_jobs(JobType::PC1, query.clone()).await.unwrap();
+        assert_eq!(sectors(&page), vec![3, 2]);
+
+        // A job sorted before the cursor doesn't shift the next page.
+        store.add_job(JobHttp::PC1(pc1(4)).into()).await.unwrap();
+        let page = store
+            .query_jobs(JobType::PC1, query.cursor(page.next_cursor.unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(sectors(&page), vec![1]);
+        assert!(page.next_cursor.is_none());
+
+        assert!(matches!(
+            store
+                .query_jobs(
+                    JobType::PC1,
+                    JobQuery::new().cursor(PageCursor("2".to_string()))
+                )
+                .await,
+            Err(Error::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+use job::sector::{InvalidTransition, SectorState};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Client(#[from] job_client::Error),
+
+    #[error("{0}")]
+    Lotus(#[from] lotus::Error),
+
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    InvalidTransition(#[from] InvalidTransition),
+
+    #[error("Invalid CID {0}")]
+    InvalidCid(String),
+
+    #[error("Beacon randomness has {0} bytes, expected 32")]
+    InvalidSeed(usize),
+
+    #[error("{state} is missing {missing}")]
+    MissingData {
+        state: SectorState,
+        missing: &'static str,
+    },
+}
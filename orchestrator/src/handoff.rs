@@ -0,0 +1,181 @@
+use filecoin_spec::{sector_size, Commitment};
+use fvm_shared::{commcid, piece::UnpaddedPieceSize};
+use job_client::sector_storage::{http::HttpSectorStore, SectorFile};
+use lotus::types::miner::{LotusState, Meta, Piece, Sector, SectorLocation, SectorPiece};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{missing, record::SectorRecord, Error, HandoffMeta};
+
+/// Hands sealed sectors to lotus as proving, with their files served by
+/// `storage` and the commitments the pre-commit landed with.
+pub struct LotusHandoff {
+    storage: HttpSectorStore,
+    commit1_endpoint: Url,
+    commit2_endpoint: Url,
+    sealing_done_endpoint: Url,
+}
+
+impl LotusHandoff {
+    pub fn new(
+        storage: HttpSectorStore,
+        commit1_endpoint: Url,
+        commit2_endpoint: Url,
+        sealing_done_endpoint: Url,
+    ) -> Self {
+        Self {
+            storage,
+            commit1_endpoint,
+            commit2_endpoint,
+            sealing_done_endpoint,
+        }
+    }
+
+    fn location(&self, file: SectorFile) -> SectorLocation {
+        SectorLocation {
+            local: false,
+            url: self.storage.file_url(&file),
+        }
+    }
+}
+
+impl HandoffMeta for LotusHandoff {
+    fn meta(&self, record: &SectorRecord) -> Result<Meta, Error> {
+        let (storage_provider_id, sector_id) = record.key();
+        let ticket = record
+            .ticket
+            .clone()
+            .ok_or_else(|| missing(record, "ticket"))?;
+        let comm_r = record
+            .sealed_cid
+            .as_deref()
+            .ok_or_else(|| missing(record, "sealed CID"))?
+            .parse()
+            .map_err(|_| Error::InvalidCid(record.sealed_cid.clone().unwrap_or_default()))?;
+        let comm_d = match &record.unsealed_cid {
+            Some(cid) => cid.parse().map_err(|_| Error::InvalidCid(cid.clone()))?,
+            // Committed capacity sectors are all zeros.
+            None => to_cid(commcid::data_commitment_v1_to_cid(&zero_commitment(
+                sector_size(&record.spec.registered_proof),
+            )))?,
+        };
+        let pieces = record
+            .spec
+            .piece_infos
+            .iter()
+            .map(|piece| {
+                Ok(SectorPiece {
+                    piece: Piece {
+                        cid: to_cid(commcid::piece_commitment_v1_to_cid(&piece.commitment.0))?,
+                        size: UnpaddedPieceSize(piece.size.0).padded(),
+                    },
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Meta {
+            state: LotusState::Proving,
+            sector: Sector {
+                miner: storage_provider_id.0,
+                number: sector_id.0,
+            },
+            proof_type: record.spec.registered_proof,
+            pieces,
+            ticket_value: ticket.ticket,
+            ticket_epoch: ticket.ticket_epoch.0,
+            // Lotus only reads the PC1 output to run PC2 itself.
+            pre_commit1_out: Commitment([0; 32]),
+            comm_d,
+            comm_r,
+            data_unsealed: self.location(SectorFile::unsealed(storage_provider_id, sector_id)),
+            data_sealed: self.location(SectorFile::sealed(storage_provider_id, sector_id)),
+            data_cache: self.location(SectorFile::cache_dir(storage_provider_id, sector_id)),
+            remote_commit1_endpoint: self.commit1_endpoint.clone(),
+            remote_commit2_endpoint: self.commit2_endpoint.clone(),
+            remote_sealing_done_endpoint: self.sealing_done_endpoint.clone(),
+        })
+    }
+}
+
+/// Root of the sha256 trunc254 merkle tree over `size` zero bytes. fvm_shared
+/// only has these behind its proofs feature.
+fn zero_commitment(size: u64) -> [u8; 32] {
+    let mut commitment = [0; 32];
+    for _ in 0..(size / 32).trailing_zeros() {
+        let mut hasher = Sha256::new();
+        hasher.update(commitment);
+        hasher.update(commitment);
+        commitment = hasher.finalize().into();
+        commitment[31] &= 0b0011_1111;
+    }
+    commitment
+}
+
+/// fvm_shared is on a newer `cid` than the lotus types.
+fn to_cid(cid: Result<impl ToString, &'static str>) -> Result<cid::Cid, Error> {
+    let cid = cid
+        .map_err(|err| Error::InvalidCid(err.to_string()))?
+        .to_string();
+    cid.parse().map_err(|_| Error::InvalidCid(cid))
+}
+
+#[cfg(test)]
+mod test {
+    use filecoin_spec::{
+        ChainEpoch, PieceInfo, RegisteredSealProof, SectorId, StorageProviderId, Ticket,
+        UnpaddedBytesAmount,
+    };
+    use job_client::sector::GeneratedTicket;
+
+    use super::*;
+    use crate::record::SectorSpec;
+
+    #[test]
+    fn test_lotus_handoff_meta() {
+        let mut record = SectorRecord::new(SectorSpec {
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(7),
+            registered_proof: RegisteredSealProof::StackedDrg2KiBV1_1,
+            piece_infos: vec![PieceInfo {
+                commitment: Commitment([1; 32]),
+                size: UnpaddedBytesAmount(2032),
+            }],
+        });
+        record.ticket = Some(GeneratedTicket {
+            ticket: Ticket([2; 32]),
+            ticket_epoch: ChainEpoch(42),
+        });
+        let sealed_cid = commcid::replica_commitment_v1_to_cid(&[3; 32])
+            .unwrap()
+            .to_string();
+        record.sealed_cid = Some(sealed_cid.clone());
+
+        let endpoint = Url::parse("http://orchestrator/").unwrap();
+        let handoff = LotusHandoff::new(
+            HttpSectorStore::new("http://worker:3456/"),
+            endpoint.clone(),
+            endpoint.clone(),
+            endpoint,
+        );
+        let meta = handoff.meta(&record).unwrap();
+
+        assert_eq!(meta.comm_r.to_string(), sealed_cid);
+        // CommD of an empty 2KiB sector.
+        assert_eq!(
+            meta.comm_d.to_string(),
+            "baga6ea4seaqpy7usqklokfx2vxuynmupslkeutzexe2uqurdg5vhtebhxqmpqmy"
+        );
+        assert_eq!(zero_commitment(128)[..4], [0x37, 0x31, 0xbb, 0x99]);
+        assert_eq!(meta.pieces.len(), 1);
+        assert_eq!(meta.pieces[0].piece.size.0, 2048);
+        assert_eq!(meta.ticket_epoch, 42);
+        assert_eq!(
+            meta.data_cache.url,
+            "http://worker:3456/remote/cache/s-t01000-7"
+        );
+        assert_eq!(
+            meta.data_sealed.url,
+            "http://worker:3456/remote/sealed/s-t01000-7"
+        );
+    }
+}
@@ -0,0 +1,676 @@
+pub mod error;
+pub mod handoff;
+pub mod record;
+
+use std::{collections::HashSet, time::Duration};
+
+use filecoin_spec::{ChainEpoch, Ticket};
+use futures::{stream, StreamExt};
+use job::{
+    sealing::{
+        C1Input, C1Output, C2Input, PC1Input, PC1Output, PC2Input, PC2Output, SealingJob, C1, C2,
+        PC1, PC2,
+    },
+    sector::{SectorEvent, SectorState},
+    JobType,
+};
+use job_client::{JobHttp, JobOutputHttp, SealingJobManagerClient};
+use lotus::{
+    helpers::marshal,
+    types::{
+        chain::{cid::CID, randomness::DomainSeparationTag},
+        miner::Meta,
+    },
+    Address, Lotus,
+};
+use mockall::automock;
+use tracing::Instrument;
+
+pub use error::Error;
+use record::{SectorRecord, SectorRecordStore, SectorSpec};
+
+/// Epochs between a pre-commit landing on chain and its interactive seed.
+pub const PRE_COMMIT_CHALLENGE_DELAY: i64 = 150;
+
+#[derive(Debug, Clone)]
+pub struct OrchestratorConfig {
+    /// How often job outputs and the chain head are polled.
+    pub poll_interval: Duration,
+    /// Sectors driven at the same time by `run`.
+    pub max_concurrent_sectors: usize,
+    /// Epochs after the pre-commit lands on chain before the seed is drawn.
+    pub seed_delay: i64,
+    /// Times a sector's failed jobs are requeued before the sector stays failed.
+    pub max_job_retries: u32,
+    pub max_handoff_attempts: u32,
+}
+
+impl Default for OrchestratorConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(30),
+            max_concurrent_sectors: 16,
+            seed_delay: PRE_COMMIT_CHALLENGE_DELAY,
+            max_job_retries: 3,
+            max_handoff_attempts: 3,
+        }
+    }
+}
+
+/// Builds what Lotus needs to take over a sealed sector, e.g. where its
+/// sealed and cache files are served from.
+#[automock]
+pub trait HandoffMeta: Send + Sync {
+    fn meta(&self, record: &SectorRecord) -> Result<Meta, Error>;
+}
+
+/// Drives sectors from packing to proving: PC1 and PC2 through the job
+/// manager, the seed from the chain, C1 and C2, then the handoff to Lotus.
+pub struct Orchestrator<Client, LotusT, Store, Handoff> {
+    client: Client,
+    lotus: LotusT,
+    store: Store,
+    handoff: Handoff,
+    config: OrchestratorConfig,
+}
+
+/// Lotus encodes CIDs as `{"/": "<cid>"}`.
+fn cid_string(cid: &CID) -> Option<String> {
+    cid.get("/").cloned()
+}
+
+fn missing(record: &SectorRecord, missing: &'static str) -> Error {
+    Error::MissingData {
+        state: record.state,
+        missing,
+    }
+}
+
+impl<Client, LotusT, Store, Handoff> Orchestrator<Client, LotusT, Store, Handoff>
+where
+    Client: SealingJobManagerClient,
+    LotusT: Lotus + Send + Sync,
+    Store: SectorRecordStore,
+    Handoff: HandoffMeta,
+{
+    pub fn new(
+        client: Client,
+        lotus: LotusT,
+        store: Store,
+        handoff: Handoff,
+        config: OrchestratorConfig,
+    ) -> Self {
+        Self {
+            client,
+            lotus,
+            store,
+            handoff,
+            config,
+        }
+    }
+
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Starts tracking a sector, a no-op when it's already tracked.
+    pub async fn add_sector(&self, spec: SectorSpec) -> Result<(), Error> {
+        if self
+            .store
+            .load(spec.storage_provider_id, spec.sector_id)
+            .await?
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        self.store.save(&SectorRecord::new(spec)).await
+    }
+
+    /// Drives every stored sector that isn't done yet, at most
+    /// `max_concurrent_sectors` at a time, and returns their final records.
+    /// A sector that hits an error keeps its state and is picked up by the
+    /// next run.
+    pub async fn run(&self) -> Result<Vec<SectorRecord>, Error> {
+        let records = self.store.load_all().await?;
+        // Failed sectors are driven too, `drive` retries the failed job or
+        // handoff if it has retries left and returns right away otherwise.
+        let active = records
+            .into_iter()
+            .filter(|record| !record.state.is_terminal());
+
+        Ok(stream::iter(active)
+            .map(|mut record| {
+                // Jobs added and the handoff all belong to this span's trace.
+                let span = tracing::info_span!(
+                    "sector",
+                    storage_provider_id = record.spec.storage_provider_id.0,
+                    sector_id = record.spec.sector_id.0,
+                );
+                async move {
+                    if let Err(err) = self.drive(&mut record).await {
+                        tracing::error!(
+                            "Sector {} of storage provider {} stopped in {}: {}",
+                            record.spec.sector_id.0,
+                            record.spec.storage_provider_id.0,
+                            record.state,
+                            err
+                        );
+                        record.last_error = Some(err.to_string());
+                        if let Err(err) = self.store.save(&record).await {
+                            tracing::error!("Failed to save sector record: {}", err);
+                        }
+                    }
+                    record
+                }
+                .instrument(span)
+            })
+            .buffer_unordered(self.config.max_concurrent_sectors.max(1))
+            .collect()
+            .await)
+    }
+
+    /// Drives one sector until it's proving, aborted, or failed for good.
+    pub async fn drive(&self, record: &mut SectorRecord) -> Result<(), Error> {
+        // Jobs already added by this call, adding is idempotent but not free.
+        let mut added = HashSet::new();
+
+        loop {
+            let event = match record.state {
+                SectorState::Packing => self.pack(record).await?,
+                SectorState::PreCommit1 => {
+                    self.step(record, &mut added, pc1, |record, output| {
+                        record.pc1_output = Some(output)
+                    })
+                    .await?
+                }
+                SectorState::PreCommit2 => {
+                    self.step(record, &mut added, pc2, |record, output| {
+                        record.pc2_output = Some(output)
+                    })
+                    .await?
+                }
+                SectorState::PreCommitting => self.wait_pre_commit(record).await?,
+                SectorState::WaitSeed => self.wait_seed(record).await?,
+                SectorState::Commit1 => {
+                    self.step(record, &mut added, c1, |record, output| {
+                        record.c1_output = Some(output)
+                    })
+                    .await?
+                }
+                SectorState::Commit2 => {
+                    self.step(record, &mut added, c2, |record, output| {
+                        record.c2_output = Some(output)
+                    })
+                    .await?
+                }
+                SectorState::PreCommit1Failed
+                | SectorState::PreCommit2Failed
+                | SectorState::Commit1Failed
+                | SectorState::Commit2Failed
+                    if record.retryable && record.job_retries < self.config.max_job_retries =>
+                {
+                    Some(self.retry_job(record).await?)
+                }
+                SectorState::Handoff => Some(self.hand_off(record).await?),
+                SectorState::HandoffFailed
+                    if record.handoff_attempts < self.config.max_handoff_attempts =>
+                {
+                    tokio::time::sleep(self.config.poll_interval).await;
+                    Some(SectorEvent::Retry)
+                }
+                _ => return Ok(()),
+            };
+
+            match event {
+                Some(event) => {
+                    let state = record.state.next(event)?;
+                    tracing::info!(
+                        "Sector {} of storage provider {}: {} -> {}",
+                        record.spec.sector_id.0,
+                        record.spec.storage_provider_id.0,
+                        record.state,
+                        state
+                    );
+                    record.state = state;
+                    self.store.save(record).await?;
+                }
+                None => tokio::time::sleep(self.config.poll_interval).await,
+            }
+        }
+    }
+
+    async fn pack(&self, record: &mut SectorRecord) -> Result<Option<SectorEvent>, Error> {
+        // The ticket is saved before PC1 is added, so a restart reuses it.
+        if record.ticket.is_none() {
+            let ticket = self
+                .client
+                .generate_ticket(record.spec.storage_provider_id)
+                .await?;
+            record.ticket = Some(ticket);
+            self.store.save(record).await?;
+        }
+
+        Ok(Some(SectorEvent::Packed))
+    }
+
+    /// Adds the state's job unless this call already did, then checks for its output.
+    async fn step<SealingJobT>(
+        &self,
+        record: &mut SectorRecord,
+        added: &mut HashSet<JobType>,
+        job: fn(&SectorRecord) -> Result<SealingJobT, Error>,
+        store_output: fn(&mut SectorRecord, SealingJobT::Output),
+    ) -> Result<Option<SectorEvent>, Error>
+    where
+        SealingJobT: SealingJob + Into<JobHttp> + 'static,
+        SealingJobT::Output: TryFrom<JobOutputHttp, Error = job_client::Error>,
+    {
+        let job_type = SealingJobT::job_type();
+        let (storage_provider_id, sector_id) = record.key();
+
+        if !added.contains(&job_type) {
+            self.client.add_job(job(record)?).await?;
+            added.insert(job_type);
+        }
+
+        let output = self
+            .client
+            .get_job_output::<SealingJobT>(storage_provider_id, sector_id)
+            .await?;
+
+        Ok(match output.map(|output| output.0) {
+            None => None,
+            Some(Ok(output)) => {
+                store_output(record, output);
+                Some(SectorEvent::JobDone(job_type))
+            }
+            Some(Err(failure)) => {
+                record.retryable = failure.retryable;
+                record.last_error = Some(failure.details);
+                Some(SectorEvent::JobFailed(job_type))
+            }
+        })
+    }
+
+    /// Requeues the job the sector failed on, it's computed again from the
+    /// same input.
+    async fn retry_job(&self, record: &mut SectorRecord) -> Result<SectorEvent, Error> {
+        let job_type = match record.state {
+            SectorState::PreCommit1Failed => JobType::PC1,
+            SectorState::PreCommit2Failed => JobType::PC2,
+            SectorState::Commit1Failed => JobType::C1,
+            _ => JobType::C2,
+        };
+        let (storage_provider_id, sector_id) = record.key();
+        self.client
+            .retry_any_job(storage_provider_id, sector_id, job_type)
+            .await?;
+        record.job_retries += 1;
+
+        Ok(SectorEvent::Retry)
+    }
+
+    async fn wait_pre_commit(
+        &self,
+        record: &mut SectorRecord,
+    ) -> Result<Option<SectorEvent>, Error> {
+        let head = self.lotus.chain_head().await?;
+        let miner = Address::new_id(record.spec.storage_provider_id.0);
+        let info = match self
+            .lotus
+            .state_sector_precommit_info(miner.to_string(), record.spec.sector_id.0, head.Cids)
+            .await
+        {
+            Ok(info) => info,
+            // Lotus errors until the pre-commit is on chain.
+            Err(err) => {
+                tracing::debug!(
+                    "No pre-commit on chain for sector {}: {}",
+                    record.spec.sector_id.0,
+                    err
+                );
+                return Ok(None);
+            }
+        };
+
+        record.sealed_cid = cid_string(&info.Info.SealedCID);
+        record.unsealed_cid = info.Info.UnsealedCid.as_ref().and_then(cid_string);
+        record.seed_epoch = Some(ChainEpoch(info.PreCommitEpoch + self.config.seed_delay));
+        Ok(Some(SectorEvent::PreCommitLanded))
+    }
+
+    async fn wait_seed(&self, record: &mut SectorRecord) -> Result<Option<SectorEvent>, Error> {
+        let seed_epoch = record
+            .seed_epoch
+            .ok_or_else(|| missing(record, "seed epoch"))?;
+        let head = self.lotus.chain_head().await?;
+        if head.Height < seed_epoch.0 {
+            return Ok(None);
+        }
+
+        // Lotus draws the seed with the CBOR encoded miner address as entropy.
+        let entropy =
+            marshal::marshal_cbor_address(&Address::new_id(record.spec.storage_provider_id.0));
+        let randomness = self
+            .lotus
+            .state_get_randomness_from_beacon(
+                DomainSeparationTag::InteractiveSealChallengeSeed,
+                seed_epoch.0,
+                &entropy,
+                head.Cids,
+            )
+            .await?;
+        let seed = randomness
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::InvalidSeed(randomness.len()))?;
+        record.seed = Some(Ticket(seed));
+
+        Ok(Some(SectorEvent::SeedReady))
+    }
+
+    async fn hand_off(&self, record: &mut SectorRecord) -> Result<SectorEvent, Error> {
+        let meta = self.handoff.meta(record)?;
+
+        match self.lotus.sector_receive(meta).await {
+            Ok(()) => Ok(SectorEvent::HandedOff),
+            Err(err) => {
+                tracing::warn!("Lotus refused sector {}: {}", record.spec.sector_id.0, err);
+                record.handoff_attempts += 1;
+                record.last_error = Some(err.to_string());
+                Ok(SectorEvent::HandoffFailed)
+            }
+        }
+    }
+}
+
+fn pc1(record: &SectorRecord) -> Result<PC1, Error> {
+    let ticket = record
+        .ticket
+        .clone()
+        .ok_or_else(|| missing(record, "ticket"))?;
+
+    Ok(PC1 {
+        input: PC1Input {
+            registered_proof: record.spec.registered_proof,
+            storage_provider_id: record.spec.storage_provider_id,
+            sector_id: record.spec.sector_id,
+            ticket: ticket.ticket,
+            ticket_epoch: ticket.ticket_epoch,
+        },
+    })
+}
+
+fn pc2(record: &SectorRecord) -> Result<PC2, Error> {
+    let pc1_output = record
+        .pc1_output
+        .as_ref()
+        .ok_or_else(|| missing(record, "PC1 output"))?;
+
+    Ok(PC2 {
+        input: PC2Input {
+            pc1_output: PC1Output(pc1_output.0.clone()),
+            sector_id: record.spec.sector_id,
+            storage_provider_id: record.spec.storage_provider_id,
+            registered_proof: record.spec.registered_proof,
+        },
+    })
+}
+
+fn c1(record: &SectorRecord) -> Result<C1, Error> {
+    let pc2_output = record
+        .pc2_output
+        .as_ref()
+        .ok_or_else(|| missing(record, "PC2 output"))?;
+    let ticket = record
+        .ticket
+        .clone()
+        .ok_or_else(|| missing(record, "ticket"))?;
+    let seed = record.seed.clone().ok_or_else(|| missing(record, "seed"))?;
+
+    Ok(C1 {
+        input: C1Input {
+            pc2_output: PC2Output(pc2_output.0.clone()),
+            storage_provider_id: record.spec.storage_provider_id,
+            sector_id: record.spec.sector_id,
+            ticket: ticket.ticket,
+            seed,
+            piece_infos: record.spec.piece_infos.clone(),
+            registered_proof: record.spec.registered_proof,
+        },
+    })
+}
+
+fn c2(record: &SectorRecord) -> Result<C2, Error> {
+    let c1_output = record
+        .c1_output
+        .as_ref()
+        .ok_or_else(|| missing(record, "C1 output"))?;
+
+    Ok(C2 {
+        input: C2Input {
+            c1_output: C1Output(c1_output.0.clone()),
+            storage_provider_id: record.spec.storage_provider_id,
+            sector_id: record.spec.sector_id,
+            registered_proof: record.spec.registered_proof,
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use filecoin_spec::{RegisteredSealProof, SectorId, StorageProviderId, Ticket as SpecTicket};
+    use job::sealing::{C2Output, PC1Output, PC2Output};
+    use job_client::{
+        failure::JobFailure, in_memory::InMemorySealingJobManager,
+        sector_storage::http::HttpSectorStore,
+    };
+    use lotus::{
+        types::chain::{
+            chain_head::ChainHead,
+            sector::{SectorPreCommitInfo, SectorPreCommitOnChainInfo},
+        },
+        MockLotus,
+    };
+    use url::Url;
+
+    use super::*;
+    use crate::{handoff::LotusHandoff, record::MemoryRecordStore};
+
+    /// Computes every job it can claim with a dummy output, except the first
+    /// PC2 which fails with `failure`.
+    async fn fake_worker(manager: InMemorySealingJobManager, failure: JobFailure) {
+        let job_types = [JobType::PC1, JobType::PC2, JobType::C1, JobType::C2];
+        let mut failed = false;
+        loop {
+            let Ok(jobs) = manager.request_any_jobs(&job_types, 1).await else {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                continue;
+            };
+
+            for job in jobs.into_iter().map(|envelope| envelope.job) {
+                if job.job_type() == JobType::PC2 && !failed {
+                    failed = true;
+                    manager
+                        .fail_any_job(
+                            job.storage_provider_id(),
+                            job.sector_id(),
+                            JobType::PC2,
+                            failure.clone(),
+                        )
+                        .await
+                        .unwrap();
+                    continue;
+                }
+                let output = match job.job_type() {
+                    JobType::PC1 => JobOutputHttp::PC1(PC1Output(vec![1])),
+                    JobType::PC2 => JobOutputHttp::PC2(PC2Output(vec![2])),
+                    JobType::C1 => JobOutputHttp::C1(C1Output(vec![3])),
+                    _ => JobOutputHttp::C2(C2Output(vec![4])),
+                };
+                manager
+                    .submit_any_job_output(job.storage_provider_id(), job.sector_id(), output)
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
+    fn spec(sector_id: u64) -> SectorSpec {
+        SectorSpec {
+            storage_provider_id: StorageProviderId(1000),
+            sector_id: SectorId(sector_id),
+            registered_proof: RegisteredSealProof::StackedDrg2KiBV1,
+            piece_infos: vec![],
+        }
+    }
+
+    fn handoff() -> LotusHandoff {
+        let endpoint = Url::parse("http://orchestrator/").unwrap();
+        LotusHandoff::new(
+            HttpSectorStore::new("http://worker:3456"),
+            endpoint.clone(),
+            endpoint.clone(),
+            endpoint,
+        )
+    }
+
+    fn config() -> OrchestratorConfig {
+        OrchestratorConfig {
+            poll_interval: Duration::from_millis(5),
+            max_concurrent_sectors: 2,
+            seed_delay: 10,
+            max_job_retries: 1,
+            max_handoff_attempts: 3,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_seals_sectors() {
+        // A single attempt, so the transient failure reaches the orchestrator.
+        let manager = InMemorySealingJobManager::new().with_max_attempts(1);
+        let worker = tokio::spawn(fake_worker(
+            manager.clone(),
+            JobFailure::transient("worker restarted"),
+        ));
+
+        let mut lotus = MockLotus::new();
+        lotus.expect_chain_head().returning(|| {
+            Ok(ChainHead {
+                Height: 150,
+                Blocks: vec![],
+                Cids: vec![],
+            })
+        });
+        let sealed_cid = fvm_shared::commcid::replica_commitment_v1_to_cid(&[3; 32])
+            .unwrap()
+            .to_string();
+        let chain_cid = sealed_cid.clone();
+        lotus
+            .expect_state_sector_precommit_info()
+            .returning(move |_, sector_number, _| {
+                Ok(SectorPreCommitOnChainInfo {
+                    Info: SectorPreCommitInfo {
+                        SealProof: 5,
+                        SectorNumber: sector_number,
+                        SealedCID: [("/".to_string(), chain_cid.clone())].into(),
+                        SealRandEpoch: 0,
+                        DealIDs: None,
+                        Expiration: 0,
+                        UnsealedCid: None,
+                    },
+                    PreCommitDeposit: "0".to_string(),
+                    PreCommitEpoch: 90,
+                })
+            });
+        lotus
+            .expect_state_get_randomness_from_beacon()
+            .returning(|_, epoch, entropy, _| {
+                assert_eq!(epoch, 100);
+                // CBOR byte string of the id address t01000.
+                assert_eq!(entropy, &[0x43, 0x00, 0xe8, 0x07]);
+                Ok(vec![7; 32])
+            });
+        // Lotus refuses the first handoff, which is retried.
+        let handoffs = AtomicUsize::new(0);
+        lotus.expect_sector_receive().times(3).returning(move |_| {
+            match handoffs.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(serde_json::from_str::<()>("").unwrap_err().into()),
+                _ => Ok(()),
+            }
+        });
+
+        let orchestrator = Orchestrator::new(
+            manager,
+            lotus,
+            MemoryRecordStore::new(),
+            handoff(),
+            config(),
+        );
+        orchestrator.add_sector(spec(1)).await.unwrap();
+        orchestrator.add_sector(spec(2)).await.unwrap();
+        orchestrator.add_sector(spec(1)).await.unwrap();
+
+        let mut records = orchestrator.run().await.unwrap();
+        records.sort_by_key(|record| record.spec.sector_id.0);
+        assert_eq!(records.len(), 2);
+        for record in &records {
+            assert_eq!(record.state, SectorState::Proving);
+            assert_eq!(record.seed, Some(SpecTicket([7; 32])));
+            assert_eq!(record.c2_output.as_ref().unwrap().0, vec![4]);
+        }
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| record.handoff_attempts)
+                .sum::<u32>(),
+            1
+        );
+        assert_eq!(
+            records.iter().map(|record| record.job_retries).sum::<u32>(),
+            1
+        );
+
+        // Everything is saved, a second run has nothing left to do.
+        assert!(orchestrator.run().await.unwrap().is_empty());
+        let saved = orchestrator
+            .store()
+            .load(StorageProviderId(1000), SectorId(2))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved.state, SectorState::Proving);
+        assert_eq!(saved.sealed_cid, Some(sealed_cid));
+
+        worker.abort();
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_does_not_retry_invalid_jobs() {
+        let manager = InMemorySealingJobManager::new();
+        let worker = tokio::spawn(fake_worker(
+            manager.clone(),
+            JobFailure::invalid_input("bad PC1 output"),
+        ));
+
+        let orchestrator = Orchestrator::new(
+            manager,
+            MockLotus::new(),
+            MemoryRecordStore::new(),
+            handoff(),
+            config(),
+        );
+        orchestrator.add_sector(spec(1)).await.unwrap();
+
+        let records = orchestrator.run().await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].state, SectorState::PreCommit2Failed);
+        assert_eq!(records[0].job_retries, 0);
+        assert_eq!(records[0].last_error.as_deref(), Some("bad PC1 output"));
+
+        worker.abort();
+    }
+}
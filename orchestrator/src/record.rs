@@ -0,0 +1,201 @@
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+
+use async_trait::async_trait;
+use filecoin_spec::{
+    ChainEpoch, PieceInfo, RegisteredSealProof, SectorId, StorageProviderId, Ticket,
+};
+use job::{
+    sealing::{C1Output, C2Output, PC1Output, PC2Output},
+    sector::SectorState,
+};
+use job_client::sector::GeneratedTicket;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// What a sector is sealed from, given when it's added to the orchestrator.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SectorSpec {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    pub registered_proof: RegisteredSealProof,
+    pub piece_infos: Vec<PieceInfo>,
+}
+
+/// Everything the orchestrator knows about a sector, saved after every step
+/// so a restarted orchestrator resumes where it stopped.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SectorRecord {
+    pub spec: SectorSpec,
+    pub state: SectorState,
+    pub ticket: Option<GeneratedTicket>,
+    pub pc1_output: Option<PC1Output>,
+    pub pc2_output: Option<PC2Output>,
+    /// Epoch the interactive seed is drawn at.
+    pub seed_epoch: Option<ChainEpoch>,
+    /// CIDs the pre-commit landed with, there's no unsealed CID for committed
+    /// capacity sectors.
+    #[serde(default)]
+    pub sealed_cid: Option<String>,
+    #[serde(default)]
+    pub unsealed_cid: Option<String>,
+    pub seed: Option<Ticket>,
+    pub c1_output: Option<C1Output>,
+    pub c2_output: Option<C2Output>,
+    /// Jobs requeued after they failed for good.
+    #[serde(default)]
+    pub job_retries: u32,
+    #[serde(default)]
+    pub handoff_attempts: u32,
+    pub last_error: Option<String>,
+    /// Whether the job the sector failed on is worth computing again.
+    #[serde(default)]
+    pub retryable: bool,
+}
+
+impl SectorRecord {
+    pub fn new(spec: SectorSpec) -> Self {
+        Self {
+            spec,
+            state: SectorState::Packing,
+            ticket: None,
+            pc1_output: None,
+            pc2_output: None,
+            seed_epoch: None,
+            sealed_cid: None,
+            unsealed_cid: None,
+            seed: None,
+            c1_output: None,
+            c2_output: None,
+            job_retries: 0,
+            handoff_attempts: 0,
+            last_error: None,
+            retryable: false,
+        }
+    }
+
+    pub fn key(&self) -> (StorageProviderId, SectorId) {
+        (self.spec.storage_provider_id, self.spec.sector_id)
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait SectorRecordStore: Send + Sync {
+    async fn save(&self, record: &SectorRecord) -> Result<(), Error>;
+
+    async fn load(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Option<SectorRecord>, Error>;
+
+    async fn load_all(&self) -> Result<Vec<SectorRecord>, Error>;
+}
+
+/// Records are only kept for the life of the process, for tests.
+#[derive(Default)]
+pub struct MemoryRecordStore {
+    // Records hold job outputs which aren't `Clone`.
+    records: Mutex<HashMap<(StorageProviderId, SectorId), String>>,
+}
+
+impl MemoryRecordStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SectorRecordStore for MemoryRecordStore {
+    async fn save(&self, record: &SectorRecord) -> Result<(), Error> {
+        let json = serde_json::to_string(record)?;
+        self.records.lock().unwrap().insert(record.key(), json);
+        Ok(())
+    }
+
+    async fn load(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Option<SectorRecord>, Error> {
+        let records = self.records.lock().unwrap();
+        records
+            .get(&(storage_provider_id, sector_id))
+            .map(|json| Ok(serde_json::from_str(json)?))
+            .transpose()
+    }
+
+    async fn load_all(&self) -> Result<Vec<SectorRecord>, Error> {
+        let records = self.records.lock().unwrap();
+        records
+            .values()
+            .map(|json| Ok(serde_json::from_str(json)?))
+            .collect()
+    }
+}
+
+/// One JSON file per sector in `dir`, replaced atomically on every save.
+pub struct FileRecordStore {
+    dir: PathBuf,
+}
+
+impl FileRecordStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, storage_provider_id: StorageProviderId, sector_id: SectorId) -> PathBuf {
+        self.dir.join(format!(
+            "s-t0{}-{}.json",
+            storage_provider_id.0, sector_id.0
+        ))
+    }
+}
+
+#[async_trait]
+impl SectorRecordStore for FileRecordStore {
+    async fn save(&self, record: &SectorRecord) -> Result<(), Error> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let (storage_provider_id, sector_id) = record.key();
+        let path = self.path(storage_provider_id, sector_id);
+        let tmp = path.with_extension("json.tmp");
+
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(record)?).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+
+        Ok(())
+    }
+
+    async fn load(
+        &self,
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> Result<Option<SectorRecord>, Error> {
+        match tokio::fs::read(self.path(storage_provider_id, sector_id)).await {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn load_all(&self) -> Result<Vec<SectorRecord>, Error> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut records = vec![];
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_some_and(|ext| ext == "json") {
+                records.push(serde_json::from_slice(
+                    &tokio::fs::read(entry.path()).await?,
+                )?);
+            }
+        }
+
+        Ok(records)
+    }
+}
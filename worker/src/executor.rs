@@ -0,0 +1,117 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use filecoin_spec::{SectorId, StorageProviderId};
+use job::sealing::{C1Output, C2Output, PC1Output, PC2Output, PCOutput, SealingJob};
+use job_client::{failure::JobFailure, Error, JobHttp, JobOutputHttp, JobProgress};
+use tokio::sync::watch;
+
+/// What a job holds a slot of while it runs, see [`crate::WorkerConfig::slots`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Cpu,
+    Gpu,
+}
+
+/// Handed to an executor along with its job.
+pub struct JobContext {
+    pub storage_provider_id: StorageProviderId,
+    pub sector_id: SectorId,
+    progress: watch::Sender<Option<JobProgress>>,
+}
+
+impl JobContext {
+    pub(crate) fn new(
+        storage_provider_id: StorageProviderId,
+        sector_id: SectorId,
+    ) -> (Self, watch::Receiver<Option<JobProgress>>) {
+        let (progress, receiver) = watch::channel(None);
+        let context = Self {
+            storage_provider_id,
+            sector_id,
+            progress,
+        };
+
+        (context, receiver)
+    }
+
+    /// Sent to the job manager with the next heartbeat.
+    pub fn report_progress(&self, progress: JobProgress) {
+        self.progress.send_replace(Some(progress));
+    }
+}
+
+/// The compute part of a worker for one type of job. Fetching, reporting and
+/// shutdown are left to [`crate::Worker`].
+#[async_trait]
+pub trait JobExecutor<SealingJobT: SealingJob>: Send + Sync {
+    fn resource(&self) -> Resource;
+
+    async fn execute(
+        &self,
+        job: SealingJobT,
+        context: &JobContext,
+    ) -> Result<SealingJobT::Output, JobFailure>;
+}
+
+/// [`JobExecutor`] with the job type erased, so executors of every type can
+/// be kept together.
+#[async_trait]
+pub(crate) trait AnyExecutor: Send + Sync {
+    fn resource(&self) -> Resource;
+
+    async fn execute(
+        &self,
+        job: JobHttp,
+        context: &JobContext,
+    ) -> Result<JobOutputHttp, JobFailure>;
+}
+
+pub(crate) struct TypedExecutor<SealingJobT, Executor> {
+    executor: Executor,
+    job: PhantomData<fn() -> SealingJobT>,
+}
+
+impl<SealingJobT, Executor> TypedExecutor<SealingJobT, Executor> {
+    pub(crate) fn new(executor: Executor) -> Self {
+        Self {
+            executor,
+            job: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<SealingJobT, Executor> AnyExecutor for TypedExecutor<SealingJobT, Executor>
+where
+    SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static,
+    SealingJobT::Output: Into<JobOutputHttp>,
+    Executor: JobExecutor<SealingJobT>,
+{
+    fn resource(&self) -> Resource {
+        self.executor.resource()
+    }
+
+    async fn execute(
+        &self,
+        job: JobHttp,
+        context: &JobContext,
+    ) -> Result<JobOutputHttp, JobFailure> {
+        let job = SealingJobT::try_from(job).map_err(|err| {
+            JobFailure::invalid_input(err.to_string()).with_code("WRONG_JOB_TYPE")
+        })?;
+
+        self.executor.execute(job, context).await.map(Into::into)
+    }
+}
+
+/// Outputs aren't `Clone`, but a submission may have to be sent again.
+pub(crate) fn copy_output(output: &JobOutputHttp) -> JobOutputHttp {
+    match output {
+        JobOutputHttp::PC1(output) => JobOutputHttp::PC1(PC1Output(output.0.clone())),
+        JobOutputHttp::PC2(output) => JobOutputHttp::PC2(PC2Output(output.0.clone())),
+        JobOutputHttp::C1(output) => JobOutputHttp::C1(C1Output(output.0.clone())),
+        JobOutputHttp::C2(output) => JobOutputHttp::C2(C2Output(output.0.clone())),
+        JobOutputHttp::PC(output) => JobOutputHttp::PC(PCOutput(output.0.clone())),
+    }
+}
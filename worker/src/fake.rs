@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use job::{
+    sealing::{
+        C1Output, C2Output, PC1Output, PC2Output, PCOutput, SealingJob, C1, C2, PC, PC1, PC2,
+    },
+    JobType,
+};
+use job_client::{dispatch::JobDispatcher, failure::JobFailure};
+use rand::Rng;
+
+use crate::{JobContext, JobExecutor, Resource};
+
+/// Output a [`FakeExecutor`] produces for `job`, the blake3 hash of its domain id.
+pub fn fake_output<SealingJobT: SealingJob>(job: &SealingJobT) -> SealingJobT::Output {
+    blake3::hash(job.domain_id().as_bytes())
+        .as_bytes()
+        .to_vec()
+        .into()
+}
+
+/// Runs any sealing job without the proofs library or a GPU, for dry runs
+/// and integration tests. Outputs are dummies derived from the job, see
+/// [`fake_output`], so the same job always gets the same output.
+#[derive(Debug, Clone)]
+pub struct FakeExecutor {
+    latency: Duration,
+    failure_rate: f64,
+    resource: Resource,
+}
+
+impl Default for FakeExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeExecutor {
+    pub fn new() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            failure_rate: 0.0,
+            resource: Resource::Cpu,
+        }
+    }
+
+    /// How long every job takes.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Fraction of jobs failed with a transient failure, in the `0.0..=1.0` range.
+    pub fn with_failure_rate(mut self, failure_rate: f64) -> Self {
+        self.failure_rate = failure_rate;
+        self
+    }
+
+    pub fn with_resource(mut self, resource: Resource) -> Self {
+        self.resource = resource;
+        self
+    }
+
+    async fn run<SealingJobT: SealingJob>(
+        &self,
+        job: SealingJobT,
+    ) -> Result<SealingJobT::Output, JobFailure> {
+        tokio::time::sleep(self.latency).await;
+
+        if self.failure_rate > 0.0 && rand::thread_rng().gen_bool(self.failure_rate.min(1.0)) {
+            return Err(
+                JobFailure::transient(format!("Fake failure of {}", job.domain_id()))
+                    .with_code("FAKE_FAILURE"),
+            );
+        }
+
+        Ok(fake_output(&job))
+    }
+}
+
+#[async_trait]
+impl<SealingJobT: SealingJob + 'static> JobExecutor<SealingJobT> for FakeExecutor {
+    fn resource(&self) -> Resource {
+        self.resource
+    }
+
+    async fn execute(
+        &self,
+        job: SealingJobT,
+        _context: &JobContext,
+    ) -> Result<SealingJobT::Output, JobFailure> {
+        self.run(job).await
+    }
+}
+
+#[async_trait]
+impl JobDispatcher for FakeExecutor {
+    fn job_types(&self) -> Vec<JobType> {
+        vec![
+            JobType::PC1,
+            JobType::PC2,
+            JobType::C1,
+            JobType::C2,
+            JobType::PC,
+        ]
+    }
+
+    async fn pc1(&self, job: PC1) -> Result<PC1Output, JobFailure> {
+        self.run(job).await
+    }
+
+    async fn pc2(&self, job: PC2) -> Result<PC2Output, JobFailure> {
+        self.run(job).await
+    }
+
+    async fn c1(&self, job: C1) -> Result<C1Output, JobFailure> {
+        self.run(job).await
+    }
+
+    async fn c2(&self, job: C2) -> Result<C2Output, JobFailure> {
+        self.run(job).await
+    }
+
+    async fn pc(&self, job: PC) -> Result<PCOutput, JobFailure> {
+        self.run(job).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use filecoin_spec::{RegisteredSealProof, SectorId, StorageProviderId, Ticket};
+    use job::{
+        sealing::{C1Input, C2Input, PC2Input},
+        test_utils::pc1,
+    };
+    use job_client::{
+        dispatch::process_jobs, in_memory::InMemorySealingJobManager, JobOutput,
+        SealingJobManagerClient,
+    };
+
+    use super::*;
+    use crate::{test::worker_info, Worker, WorkerConfig};
+
+    const SP: StorageProviderId = StorageProviderId(1000);
+    const PROOF: RegisteredSealProof = RegisteredSealProof::StackedDrg2KiBV1;
+
+    async fn output<SealingJobT: SealingJob + 'static>(
+        manager: &InMemorySealingJobManager,
+        sector_id: SectorId,
+    ) -> SealingJobT::Output
+    where
+        SealingJobT::Output: TryFrom<job_client::JobOutputHttp, Error = job_client::Error>,
+    {
+        loop {
+            if let Some(JobOutput(output)) = manager
+                .get_job_output::<SealingJobT>(SP, sector_id)
+                .await
+                .unwrap()
+            {
+                return output.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    /// Seals a sector from PC1 to C2, feeding each output into the next job.
+    #[tokio::test]
+    async fn test_fake_sealing_flow() {
+        let manager = InMemorySealingJobManager::new();
+        let sector_id = SectorId(1);
+        let worker = Worker::new(
+            manager.clone(),
+            worker_info(&[JobType::PC1, JobType::PC2, JobType::C1, JobType::C2]),
+            WorkerConfig {
+                poll_interval: Duration::from_millis(5),
+                ..Default::default()
+            },
+        )
+        .with_fake_executors(FakeExecutor::new().with_latency(Duration::from_millis(1)));
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let worker = tokio::spawn(worker.run(async {
+            stopped.await.ok();
+        }));
+
+        let job = pc1(sector_id.0);
+        let expected = fake_output(&job);
+        manager.add_job(job).await.unwrap();
+        let pc1_output = output::<PC1>(&manager, sector_id).await;
+        assert_eq!(pc1_output.0, expected.0);
+
+        manager
+            .add_job(PC2 {
+                input: PC2Input {
+                    pc1_output,
+                    sector_id,
+                    storage_provider_id: SP,
+                    registered_proof: PROOF,
+                },
+            })
+            .await
+            .unwrap();
+        let pc2_output = output::<PC2>(&manager, sector_id).await;
+
+        manager
+            .add_job(C1 {
+                input: C1Input {
+                    pc2_output,
+                    storage_provider_id: SP,
+                    sector_id,
+                    ticket: Ticket([0; 32]),
+                    seed: Ticket([1; 32]),
+                    piece_infos: vec![],
+                    registered_proof: PROOF,
+                },
+            })
+            .await
+            .unwrap();
+        let c1_output = output::<C1>(&manager, sector_id).await;
+
+        let c2 = C2 {
+            input: C2Input {
+                c1_output,
+                storage_provider_id: SP,
+                sector_id,
+                registered_proof: PROOF,
+            },
+        };
+        let expected = fake_output(&c2);
+        manager.add_job(c2).await.unwrap();
+        assert_eq!(output::<C2>(&manager, sector_id).await.0, expected.0);
+
+        stop.send(()).unwrap();
+        worker.await.unwrap().unwrap();
+
+        // Through the job client's dispatcher, every job fails.
+        let manager = InMemorySealingJobManager::new().with_max_attempts(1);
+        manager.add_job(pc1(sector_id.0)).await.unwrap();
+        let failing = FakeExecutor::new().with_failure_rate(1.0);
+        let processed = process_jobs(&manager, &failing, 1).await.unwrap();
+        assert_eq!(processed.processed, 1);
+        assert!(processed.errors.is_empty());
+        let output = manager.get_job_output::<PC1>(SP, sector_id).await.unwrap();
+        assert!(
+            matches!(output, Some(JobOutput(Err(failure))) if failure.code.as_deref() == Some("FAKE_FAILURE"))
+        );
+    }
+}
@@ -0,0 +1,661 @@
+pub mod executor;
+pub mod fake;
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use filecoin_spec::{SectorId, StorageProviderId};
+use job::{
+    sealing::{SealingJob, C1, C2, PC, PC1, PC2},
+    JobType,
+};
+use job_client::{
+    worker::WorkerInfo, Error, JobEnvelope, JobHttp, JobOutputHttp, JobProgress,
+    SealingJobManagerClient,
+};
+use tokio::{
+    sync::{watch, OwnedSemaphorePermit, Semaphore},
+    task::JoinSet,
+};
+use tracing::Instrument;
+
+use executor::{copy_output, AnyExecutor, TypedExecutor};
+pub use executor::{JobContext, JobExecutor, Resource};
+pub use fake::FakeExecutor;
+
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// How long to wait before asking for jobs again when none were claimed.
+    pub poll_interval: Duration,
+    /// Jobs run at the same time per resource, resources without an entry
+    /// get one slot.
+    pub slots: HashMap<Resource, usize>,
+    /// How often progress is reported and cancellation checked for running jobs.
+    pub heartbeat_interval: Duration,
+    /// How long running jobs get to finish on shutdown before they're
+    /// released back to the job manager.
+    pub shutdown_timeout: Duration,
+    /// Attempts at submitting an output or failure before giving up on it.
+    pub report_attempts: u32,
+    /// Backoff before the second attempt, doubled for every attempt after.
+    pub report_backoff: Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            slots: HashMap::new(),
+            heartbeat_interval: Duration::from_secs(30),
+            shutdown_timeout: Duration::from_secs(60),
+            report_attempts: 5,
+            report_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl WorkerConfig {
+    pub fn with_slots(mut self, resource: Resource, slots: usize) -> Self {
+        self.slots.insert(resource, slots);
+        self
+    }
+
+    fn slots(&self, resource: Resource) -> usize {
+        self.slots.get(&resource).copied().unwrap_or(1)
+    }
+}
+
+/// Runs jobs with the registered executors: claims jobs while there are free
+/// slots, sends heartbeats, reports outputs and failures, and shuts down
+/// gracefully.
+pub struct Worker<Client> {
+    client: Client,
+    config: WorkerConfig,
+    info: WorkerInfo,
+    executors: HashMap<JobType, Arc<dyn AnyExecutor>>,
+}
+
+impl<Client> Worker<Client>
+where
+    Client: SealingJobManagerClient + 'static,
+{
+    /// The worker registers with `info` when it starts. Its requests carry
+    /// the worker id from then on, which is what renews its claim leases.
+    pub fn new(client: Client, info: WorkerInfo, config: WorkerConfig) -> Self {
+        Self {
+            client,
+            config,
+            info,
+            executors: HashMap::new(),
+        }
+    }
+
+    /// Runs jobs of type `SealingJobT` with `executor`, replacing the
+    /// executor registered before for the type.
+    pub fn with_executor<SealingJobT, Executor>(mut self, executor: Executor) -> Self
+    where
+        SealingJobT: SealingJob + TryFrom<JobHttp, Error = Error> + 'static,
+        SealingJobT::Output: Into<JobOutputHttp>,
+        Executor: JobExecutor<SealingJobT> + 'static,
+    {
+        self.executors.insert(
+            SealingJobT::job_type(),
+            Arc::new(TypedExecutor::<SealingJobT, _>::new(executor)),
+        );
+        self
+    }
+
+    /// Runs every type of sealing job with `fake`, for dry runs without the
+    /// proofs library.
+    pub fn with_fake_executors(self, fake: FakeExecutor) -> Self {
+        self.with_executor::<PC1, _>(fake.clone())
+            .with_executor::<PC2, _>(fake.clone())
+            .with_executor::<C1, _>(fake.clone())
+            .with_executor::<C2, _>(fake.clone())
+            .with_executor::<PC, _>(fake)
+    }
+
+    /// Runs jobs until `shutdown` resolves, e.g. with [`shutdown_signal`].
+    /// Jobs still running then get `shutdown_timeout` to finish, after that
+    /// they're released so another worker picks them up.
+    pub async fn run(self, shutdown: impl Future<Output = ()>) -> Result<(), Error> {
+        let worker_id = self.client.register_worker(self.info.clone()).await?;
+        tracing::info!(worker_id = %worker_id.0, "Registered worker");
+
+        let mut slots: HashMap<Resource, (Arc<Semaphore>, Vec<JobType>)> = HashMap::new();
+        for (job_type, executor) in &self.executors {
+            let resource = executor.resource();
+            slots
+                .entry(resource)
+                .or_insert_with(|| {
+                    (
+                        Arc::new(Semaphore::new(self.config.slots(resource))),
+                        vec![],
+                    )
+                })
+                .1
+                .push(*job_type);
+        }
+
+        let (release, released) = watch::channel(false);
+        let mut running = JoinSet::new();
+        tokio::pin!(shutdown);
+
+        loop {
+            // Checked before every claim pass, while the queue is busy the
+            // loop never gets to wait below.
+            tokio::select! {
+                biased;
+                _ = &mut shutdown => break,
+                _ = std::future::ready(()) => {}
+            }
+
+            let mut claimed = 0;
+            for (resource, (semaphore, job_types)) in &slots {
+                let free = semaphore.available_permits();
+                if free == 0 {
+                    continue;
+                }
+
+                for envelope in self.claim(*resource, job_types, free).await {
+                    let job = &envelope.job;
+                    let Ok(permit) = semaphore.clone().try_acquire_owned() else {
+                        // More jobs than asked for, another worker can run them.
+                        tracing::warn!(job_type = %job.job_type(), "Releasing surplus job");
+                        release_job(
+                            &self.client,
+                            &self.config,
+                            job.job_type(),
+                            job.storage_provider_id(),
+                            job.sector_id(),
+                        )
+                        .await;
+                        continue;
+                    };
+                    let span = tracing::info_span!(
+                        "job",
+                        job_type = %job.job_type(),
+                        storage_provider_id = job.storage_provider_id().0,
+                        sector_id = job.sector_id().0,
+                    );
+                    job_client::trace::continue_trace(&span, &envelope);
+                    running.spawn(
+                        run_job(
+                            self.client.clone(),
+                            self.config.clone(),
+                            self.executors[&job.job_type()].clone(),
+                            envelope.job,
+                            permit,
+                            released.clone(),
+                        )
+                        .instrument(span),
+                    );
+                    claimed += 1;
+                }
+            }
+
+            if claimed > 0 {
+                continue;
+            }
+
+            tokio::select! {
+                _ = &mut shutdown => break,
+                Some(_) = running.join_next(), if !running.is_empty() => {}
+                _ = tokio::time::sleep(self.config.poll_interval) => {}
+            }
+        }
+
+        tracing::info!(running = running.len(), "Shutting down worker");
+        let finished = tokio::time::timeout(self.config.shutdown_timeout, async {
+            while running.join_next().await.is_some() {}
+        })
+        .await;
+
+        if finished.is_err() {
+            tracing::warn!(running = running.len(), "Releasing unfinished jobs");
+            release.send_replace(true);
+            while running.join_next().await.is_some() {}
+        }
+
+        Ok(())
+    }
+
+    async fn claim(
+        &self,
+        resource: Resource,
+        job_types: &[JobType],
+        count: usize,
+    ) -> Vec<JobEnvelope> {
+        match self.client.request_any_jobs(job_types, count).await {
+            Ok(jobs) => jobs,
+            Err(Error::NotEnoughJobs(_)) => vec![],
+            Err(err) => {
+                tracing::warn!(?resource, "Failed to request jobs: {}", err);
+                vec![]
+            }
+        }
+    }
+}
+
+async fn run_job<Client: SealingJobManagerClient + 'static>(
+    client: Client,
+    config: WorkerConfig,
+    executor: Arc<dyn AnyExecutor>,
+    job: JobHttp,
+    _permit: OwnedSemaphorePermit,
+    mut released: watch::Receiver<bool>,
+) {
+    let job_type = job.job_type();
+    let storage_provider_id = job.storage_provider_id();
+    let sector_id = job.sector_id();
+    let (context, progress) = JobContext::new(storage_provider_id, sector_id);
+
+    let started = Instant::now();
+    tracing::info!("Running job");
+
+    // Beats in its own task, so a slow job manager doesn't hold up the job.
+    let (cancel, mut cancelled) = watch::channel(false);
+    let heartbeat = tokio::spawn(
+        heartbeat(
+            client.clone(),
+            config.heartbeat_interval,
+            job_type,
+            storage_provider_id,
+            sector_id,
+            progress,
+            cancel,
+        )
+        .in_current_span(),
+    );
+
+    let result = tokio::select! {
+        result = executor.execute(job, &context) => Some(result),
+        Ok(()) = cancelled.changed() => {
+            tracing::info!("Job cancelled, stopping it");
+            None
+        }
+        Ok(()) = released.changed() => None,
+    };
+    heartbeat.abort();
+
+    let Some(result) = result else {
+        if *released.borrow() {
+            release_job(&client, &config, job_type, storage_provider_id, sector_id).await;
+        }
+        return;
+    };
+
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(output) => {
+            tracing::info!(elapsed_ms, "Job done");
+            report(&config, "submit output", || {
+                client.submit_any_job_output(storage_provider_id, sector_id, copy_output(&output))
+            })
+            .await;
+        }
+        Err(failure) => {
+            tracing::error!(
+                elapsed_ms,
+                category = ?failure.category,
+                retryable = failure.retryable,
+                "Job failed: {}",
+                failure
+            );
+            report(&config, "fail job", || {
+                client.fail_any_job(storage_provider_id, sector_id, job_type, failure.clone())
+            })
+            .await;
+        }
+    }
+}
+
+/// Beats every `interval` until the job is found cancelled, which is sent on
+/// `cancel`.
+async fn heartbeat<Client: SealingJobManagerClient>(
+    client: Client,
+    interval: Duration,
+    job_type: JobType,
+    storage_provider_id: StorageProviderId,
+    sector_id: SectorId,
+    progress: watch::Receiver<Option<JobProgress>>,
+    cancel: watch::Sender<bool>,
+) {
+    let mut interval = tokio::time::interval(interval);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let progress = progress.borrow().clone();
+        match beat(&client, job_type, storage_provider_id, sector_id, progress).await {
+            Ok(true) => {
+                cancel.send_replace(true);
+                return;
+            }
+            Ok(false) => {}
+            Err(err) => tracing::warn!("Heartbeat failed: {}", err),
+        }
+    }
+}
+
+/// Hands a claimed job back to the job manager without counting the attempt.
+async fn release_job<Client: SealingJobManagerClient>(
+    client: &Client,
+    config: &WorkerConfig,
+    job_type: JobType,
+    storage_provider_id: StorageProviderId,
+    sector_id: SectorId,
+) {
+    report(config, "release job", || {
+        client.release_any_job(storage_provider_id, sector_id, job_type)
+    })
+    .await;
+}
+
+/// Reports the latest progress if there's any and returns whether the job
+/// was cancelled. Both requests carry the worker id, so the job manager sees
+/// the worker as alive.
+async fn beat<Client: SealingJobManagerClient>(
+    client: &Client,
+    job_type: JobType,
+    storage_provider_id: StorageProviderId,
+    sector_id: SectorId,
+    progress: Option<JobProgress>,
+) -> Result<bool, Error> {
+    macro_rules! beat {
+        ($job:ty) => {{
+            if let Some(progress) = progress {
+                client
+                    .report_progress::<$job>(storage_provider_id, sector_id, progress)
+                    .await?;
+            }
+            client
+                .is_job_cancelled::<$job>(storage_provider_id, sector_id)
+                .await
+        }};
+    }
+
+    match job_type {
+        JobType::PC1 => beat!(PC1),
+        JobType::PC2 => beat!(PC2),
+        JobType::C1 => beat!(C1),
+        JobType::C2 => beat!(C2),
+        JobType::PC => beat!(PC),
+    }
+}
+
+/// Sends a report, backing off between attempts. An output that was already
+/// submitted counts as sent.
+async fn report<F, Fut>(config: &WorkerConfig, what: &str, send: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let mut backoff = config.report_backoff;
+    for attempt in 1..=config.report_attempts.max(1) {
+        match send().await {
+            Ok(()) | Err(Error::AlreadySubmitted(_)) => return,
+            Err(err) if attempt < config.report_attempts => {
+                tracing::warn!(attempt, "Failed to {}, retrying: {}", what, err);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(err) => tracing::error!(attempt, "Failed to {}, giving up: {}", what, err),
+        }
+    }
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on unix.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use filecoin_spec::RegisteredSealProof;
+    use job::{
+        sealing::{C1Output, C2Input, C2Output, PC1Output},
+        test_utils::pc1,
+    };
+    use job_client::{
+        failure::JobFailure,
+        history::{JobHistoryEventKind, JobRef},
+        in_memory::InMemorySealingJobManager,
+        JobOutput, JobState,
+    };
+
+    use super::*;
+
+    /// Tracks how many jobs run at the same time.
+    #[derive(Default)]
+    struct Pc1Executor {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl JobExecutor<PC1> for Arc<Pc1Executor> {
+        fn resource(&self) -> Resource {
+            Resource::Cpu
+        }
+
+        async fn execute(&self, job: PC1, context: &JobContext) -> Result<PC1Output, JobFailure> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            context.report_progress(JobProgress::new(job_client::JobStage::Labeling, 50.0));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(PC1Output(vec![job.input.sector_id.0 as u8]))
+        }
+    }
+
+    /// Never finishes, to be released on shutdown.
+    struct StuckC2Executor;
+
+    #[async_trait]
+    impl JobExecutor<C2> for StuckC2Executor {
+        fn resource(&self) -> Resource {
+            Resource::Gpu
+        }
+
+        async fn execute(&self, _job: C2, _context: &JobContext) -> Result<C2Output, JobFailure> {
+            std::future::pending().await
+        }
+    }
+
+    pub(crate) fn worker_info(job_types: &[JobType]) -> WorkerInfo {
+        WorkerInfo {
+            hostname: "worker".to_string(),
+            job_types: job_types.to_vec(),
+            registered_proofs: vec![RegisteredSealProof::StackedDrg2KiBV1],
+            resources: Default::default(),
+            versions: Default::default(),
+        }
+    }
+
+    async fn state<SealingJobT: SealingJob + 'static>(
+        manager: &InMemorySealingJobManager,
+        sector_id: u64,
+    ) -> Option<JobState> {
+        manager
+            .get_job_state::<SealingJobT>(StorageProviderId(1000), SectorId(sector_id))
+            .await
+            .unwrap()
+            .map(|status| status.state)
+    }
+
+    #[tokio::test]
+    async fn test_worker_runs_jobs_and_releases_on_shutdown() {
+        let manager = InMemorySealingJobManager::new();
+        for sector_id in 1..=4 {
+            manager.add_job(pc1(sector_id)).await.unwrap();
+        }
+        manager
+            .add_job(C2 {
+                input: C2Input {
+                    c1_output: C1Output(vec![]),
+                    storage_provider_id: StorageProviderId(1000),
+                    sector_id: SectorId(5),
+                    registered_proof: RegisteredSealProof::StackedDrg2KiBV1,
+                },
+            })
+            .await
+            .unwrap();
+
+        let pc1_executor = Arc::new(Pc1Executor::default());
+        let worker = Worker::new(
+            manager.clone(),
+            worker_info(&[JobType::PC1, JobType::C2]),
+            WorkerConfig {
+                poll_interval: Duration::from_millis(5),
+                heartbeat_interval: Duration::from_millis(5),
+                shutdown_timeout: Duration::from_millis(10),
+                report_backoff: Duration::from_millis(1),
+                ..Default::default()
+            }
+            .with_slots(Resource::Cpu, 2),
+        )
+        .with_executor::<PC1, _>(pc1_executor.clone())
+        .with_executor::<C2, _>(StuckC2Executor);
+
+        let shutdown = {
+            let manager = manager.clone();
+            async move {
+                for sector_id in 1..=4 {
+                    while state::<PC1>(&manager, sector_id).await != Some(JobState::Done) {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                }
+            }
+        };
+        worker.run(shutdown).await.unwrap();
+
+        assert_eq!(pc1_executor.max_running.load(Ordering::SeqCst), 2);
+        let output = manager
+            .get_job_output::<PC1>(StorageProviderId(1000), SectorId(3))
+            .await
+            .unwrap();
+        assert!(matches!(output, Some(JobOutput(Ok(PC1Output(out)))) if out == [3]));
+
+        // The stuck C2 job went back to the queue for another worker.
+        assert_eq!(state::<C2>(&manager, 5).await, Some(JobState::Pending));
+        let history = manager
+            .history(JobRef::Sector {
+                storage_provider_id: StorageProviderId(1000),
+                sector_id: SectorId(5),
+                job_type: JobType::C2,
+            })
+            .unwrap();
+        assert!(history
+            .events
+            .iter()
+            .any(|event| event.kind == JobHistoryEventKind::Released));
+        assert_eq!(manager.request_jobs::<C2>(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_worker_renews_leases_of_long_jobs() {
+        let manager = InMemorySealingJobManager::new().with_lease(Duration::from_millis(50));
+        manager.add_job(pc1(1)).await.unwrap();
+
+        // Runs four times as long as the lease, without reporting progress.
+        // The spare slot keeps the worker claiming, which expires leases.
+        let worker = Worker::new(
+            manager.clone(),
+            worker_info(&[JobType::PC1]),
+            WorkerConfig {
+                poll_interval: Duration::from_millis(5),
+                heartbeat_interval: Duration::from_millis(10),
+                ..Default::default()
+            }
+            .with_slots(Resource::Cpu, 2),
+        )
+        .with_executor::<PC1, _>(FakeExecutor::new().with_latency(Duration::from_millis(200)));
+
+        let key = (StorageProviderId(1000), SectorId(1)).into();
+        let shutdown = {
+            let manager = manager.clone();
+            async move {
+                while manager.status(key, JobType::PC1).map(|status| status.state)
+                    != Some(JobState::Done)
+                {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            }
+        };
+        worker.run(shutdown).await.unwrap();
+
+        let history = manager
+            .history(JobRef::Sector {
+                storage_provider_id: StorageProviderId(1000),
+                sector_id: SectorId(1),
+                job_type: JobType::PC1,
+            })
+            .unwrap();
+        assert!(!history
+            .events
+            .iter()
+            .any(|event| event.kind == JobHistoryEventKind::LeaseExpired));
+    }
+
+    #[tokio::test]
+    async fn test_worker_checks_shutdown_before_claiming() {
+        let manager = InMemorySealingJobManager::new();
+        manager.add_job(pc1(1)).await.unwrap();
+
+        Worker::new(
+            manager.clone(),
+            worker_info(&[JobType::PC1]),
+            WorkerConfig::default(),
+        )
+        .with_executor::<PC1, _>(FakeExecutor::new())
+        .run(std::future::ready(()))
+        .await
+        .unwrap();
+
+        let history = manager
+            .history(JobRef::Sector {
+                storage_provider_id: StorageProviderId(1000),
+                sector_id: SectorId(1),
+                job_type: JobType::PC1,
+            })
+            .unwrap();
+        assert!(!history
+            .events
+            .iter()
+            .any(|event| matches!(event.kind, JobHistoryEventKind::Claimed { .. })));
+    }
+}